@@ -0,0 +1,291 @@
+//! Generates the built-in ISA table from `data/isa.toml` into
+//! `$OUT_DIR/isa_data.rs`, which `src/isa.rs` includes. The generated code
+//! only exists in the build directory, so it cannot drift from the data file;
+//! anything inconsistent in the data fails the build instead.
+
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+use std::path::Path;
+
+const DATA: &str = "data/isa.toml";
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Data {
+    intel_generations: Vec<String>,
+    amd_generations: Vec<String>,
+    features: BTreeMap<String, Introduced>,
+    /// Microarchitecture -> features it lacks despite its place in the list
+    lacks: BTreeMap<String, Vec<String>>,
+    /// Category -> mnemonic families
+    categories: BTreeMap<String, Vec<String>>,
+    refined: Vec<Refined>,
+    isa: Vec<Category>,
+    scalar: Vec<Category>,
+    other: Vec<Category>,
+    x87: X87,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct X87 {
+    mnemonics: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Introduced {
+    intel: Option<String>,
+    amd: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Refined {
+    key: String,
+    cpuid: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Category {
+    key: String,
+    cpuid: String,
+    #[serde(default)]
+    cpuid_overrides: BTreeMap<String, String>,
+    mnemonics: Vec<String>,
+}
+
+/// Same rule as `isa::category`: the longest family `mnemonic` starts with,
+/// with or without its `v` prefix.
+fn category<'a>(families: &'a [(String, &'a str)], mnemonic: &str) -> Option<&'a str> {
+    let base = mnemonic.strip_prefix('v').unwrap_or(mnemonic);
+    families
+        .iter()
+        .filter(|(family, _)| {
+            mnemonic.starts_with(family.as_str()) || base.starts_with(family.as_str())
+        })
+        .max_by_key(|(family, _)| family.len())
+        .map(|(_, category)| *category)
+}
+
+fn families(data: &Data) -> Vec<(String, &str)> {
+    data.categories
+        .iter()
+        .flat_map(|(category, families)| {
+            families
+                .iter()
+                .map(move |family| (family.clone(), category.as_str()))
+        })
+        .collect()
+}
+
+/// The features `pattern` names: itself, or every feature with its prefix
+/// when it ends in `*`.
+fn expand<'a>(data: &'a Data, pattern: &'a str) -> impl Iterator<Item = &'a String> {
+    data.features
+        .keys()
+        .filter(move |feature| match pattern.strip_suffix('*') {
+            Some(prefix) => feature.starts_with(prefix),
+            None => *feature == pattern,
+        })
+}
+
+fn check(data: &Data) -> Result<(), String> {
+    for (feature, introduced) in &data.features {
+        for (name, generations) in [
+            (&introduced.intel, &data.intel_generations),
+            (&introduced.amd, &data.amd_generations),
+        ] {
+            if let Some(name) = name {
+                if !generations.contains(name) {
+                    return Err(format!(
+                        "feature '{feature}' names unknown microarchitecture '{name}'"
+                    ));
+                }
+            }
+        }
+    }
+
+    for (generation, features) in &data.lacks {
+        if !data.intel_generations.contains(generation)
+            && !data.amd_generations.contains(generation)
+        {
+            return Err(format!(
+                "[lacks] names unknown microarchitecture '{generation}'"
+            ));
+        }
+        for pattern in features {
+            if expand(data, pattern).next().is_none() {
+                return Err(format!(
+                    "[lacks] '{generation}' names unknown feature '{pattern}'"
+                ));
+            }
+        }
+    }
+
+    let feature = |key: &str, cpuid: &str| {
+        if data.features.contains_key(cpuid) {
+            Ok(())
+        } else {
+            Err(format!(
+                "'{key}' needs CPUID feature '{cpuid}', which has no [features] entry"
+            ))
+        }
+    };
+    for refined in &data.refined {
+        feature(&refined.key, &refined.cpuid)?;
+    }
+    let families = families(data);
+    let mut seen: HashMap<&str, &str> = HashMap::new();
+    for (family, category) in &families {
+        if let Some(other) = seen.insert(family, category) {
+            return Err(format!(
+                "family '{family}' is in both categories '{other}' and '{category}'"
+            ));
+        }
+    }
+    for category in &data.isa {
+        for mnemonic in &category.mnemonics {
+            if self::category(&families, mnemonic).is_none() {
+                return Err(format!(
+                    "'{mnemonic}' ({}) matches no [categories] family",
+                    category.key
+                ));
+            }
+        }
+    }
+
+    for table in [&data.isa, &data.scalar, &data.other] {
+        let mut owner: HashMap<&str, &str> = HashMap::new();
+        for category in table {
+            feature(&category.key, &category.cpuid)?;
+            for (mnemonic, cpuid) in &category.cpuid_overrides {
+                if !category.mnemonics.contains(mnemonic) {
+                    return Err(format!(
+                        "'{}' overrides the CPUID feature of '{mnemonic}', which it doesn't list",
+                        category.key
+                    ));
+                }
+                feature(mnemonic, cpuid)?;
+            }
+            for mnemonic in &category.mnemonics {
+                if *mnemonic != mnemonic.to_lowercase() {
+                    return Err(format!("mnemonic '{mnemonic}' is not lowercase"));
+                }
+                if let Some(other) = owner.insert(mnemonic, &category.key) {
+                    return Err(format!(
+                        "'{mnemonic}' is listed under both '{other}' and '{}'",
+                        category.key
+                    ));
+                }
+            }
+        }
+    }
+    let mut x87: HashSet<&str> = HashSet::new();
+    for mnemonic in &data.x87.mnemonics {
+        if *mnemonic != mnemonic.to_lowercase() {
+            return Err(format!("mnemonic '{mnemonic}' is not lowercase"));
+        }
+        if !x87.insert(mnemonic) {
+            return Err(format!("x87 mnemonic '{mnemonic}' is listed twice"));
+        }
+    }
+    Ok(())
+}
+
+fn strings<'a>(items: impl IntoIterator<Item = &'a String>) -> String {
+    let quoted: Vec<String> = items.into_iter().map(|item| format!("{item:?}")).collect();
+    format!("&[{}]", quoted.join(", "))
+}
+
+fn categories(out: &mut String, name: &str, table: &[Category]) {
+    let _ = writeln!(out, "const {name}: &[(&str, &str, &[&str])] = &[");
+    for category in table {
+        let _ = writeln!(
+            out,
+            "    ({:?}, {:?}, {}),",
+            category.key,
+            category.cpuid,
+            strings(&category.mnemonics)
+        );
+    }
+    let _ = writeln!(out, "];");
+}
+
+fn generate(data: &Data) -> String {
+    let mut out = format!("// Generated by build.rs from {DATA}. Do not edit.\n\n");
+    let _ = writeln!(
+        out,
+        "const INTEL_GENERATIONS: &[&str] = {};",
+        strings(&data.intel_generations)
+    );
+    let _ = writeln!(
+        out,
+        "const AMD_GENERATIONS: &[&str] = {};",
+        strings(&data.amd_generations)
+    );
+
+    let _ = writeln!(out, "const INTRODUCTIONS: &[Introduction] = &[");
+    for (feature, introduced) in &data.features {
+        let _ = writeln!(
+            out,
+            "    ({feature:?}, {:?}, {:?}),",
+            introduced.intel, introduced.amd
+        );
+    }
+    let _ = writeln!(out, "];");
+
+    let _ = writeln!(out, "const LACKS: &[(&str, &[&str])] = &[");
+    for (generation, patterns) in &data.lacks {
+        let features: Vec<&String> = patterns
+            .iter()
+            .flat_map(|pattern| expand(data, pattern))
+            .collect();
+        let _ = writeln!(out, "    ({generation:?}, {}),", strings(features));
+    }
+    let _ = writeln!(out, "];");
+
+    let _ = writeln!(out, "const REFINED_FEATURES: &[(&str, &str)] = &[");
+    for refined in &data.refined {
+        let _ = writeln!(out, "    ({:?}, {:?}),", refined.key, refined.cpuid);
+    }
+    let _ = writeln!(out, "];");
+
+    let _ = writeln!(out, "const CATEGORY_FAMILIES: &[(&str, &str)] = &[");
+    for (family, category) in families(data) {
+        let _ = writeln!(out, "    ({family:?}, {category:?}),");
+    }
+    let _ = writeln!(out, "];");
+
+    categories(&mut out, "ISA_DATA", &data.isa);
+    categories(&mut out, "SCALAR_DATA", &data.scalar);
+    categories(&mut out, "OTHER_DATA", &data.other);
+    let _ = writeln!(
+        out,
+        "const X87_MNEMONICS: &[&str] = {};",
+        strings(&data.x87.mnemonics)
+    );
+
+    let _ = writeln!(out, "const CPUID_OVERRIDES: &[(&str, &str, &str)] = &[");
+    for category in data.isa.iter().chain(&data.scalar).chain(&data.other) {
+        for (mnemonic, cpuid) in &category.cpuid_overrides {
+            let _ = writeln!(out, "    ({:?}, {mnemonic:?}, {cpuid:?}),", category.key);
+        }
+    }
+    let _ = writeln!(out, "];");
+    out
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed={DATA}");
+    let text = std::fs::read_to_string(DATA).unwrap_or_else(|err| panic!("{DATA}: {err}"));
+    let data: Data = toml::from_str(&text).unwrap_or_else(|err| panic!("{DATA}: {err}"));
+    if let Err(err) = check(&data) {
+        panic!("{DATA}: {err}");
+    }
+    let out_dir = std::env::var_os("OUT_DIR").expect("cargo sets OUT_DIR");
+    std::fs::write(Path::new(&out_dir).join("isa_data.rs"), generate(&data))
+        .expect("write isa_data.rs");
+}
@@ -0,0 +1,286 @@
+//! File-header metadata, parsed independently of objdump.
+
+use crate::gnu_property::{self, Declared};
+use goblin::elf::header::{EM_386, EM_X86_64, ET_DYN, ET_EXEC, ET_REL};
+use goblin::elf::note::NT_GNU_BUILD_ID;
+use goblin::elf::section_header::{SHF_EXECINSTR, SHT_NOBITS};
+use goblin::elf::sym::STT_GNU_IFUNC;
+use goblin::mach::constants::cputype::{get_arch_name_from_types, CPU_TYPE_X86, CPU_TYPE_X86_64};
+use goblin::mach::constants::{S_ATTR_PURE_INSTRUCTIONS, S_ATTR_SOME_INSTRUCTIONS};
+use goblin::mach::header::{MH_DYLIB, MH_EXECUTE, MH_OBJECT};
+use goblin::mach::load_command::CommandVariant;
+use goblin::mach::Mach;
+use goblin::pe::header::{COFF_MACHINE_X86, COFF_MACHINE_X86_64};
+use goblin::pe::section_table::{IMAGE_SCN_CNT_CODE, IMAGE_SCN_MEM_EXECUTE};
+use goblin::Object;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct BinaryInfo {
+    /// `ELF`, `Mach-O`, `Mach-O (universal)`, `PE`, `archive`, or `unknown`
+    pub format: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bits: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endianness: Option<String>,
+    pub file_size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stripped: Option<bool>,
+    /// GNU build-id, Mach-O UUID, or PDB GUID+age, as lowercase hex
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_id: Option<String>,
+    /// `executable`, `pie-executable`, `shared-library`, or `object`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interpreter: Option<String>,
+    /// Whether the header names an x86 or x86-64 machine. `None` when the
+    /// header couldn't tell us (unknown formats, archives).
+    #[serde(skip)]
+    pub is_x86: Option<bool>,
+    /// ELF `STT_GNU_IFUNC` symbols from `.symtab` and `.dynsym`, for
+    /// `dispatch`
+    #[serde(skip)]
+    pub ifuncs: Vec<String>,
+    /// The x86 properties of an ELF file's `.note.gnu.property`, for
+    /// `gnu_property`; `None` for other formats
+    #[serde(skip)]
+    pub gnu_property: Option<Declared>,
+    /// Shared libraries the file links against, for `dependencies`
+    #[serde(skip)]
+    pub libraries: Vec<String>,
+    /// Executable sections and their sizes in bytes, named the way objdump
+    /// names them, for `code_bytes`
+    #[serde(skip)]
+    pub code_sections: Vec<(String, u64)>,
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn endianness(little: bool) -> Option<String> {
+    Some(if little { "little" } else { "big" }.to_string())
+}
+
+impl BinaryInfo {
+    fn unknown(file_size: u64) -> Self {
+        BinaryInfo {
+            format: "unknown".to_string(),
+            arch: None,
+            bits: None,
+            endianness: None,
+            file_size,
+            stripped: None,
+            build_id: None,
+            kind: None,
+            interpreter: None,
+            is_x86: None,
+            ifuncs: Vec::new(),
+            gnu_property: None,
+            libraries: Vec::new(),
+            code_sections: Vec::new(),
+        }
+    }
+
+    /// Parse the header of `bytes`. Never fails: anything unparsable is reported
+    /// as `format: "unknown"`.
+    pub fn parse(bytes: &[u8]) -> Self {
+        let size = bytes.len() as u64;
+        match Object::parse(bytes) {
+            Ok(Object::Elf(elf)) => {
+                let machine = elf.header.e_machine;
+                let build_id = elf.iter_note_headers(bytes).and_then(|notes| {
+                    notes
+                        .flatten()
+                        .find(|note| note.n_type == NT_GNU_BUILD_ID && note.name == "GNU")
+                        .map(|note| hex(note.desc))
+                });
+                let kind = match elf.header.e_type {
+                    ET_EXEC => Some("executable"),
+                    ET_DYN if elf.interpreter.is_some() => Some("pie-executable"),
+                    ET_DYN => Some("shared-library"),
+                    ET_REL => Some("object"),
+                    _ => None,
+                };
+                let mut ifuncs: Vec<String> = elf
+                    .syms
+                    .iter()
+                    .filter(|sym| sym.st_type() == STT_GNU_IFUNC)
+                    .filter_map(|sym| elf.strtab.get_at(sym.st_name))
+                    .chain(
+                        elf.dynsyms
+                            .iter()
+                            .filter(|sym| sym.st_type() == STT_GNU_IFUNC)
+                            .filter_map(|sym| elf.dynstrtab.get_at(sym.st_name)),
+                    )
+                    .map(str::to_string)
+                    .collect();
+                ifuncs.sort_unstable();
+                ifuncs.dedup();
+                // Object files have no program headers; stripped section
+                // headers leave only the PT_NOTE segment
+                let mut gnu_property = Declared::default();
+                let notes = elf
+                    .iter_note_sections(bytes, Some(".note.gnu.property"))
+                    .or_else(|| elf.iter_note_headers(bytes));
+                for note in notes.into_iter().flatten().flatten() {
+                    if note.n_type == gnu_property::NT_GNU_PROPERTY_TYPE_0 && note.name == "GNU" {
+                        gnu_property.add_note(note.desc, elf.is_64, elf.little_endian);
+                    }
+                }
+                BinaryInfo {
+                    format: "ELF".to_string(),
+                    arch: Some(goblin::elf::header::machine_to_str(machine).to_string()),
+                    bits: Some(if elf.is_64 { 64 } else { 32 }),
+                    endianness: endianness(elf.little_endian),
+                    file_size: size,
+                    stripped: Some(elf.syms.is_empty()),
+                    build_id,
+                    kind: kind.map(str::to_string),
+                    interpreter: elf.interpreter.map(str::to_string),
+                    is_x86: Some(machine == EM_X86_64 || machine == EM_386),
+                    ifuncs,
+                    gnu_property: Some(gnu_property),
+                    libraries: elf.libraries.iter().map(|lib| lib.to_string()).collect(),
+                    code_sections: elf
+                        .section_headers
+                        .iter()
+                        .filter(|sh| sh.sh_flags & u64::from(SHF_EXECINSTR) != 0)
+                        .filter(|sh| sh.sh_type != SHT_NOBITS)
+                        .filter_map(|sh| {
+                            let name = elf.shdr_strtab.get_at(sh.sh_name)?;
+                            Some((name.to_string(), sh.sh_size))
+                        })
+                        .collect(),
+                }
+            }
+            Ok(Object::Mach(Mach::Binary(macho))) => {
+                let header = &macho.header;
+                let build_id = macho
+                    .load_commands
+                    .iter()
+                    .find_map(|cmd| match &cmd.command {
+                        CommandVariant::Uuid(uuid) => Some(hex(&uuid.uuid)),
+                        _ => None,
+                    });
+                let kind = match header.filetype {
+                    MH_EXECUTE => Some("executable"),
+                    MH_DYLIB => Some("shared-library"),
+                    MH_OBJECT => Some("object"),
+                    _ => None,
+                };
+                BinaryInfo {
+                    format: "Mach-O".to_string(),
+                    arch: get_arch_name_from_types(header.cputype, header.cpusubtype)
+                        .map(str::to_string),
+                    bits: Some(if macho.is_64 { 64 } else { 32 }),
+                    endianness: endianness(macho.little_endian),
+                    file_size: size,
+                    stripped: Some(
+                        macho
+                            .symbols
+                            .as_ref()
+                            .is_none_or(|s| s.iter().next().is_none()),
+                    ),
+                    build_id,
+                    kind: kind.map(str::to_string),
+                    interpreter: None,
+                    is_x86: Some(
+                        header.cputype == CPU_TYPE_X86_64 || header.cputype == CPU_TYPE_X86,
+                    ),
+                    ifuncs: Vec::new(),
+                    gnu_property: None,
+                    // The first entry stands for the file itself
+                    libraries: macho
+                        .libs
+                        .iter()
+                        .filter(|&&lib| lib != "self")
+                        .map(|lib| lib.to_string())
+                        .collect(),
+                    // objdump calls them `__TEXT,__text`
+                    code_sections: macho
+                        .segments
+                        .iter()
+                        .filter_map(|segment| segment.sections().ok())
+                        .flatten()
+                        .filter(|(section, _)| {
+                            section.flags & (S_ATTR_PURE_INSTRUCTIONS | S_ATTR_SOME_INSTRUCTIONS)
+                                != 0
+                        })
+                        .filter_map(|(section, _)| {
+                            let name =
+                                format!("{},{}", section.segname().ok()?, section.name().ok()?);
+                            Some((name, section.size))
+                        })
+                        .collect(),
+                }
+            }
+            Ok(Object::Mach(Mach::Fat(_))) => BinaryInfo {
+                format: "Mach-O (universal)".to_string(),
+                ..BinaryInfo::unknown(size)
+            },
+            Ok(Object::PE(pe)) => {
+                let machine = pe.header.coff_header.machine;
+                let build_id = pe
+                    .debug_data
+                    .as_ref()
+                    .and_then(|debug| debug.codeview_pdb70_debug_info.as_ref())
+                    .map(|pdb| format!("{}{:x}", hex(&pdb.signature), pdb.age));
+                BinaryInfo {
+                    format: "PE".to_string(),
+                    arch: Some(
+                        match machine {
+                            COFF_MACHINE_X86_64 => "x86_64",
+                            COFF_MACHINE_X86 => "i386",
+                            _ => "other",
+                        }
+                        .to_string(),
+                    ),
+                    bits: Some(if pe.is_64 { 64 } else { 32 }),
+                    endianness: endianness(true),
+                    file_size: size,
+                    stripped: Some(pe.header.coff_header.pointer_to_symbol_table == 0),
+                    build_id,
+                    kind: Some(
+                        if pe.is_lib {
+                            "shared-library"
+                        } else {
+                            "executable"
+                        }
+                        .to_string(),
+                    ),
+                    interpreter: None,
+                    is_x86: Some(machine == COFF_MACHINE_X86_64 || machine == COFF_MACHINE_X86),
+                    ifuncs: Vec::new(),
+                    gnu_property: None,
+                    libraries: pe.libraries.iter().map(|lib| lib.to_string()).collect(),
+                    // The raw data is padded to the file alignment
+                    code_sections: pe
+                        .sections
+                        .iter()
+                        .filter(|section| {
+                            section.characteristics & (IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE)
+                                != 0
+                        })
+                        .filter_map(|section| {
+                            let size = match section.virtual_size {
+                                0 => section.size_of_raw_data,
+                                virtual_size => virtual_size.min(section.size_of_raw_data),
+                            };
+                            Some((section.name().ok()?.to_string(), u64::from(size)))
+                        })
+                        .collect(),
+                }
+            }
+            Ok(Object::Archive(_)) => BinaryInfo {
+                format: "archive".to_string(),
+                ..BinaryInfo::unknown(size)
+            },
+            _ => BinaryInfo::unknown(size),
+        }
+    }
+}
@@ -0,0 +1,285 @@
+//! `--entry`: the functions reachable from one symbol, so classification can
+//! skip everything else.
+//!
+//! A conservative static call graph read off the disassembly: direct
+//! `call <sym>` edges, plus direct `jmp`s into another symbol (tail calls).
+//! Calls through a register or memory operand can't be followed and are
+//! listed instead, as are calls into IFUNC and multiversioned dispatchers,
+//! whose target depends on CPUID. Indirect `jmp`s are mostly switch tables
+//! and are ignored, so indirect tail calls are missed silently.
+//!
+//! The graph also records, per function, the symbols whose address it
+//! loads and its `cpuid`/`xgetbv` instructions, for `guards`.
+
+use crate::classify::{parent_symbol, Demangle};
+use crate::dispatch;
+use crate::encoding;
+use crate::report::{CallSite, Reachability};
+use indexmap::IndexMap;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+
+lazy_static! {
+    static ref SYMBOL_RE: Regex = Regex::new(r"^([0-9a-fA-F]+) <(.+)>:$").unwrap();
+    static ref SECTION_RE: Regex = Regex::new(r"^Disassembly of section (.+):$").unwrap();
+    // Branch prefixes (`bnd`, `notrack`) come before the mnemonic
+    static ref BRANCH_RE: Regex = Regex::new(
+        r"(?i)^\s*([0-9a-f]+):\s+(?:(?:bnd|notrack|ds|cs)\s+)*(call|jmp)[lqw]?\s+(.+?)\s*$"
+    )
+    .unwrap();
+    static ref DIRECT_RE: Regex = Regex::new(r"^([0-9a-fA-F]+) <(.+)>$").unwrap();
+    // `call *0x2fe2(%rip)  # 403ff0 <free@GLIBC_2.2.5>`: a `-fno-plt` call
+    // through the GOT slot objdump names
+    static ref GOT_RE: Regex = Regex::new(r"#\s*[0-9a-fA-F]+ <([^+>]+)>$").unwrap();
+    static ref OFFSET_RE: Regex = Regex::new(r"[+-]0x[0-9a-fA-F]+$").unwrap();
+    // `lea 0x2ed9(%rip),%rax  # 4010 <kernel_avx2>`, `testb $0x4,0x2f15(%rip)
+    // # 4040 <__cpu_model+0xc>`
+    static ref REFERENCE_RE: Regex = Regex::new(r"#\s*[0-9a-fA-F]+ <([^>]+)>$").unwrap();
+    static ref CPU_CHECK_RE: Regex = Regex::new(r"(?i)^\s*[0-9a-f]+:\s+(cpuid|xgetbv)\b").unwrap();
+}
+
+/// Where one call goes.
+enum Callee {
+    /// A symbol, raw, without `@plt` or version suffixes
+    Direct(String),
+    /// Register or memory operand, as objdump printed it
+    Indirect(String),
+    /// An IRELATIVE PLT slot (`*ABS*+0x4a5a80@plt`): the IFUNC resolver at
+    /// this address picks the target
+    Ifunc(u64),
+    /// A PLT slot without a symbol of its own, at this address. A static
+    /// binary has only IRELATIVE ones, which objdump labels `_init+0xd0`
+    Slot(u64),
+}
+
+struct Call {
+    address: u64,
+    callee: Callee,
+}
+
+/// What one function does that the graph keeps.
+#[derive(Default)]
+pub struct Node {
+    calls: Vec<Call>,
+    /// Symbols whose address the function loads or whose data it reads,
+    /// raw, offsets and version suffixes dropped
+    pub references: Vec<String>,
+    pub cpuid: usize,
+    pub xgetbv: usize,
+}
+
+impl Node {
+    /// Symbols the function calls or tail-jumps to directly.
+    pub fn callees(&self) -> impl Iterator<Item = &str> {
+        self.calls.iter().filter_map(|call| match &call.callee {
+            Callee::Direct(name) => Some(name.as_str()),
+            _ => None,
+        })
+    }
+}
+
+/// Every symbol, keyed by raw parent symbol (see `classify::parent_symbol`)
+/// in disassembly order.
+pub struct CallGraph {
+    pub functions: IndexMap<String, Node>,
+    /// Symbol start addresses, with whether the symbol is in a `.plt*`
+    /// section
+    starts: BTreeMap<u64, (String, bool)>,
+}
+
+/// The raw name a call operand's `<...>` annotation refers to.
+fn target_name(annotation: &str) -> &str {
+    let name = OFFSET_RE
+        .find(annotation)
+        .map_or(annotation, |offset| &annotation[..offset.start()]);
+    name.split('@').next().unwrap_or(name)
+}
+
+impl CallGraph {
+    /// Read the call sites from objdump output, every section included.
+    pub fn build(lines: &[String]) -> Self {
+        let mut functions: IndexMap<String, Node> = IndexMap::new();
+        let mut starts: BTreeMap<u64, (String, bool)> = BTreeMap::new();
+        let mut current: Option<String> = None;
+        let mut in_plt = false;
+        for line in lines {
+            if let Some(captures) = SECTION_RE.captures(line) {
+                in_plt = captures[1].starts_with(".plt");
+                current = None;
+                continue;
+            }
+            if let Some(captures) = SYMBOL_RE.captures(line) {
+                let symbol = &captures[2];
+                if let Ok(address) = u64::from_str_radix(&captures[1], 16) {
+                    starts
+                        .entry(address)
+                        .or_insert_with(|| (symbol.to_string(), in_plt));
+                }
+                // Local labels stay part of the function they sit in
+                if !symbol.starts_with(".L") {
+                    let parent = parent_symbol(symbol).to_string();
+                    functions.entry(parent.clone()).or_default();
+                    current = Some(parent);
+                }
+                continue;
+            }
+            let Some(function) = &current else {
+                continue;
+            };
+            let text = match encoding::split_raw(line) {
+                Some((_, Some(text))) => text,
+                Some((_, None)) => continue,
+                None => line.clone(),
+            };
+            let node = &mut functions[function];
+            if let Some(check) = CPU_CHECK_RE.captures(&text) {
+                if check[1].eq_ignore_ascii_case("cpuid") {
+                    node.cpuid += 1;
+                } else {
+                    node.xgetbv += 1;
+                }
+                continue;
+            }
+            let Some(captures) = BRANCH_RE.captures(&text) else {
+                if let Some(reference) = REFERENCE_RE.captures(&text) {
+                    let name = target_name(&reference[1]);
+                    if !name.starts_with(".L") && !node.references.iter().any(|r| r == name) {
+                        node.references.push(name.to_string());
+                    }
+                }
+                continue;
+            };
+            let address = u64::from_str_radix(&captures[1], 16).unwrap_or(0);
+            let is_call = captures[2].eq_ignore_ascii_case("call");
+            let operand = &captures[3];
+            let callee = if let Some(direct) = DIRECT_RE.captures(operand) {
+                let target = u64::from_str_radix(&direct[1], 16).unwrap_or(0);
+                let annotation = &direct[2];
+                if target == 0 {
+                    // An undefined weak symbol, which callers check for first
+                    continue;
+                } else if let Some(slot) = annotation
+                    .strip_prefix("*ABS*+0x")
+                    .and_then(|rest| rest.strip_suffix("@plt"))
+                {
+                    Callee::Ifunc(u64::from_str_radix(slot, 16).unwrap_or(0))
+                } else if OFFSET_RE.is_match(annotation)
+                    && starts
+                        .range(..=target)
+                        .next_back()
+                        .is_some_and(|(_, &(_, plt))| plt)
+                {
+                    Callee::Slot(target)
+                } else {
+                    let name = parent_symbol(target_name(annotation));
+                    // Branches within the function, its `.cold` part included
+                    if name.starts_with(".L") || name == function {
+                        continue;
+                    }
+                    Callee::Direct(name.to_string())
+                }
+            } else if !is_call {
+                continue;
+            } else if let Some(got) = GOT_RE.captures(operand) {
+                Callee::Direct(target_name(&got[1]).to_string())
+            } else {
+                Callee::Indirect(operand.to_string())
+            };
+            node.calls.push(Call { address, callee });
+        }
+        CallGraph { functions, starts }
+    }
+
+    /// Raw symbols whose raw or demangled name is `entry`, Rust hashes
+    /// optional.
+    pub fn roots(&self, entry: &str, demangle: Demangle) -> Vec<String> {
+        let hashless = Demangle {
+            strip_hash: true,
+            ..demangle
+        };
+        self.functions
+            .keys()
+            .filter(|symbol| {
+                *symbol == entry
+                    || demangle.apply(symbol) == entry
+                    || hashless.apply(symbol) == entry
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Walk the graph from `roots`. Returns the raw names of the reachable
+    /// functions and the `reachability` section; `ifuncs` are the binary's
+    /// `STT_GNU_IFUNC` symbols.
+    pub fn reach(
+        &self,
+        entry: &str,
+        roots: &[String],
+        ifuncs: &[String],
+        demangle: Demangle,
+    ) -> (HashSet<String>, Reachability) {
+        let mut reachable: HashSet<String> = roots.iter().cloned().collect();
+        let mut queue: VecDeque<&str> = roots.iter().map(String::as_str).collect();
+        let mut indirect: Vec<CallSite> = Vec::new();
+        let mut dispatched: Vec<CallSite> = Vec::new();
+        let mut external: BTreeSet<String> = BTreeSet::new();
+        let site = |function: &str, address: u64, target: String| CallSite {
+            function: demangle.apply(function),
+            address: format!("{address:x}"),
+            target,
+        };
+        while let Some(function) = queue.pop_front() {
+            for call in &self.functions[function].calls {
+                match &call.callee {
+                    Callee::Indirect(operand) => {
+                        indirect.push(site(function, call.address, operand.clone()));
+                    }
+                    Callee::Ifunc(resolver) => {
+                        let target = self.starts.get(resolver).map_or_else(
+                            || format!("*ABS*+{resolver:#x}"),
+                            |(symbol, _)| demangle.apply(symbol),
+                        );
+                        dispatched.push(site(function, call.address, target));
+                    }
+                    Callee::Slot(slot) => {
+                        dispatched.push(site(function, call.address, format!("PLT slot {slot:x}")));
+                    }
+                    Callee::Direct(name) => {
+                        let guarded = ifuncs.contains(name)
+                            || dispatch::variant(name, ifuncs).is_some_and(|v| v.guarded());
+                        if guarded {
+                            dispatched.push(site(function, call.address, demangle.apply(name)));
+                        } else if let Some((callee, _)) = self.functions.get_key_value(name) {
+                            if reachable.insert(callee.clone()) {
+                                queue.push_back(callee);
+                            }
+                        } else {
+                            external.insert(demangle.apply(name));
+                        }
+                    }
+                }
+            }
+        }
+        let by_address = |sites: &mut Vec<CallSite>| {
+            sites.sort_by_cached_key(|site| u64::from_str_radix(&site.address, 16).unwrap_or(0));
+        };
+        by_address(&mut indirect);
+        by_address(&mut dispatched);
+        let functions: Vec<String> = self
+            .functions
+            .keys()
+            .filter(|symbol| reachable.contains(*symbol))
+            .map(|symbol| demangle.apply(symbol))
+            .collect();
+        let section = Reachability {
+            entry: entry.to_string(),
+            reachable_functions: functions.len(),
+            functions,
+            indirect_calls: indirect,
+            dispatched_calls: dispatched,
+            external_calls: external.into_iter().collect(),
+        };
+        (reachable, section)
+    }
+}
@@ -0,0 +1,1944 @@
+//! The single pass over objdump output that turns instruction lines into counts.
+
+use crate::compat;
+use crate::dispatch;
+use crate::encoding;
+use crate::heaviness;
+use crate::isa;
+use crate::isa_table;
+use crate::loops::{self, LoopSplit, LoopSummary};
+use crate::report::{
+    Diagnostics, DispatchVariant, EvexOperands, Exclusion, ExclusionRule, InstAddress, MaskUsage,
+    RegisterWidths, SectionCounts,
+};
+use crate::text_data;
+use crate::transitions::{self, TransitionWarning};
+use indexmap::IndexMap;
+use lazy_static::lazy_static;
+use regex::{Regex, RegexSet};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+/// Bucket for instructions that appear before the first symbol header.
+pub const UNATTRIBUTED: &str = "<unattributed>";
+
+lazy_static! {
+    // `{` covers objdump's `{vex}` / `{evex}` encoding markers
+    static ref OBJLINE_RE: Regex = Regex::new(r"^\s*[0-9a-f]+:\s+[\w{]").unwrap();
+    // `_` appears in compare pseudo-ops such as `vcmplt_oqps`
+    static ref MNE_RE: Regex = Regex::new(r"\s([a-z][a-z0-9_]+\b)").unwrap();
+    // Matches both AT&T (`%ymm3`) and Intel (`ymm3`) operand spellings
+    static ref VREG_RE: Regex = Regex::new(r"\b([xyz])mm([0-9]|[12][0-9]|3[01])\b").unwrap();
+    // Symbol headers are matched before lowercasing, so the address may be
+    // in either case
+    static ref SYMBOL_RE: Regex = Regex::new(r"^([0-9a-fA-F]+) <(.+)>:$").unwrap();
+    static ref BANNER_RE: Regex = Regex::new(r":\s+file format \S+$").unwrap();
+    // EVEX operand decorations: opmask (`{%k1}` in AT&T syntax, `{k1}` in
+    // Intel; k0 can't be a writemask), zeroing, embedded broadcast, static
+    // rounding and SAE
+    static ref DECORATION_RE: Regex =
+        Regex::new(r"\{(%?k[1-7]|z|1to(?:2|4|8|16|32)|r[nduz]-sae|sae)\}").unwrap();
+    // Intel syntax spells a broadcast `DWORD BCST [rax]` rather than `{1to16}`,
+    // lowercased like the rest of the line by the time this runs
+    static ref INTEL_BCST_RE: Regex = Regex::new(r"\bbcst\b").unwrap();
+    // MMX registers; `\b` keeps `%xmm0` from matching
+    static ref MMX_REG_RE: Regex = Regex::new(r"\bmm[0-7]\b").unwrap();
+    static ref SECTION_RE: Regex = Regex::new(r"^Disassembly of section (.+):$").unwrap();
+}
+
+/// Counts gathered in one pass over the disassembly.
+pub struct Classification {
+    pub isa_counts: IndexMap<String, usize>,
+    /// Encoded size in bytes of the instructions in `isa_counts`, by ISA key
+    pub isa_bytes: IndexMap<String, usize>,
+    pub inst_detail: HashMap<String, HashMap<String, usize>>,
+    /// The first `Options::address_samples` addresses of each mnemonic, by
+    /// ISA key and mnemonic like `inst_detail`
+    pub inst_addresses: HashMap<String, HashMap<String, Vec<InstAddress>>>,
+    /// Every decoded instruction line, SIMD or not
+    pub total_insts: usize,
+    pub register_widths: RegisterWidths,
+    /// Per-ISA counts for each function containing SIMD, keyed by demangled name
+    pub functions: IndexMap<String, FunctionCounts>,
+    /// Every symbol in disassembly order, with `.cold`/`.part.N` splits
+    /// folded into their parent (see [`parent_symbol`]), keyed by the
+    /// parent's demangled name
+    pub parent_functions: IndexMap<String, FunctionCounts>,
+    /// Raw symbol name behind each function name that demangling changed
+    pub mangled_names: HashMap<String, String>,
+    /// Demangled names of the symbols `Options::functions` matched, in
+    /// disassembly order
+    pub matched_functions: Vec<String>,
+    /// With `Options::range` and no end given: the address of the first
+    /// symbol after the start, where classification stopped
+    pub range_end: Option<u64>,
+    /// Demangled names of the symbols with instructions in `Options::range`
+    pub range_symbols: Vec<String>,
+    /// Instructions skipped by the default and `exclude_functions` rules
+    pub exclusions: Vec<Exclusion>,
+    /// Counts of the symbols `Options::runtime_routines` matched, keyed like
+    /// `parent_functions`. Their instructions are in no other count
+    pub runtime: IndexMap<String, FunctionCounts>,
+    /// Address and ISA key of every SIMD instruction, with `by_source` on
+    pub simd_addresses: Vec<(u64, &'static str)>,
+    /// Instruction counts per section, in disassembly order
+    pub sections: IndexMap<String, SectionCounts>,
+    /// Every section the section filters let through, in disassembly order,
+    /// with or without instructions
+    pub scanned_sections: Vec<String>,
+    pub diagnostics: Diagnostics,
+    /// Vector-looking mnemonics that no ISA set contains
+    pub unclassified: HashMap<String, usize>,
+    /// SIMD moves with a memory operand, by mnemonic
+    pub memory_moves: HashMap<String, usize>,
+    pub scalar_fp: usize,
+    pub packed_fp: usize,
+    pub packed_int: usize,
+    /// CPUID features the classified instructions need
+    pub cpu_features: HashSet<String>,
+    /// Variants of each multiversioned function (see `dispatch`), keyed by
+    /// the function's demangled name
+    pub dispatch: IndexMap<String, Vec<DispatchVariant>>,
+    /// Mnemonic counts inside guarded variants, by (fine) ISA or scalar
+    /// extension key like `inst_detail` and `scalar_detail`
+    pub guarded_detail: HashMap<String, HashMap<String, usize>>,
+    /// `cpu_features` of the instructions outside guarded variants
+    pub baseline_features: HashSet<String>,
+    /// Scalar-extension mnemonic counts by category, with `scalar_ext` on
+    pub scalar_detail: HashMap<String, HashMap<String, usize>>,
+    /// Mnemonic counts of the enabled `extensions`, by key
+    pub other_detail: HashMap<String, HashMap<String, usize>>,
+    pub mask_usage: MaskUsage,
+    pub evex_operands: EvexOperands,
+    /// SIMD mnemonic counts by `isa::category`
+    pub categories: HashMap<String, HashMap<String, usize>>,
+    pub gather_scatter: Attributed,
+    /// Non-temporal stores, including the GPR `movnti`
+    pub non_temporal: Attributed,
+    /// x87 instructions, with `x87` on
+    pub x87: Attributed,
+    /// x87 instructions, counted whether or not `x87` is on
+    pub x87_insts: usize,
+    /// Instructions on `%mm` registers, MMX or the forms later extensions
+    /// added (`pshufw`, `paddq`, `pshufb`, ...)
+    pub mmx_insts: usize,
+    /// Software prefetches, including the AVX512-PF forms
+    pub prefetch: Attributed,
+    /// `ldmxcsr`/`stmxcsr` and their VEX forms
+    pub mxcsr_access: Attributed,
+    /// `vzeroupper`, `vzeroall` and `emms`
+    pub state_resets: Attributed,
+    /// With `transitions` on
+    pub transition_warnings: Vec<TransitionWarning>,
+    /// With `Options::weights`
+    pub weighted: Weighted,
+    /// With `Options::loops`
+    pub loops: LoopSummary,
+    /// With `Options::target`: instructions the target lacks a feature for,
+    /// by ISA key
+    pub faults: IndexMap<String, compat::Faults>,
+    /// Heavy AVX-512 instructions, with `avx512_heaviness` on
+    pub avx512_heavy: Attributed,
+    /// Light AVX-512 instructions, with `avx512_heaviness` on
+    pub avx512_light: usize,
+    /// Register widths of the classified instructions per ISA key, with
+    /// AVX512-VL folded back into AVX512-F so one row shows its 128/256/512-bit
+    /// use side by side
+    pub vector_widths: IndexMap<String, RegisterWidths>,
+    /// Register widths of the AVX-512 instructions (any EVEX encoding)
+    pub avx512_widths: RegisterWidths,
+    /// AVX-512 mnemonic counts on zmm registers
+    pub avx512_zmm: HashMap<String, usize>,
+}
+
+/// One function's counts, for `--show-functions` and `--per-function`.
+#[derive(Default)]
+pub struct FunctionCounts {
+    /// Address of the first symbol header seen for the function, in hex
+    /// without leading zeros; `None` for code outside any symbol and in
+    /// `functions`
+    pub address: Option<String>,
+    /// Every decoded instruction in the function, SIMD or not
+    pub total_insts: usize,
+    /// Profile samples on the function's instructions, with `Options::weights`
+    pub samples: usize,
+    /// SIMD in and out of loop bodies, with `Options::loops`
+    pub loops: LoopSplit,
+    pub isa_counts: IndexMap<String, usize>,
+}
+
+/// Profile samples on the classified instructions, with `Options::weights`.
+#[derive(Default)]
+pub struct Weighted {
+    /// Samples on any classified instruction line
+    pub insts: usize,
+    /// Samples on SIMD instructions, by ISA key
+    pub isa_counts: IndexMap<String, usize>,
+    /// Samples on SIMD instructions, by `isa::category`
+    pub categories: HashMap<String, usize>,
+}
+
+/// Counts of a group of mnemonics worth auditing on their own, overall and
+/// per function.
+#[derive(Default)]
+pub struct Attributed {
+    pub mnemonics: HashMap<String, usize>,
+    pub functions: HashMap<String, usize>,
+}
+
+impl Attributed {
+    fn record(&mut self, mnemonic: &str, function: &str) {
+        *self.mnemonics.entry(mnemonic.to_string()).or_insert(0) += 1;
+        *self.functions.entry(function.to_string()).or_insert(0) += 1;
+    }
+}
+
+/// The operand part of an instruction line (everything after the mnemonic),
+/// without symbol annotations (`<...>`) or trailing comments.
+fn operand_text(rest: &str) -> &str {
+    rest.split(['<', '#']).next().unwrap_or("")
+}
+
+/// Widest vector register named in `operands`, as the register prefix letter
+/// (`x`, `y`, or `z`).
+fn widest_register(operands: &str) -> Option<u8> {
+    VREG_RE
+        .captures_iter(operands)
+        .map(|captures| captures[1].as_bytes()[0])
+        .max()
+}
+
+/// EVEX decorations on one instruction's operands.
+#[derive(Default)]
+struct Decorations {
+    masked: bool,
+    zeroing: bool,
+    broadcast: bool,
+    rounding: bool,
+    sae: bool,
+}
+
+fn decorations(operands: &str) -> Decorations {
+    let mut found = Decorations {
+        broadcast: INTEL_BCST_RE.is_match(operands),
+        ..Decorations::default()
+    };
+    for captures in DECORATION_RE.captures_iter(operands) {
+        match &captures[1] {
+            "z" => found.zeroing = true,
+            "sae" => found.sae = true,
+            rounding if rounding.ends_with("-sae") => found.rounding = true,
+            broadcast if broadcast.starts_with("1to") => found.broadcast = true,
+            _ => found.masked = true,
+        }
+    }
+    found
+}
+
+/// Which demangler `--demangle` applies to symbol names.
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DemangleStyle {
+    /// Rust first, then C++
+    #[default]
+    Auto,
+    Rust,
+    Cpp,
+}
+
+/// How symbol names are shown in the report.
+#[derive(Clone, Copy, Default)]
+pub struct Demangle {
+    /// `None` with `--no-demangle`: raw symbol names
+    pub style: Option<DemangleStyle>,
+    /// Drop the `::h0123...` hash of legacy Rust symbols and the crate
+    /// disambiguators of v0 ones
+    pub strip_hash: bool,
+}
+
+impl Demangle {
+    /// Demangle `symbol`, leaving it untouched if the style doesn't apply. A
+    /// trailing `@plt` / `@GLIBC_...` version suffix is kept as-is.
+    pub fn apply(&self, symbol: &str) -> String {
+        let Some(style) = self.style else {
+            return symbol.to_string();
+        };
+        let (name, suffix) = match symbol.find('@') {
+            Some(at) => symbol.split_at(at),
+            None => (symbol, ""),
+        };
+        if style != DemangleStyle::Cpp {
+            if let Ok(rust) = rustc_demangle::try_demangle(name) {
+                return if self.strip_hash {
+                    format!("{rust:#}{suffix}")
+                } else {
+                    format!("{rust}{suffix}")
+                };
+            }
+        }
+        // cpp_demangle also parses bare type codes, turning a C function
+        // named `f` into `float`; Itanium symbols always start with `_Z`
+        if style != DemangleStyle::Rust && name.starts_with("_Z") {
+            if let Ok(cpp) = cpp_demangle::Symbol::new(name) {
+                if let Ok(text) = cpp.demangle() {
+                    return format!("{text}{suffix}");
+                }
+            }
+        }
+        symbol.to_string()
+    }
+}
+
+/// The function a compiler-split symbol was carved out of: GCC's
+/// `foo.cold` / `foo.cold.3` (unlikely paths) and `foo.part.0` (partial
+/// inlining) become `foo`. Anything else is returned unchanged.
+pub fn parent_symbol(symbol: &str) -> &str {
+    let mut name = symbol;
+    loop {
+        let trimmed = name.trim_end_matches(|c: char| c.is_ascii_digit());
+        let trimmed = if trimmed.len() < name.len() {
+            trimmed.strip_suffix('.').unwrap_or(name)
+        } else {
+            name
+        };
+        let Some(stripped) = [".cold", ".part"]
+            .iter()
+            .find_map(|split| trimmed.strip_suffix(split))
+            .filter(|rest| !rest.is_empty())
+        else {
+            return name;
+        };
+        name = stripped;
+    }
+}
+
+/// Sections skipped by default: PLT stubs only jump to other code.
+const DEFAULT_EXCLUDED_SECTION: &str = ".plt*";
+
+/// Symbol prefixes skipped by default: dynamic-linker trampolines
+/// (`_dl_runtime_resolve_xsavec` saves the whole AVX-512 state) and PIC
+/// thunks.
+const DEFAULT_STUB_PREFIXES: &[&str] = &[
+    "__x86.get_pc_thunk.",
+    "_dl_runtime_resolve",
+    "_dl_runtime_profile",
+    "_dl_tlsdesc_",
+];
+
+/// C runtime start-up and tear-down symbols skipped by default.
+const DEFAULT_STUBS: &[&str] = &[
+    "_start",
+    "_init",
+    "_fini",
+    "deregister_tm_clones",
+    "register_tm_clones",
+    "__do_global_dtors_aux",
+    "frame_dummy",
+    "__libc_csu_init",
+    "__libc_csu_fini",
+];
+
+/// Whether `symbol` is one of the stubs `Options::default_excludes` skips.
+fn is_default_stub(symbol: &str) -> bool {
+    DEFAULT_STUBS.contains(&symbol)
+        || DEFAULT_STUB_PREFIXES
+            .iter()
+            .any(|prefix| symbol.starts_with(prefix))
+}
+
+/// Index into `exclusions` of the entry for `rule` and `name`, added if new.
+fn exclusion(
+    exclusions: &mut Vec<Exclusion>,
+    rule: ExclusionRule,
+    name: &str,
+    pattern: Option<&str>,
+) -> usize {
+    let existing = exclusions
+        .iter()
+        .position(|e| e.rule == rule && e.name == name && e.pattern.as_deref() == pattern);
+    existing.unwrap_or_else(|| {
+        exclusions.push(Exclusion {
+            rule,
+            name: name.to_string(),
+            pattern: pattern.map(str::to_string),
+            instructions: 0,
+        });
+        exclusions.len() - 1
+    })
+}
+
+/// The target of a direct branch: the address objdump prints before the
+/// `<symbol+offset>` annotation (`1140`, or `0x1140` in Intel syntax).
+fn branch_target(operands: &str) -> Option<u64> {
+    let target = operands.split_whitespace().next()?;
+    u64::from_str_radix(target.strip_prefix("0x").unwrap_or(target), 16).ok()
+}
+
+/// The mnemonic of an instruction line, size and x87 suffixes stripped, and
+/// where the mnemonic as written sits in `line`.
+fn mnemonic_at(line: &str, index: &isa::IsaIndex) -> Option<(String, Range<usize>)> {
+    // Search past the address, which can itself look like a mnemonic (`a24c0:`)
+    let after_address = line.find(':').map_or(0, |colon| colon + 1);
+    let matched = MNE_RE.captures_at(line, after_address)?.get(1)?;
+    let mut mnemonic = matched.as_str().to_string();
+    let bare = isa::strip_size_suffix(&mnemonic, |m| isa::is_known(index, m));
+    let bare = isa::strip_x87_suffix(bare).len();
+    mnemonic.truncate(bare);
+    Some((mnemonic, matched.range()))
+}
+
+/// Whether an instruction works on `%mm` registers: it names one and no
+/// vector register (`movq2dq %mm0,%xmm1` is SSE2).
+pub fn on_mmx_registers(operands: &str, widest: Option<u8>) -> bool {
+    widest.is_none() && MMX_REG_RE.is_match(operands)
+}
+
+/// The ISA key of `mnemonic`, before `isa::refine_key`. MMX shares its
+/// mnemonics with the SSE2 integer forms, so only the register operands tell
+/// `paddw %mm1,%mm0` from `paddw %xmm1,%xmm0`; the `%mm` forms later
+/// extensions added (`pshufw`, `paddq`, `pshufb`) keep their own key. An EVEX
+/// vector instruction the table doesn't know is still AVX-512.
+fn lookup_isa(
+    index: &isa::IsaIndex,
+    mnemonic: &str,
+    operands: &str,
+    widest: Option<u8>,
+    encoding: encoding::Encoding,
+) -> Option<&'static str> {
+    if on_mmx_registers(operands, widest) {
+        match isa::lookup(index, mnemonic) {
+            Some("SSE2") if isa::MMX_SHARED.contains(&mnemonic) => Some("MMX"),
+            None => Some("MMX"),
+            found => found,
+        }
+    } else if widest.is_none() && isa::GPR_NAMESAKES.contains(&mnemonic) {
+        None
+    } else {
+        isa::lookup(index, mnemonic).or_else(|| {
+            (encoding == encoding::Encoding::Evex && isa::looks_vector(mnemonic, widest.is_some()))
+                .then(|| isa::evex_key(mnemonic))
+        })
+    }
+}
+
+/// The address an instruction line starts with, 0 if it doesn't parse.
+fn line_address(line: &str) -> u64 {
+    let address = line[..line.find(':').unwrap_or(0)].trim();
+    u64::from_str_radix(address, 16).unwrap_or(0)
+}
+
+/// Whether section `name` matches `pattern`: exactly, or by prefix when the
+/// pattern ends in `*` (`.text*` covers `.text.hot` and `.text.unlikely`).
+fn section_matches(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => pattern == name,
+    }
+}
+
+/// What the classification pass looks at.
+pub struct Options<'a> {
+    /// Lookup over the effective ISA table (built-in plus `--isa-table`)
+    pub index: &'a isa::IsaIndex,
+    /// When non-empty, lines from any other section are skipped entirely,
+    /// including from `total_insts`. A trailing `*` matches any suffix
+    pub sections: &'a [String],
+    /// Sections skipped like those missing from `sections`, same patterns
+    pub exclude_sections: &'a [String],
+    /// Also classify BMI1/BMI2/MOVNTI into `scalar_detail`
+    pub scalar_ext: bool,
+    /// Leave crc32/popcnt/lzcnt in SSE4.2 instead of `scalar_detail`
+    pub legacy_sse4: bool,
+    /// Also count x87 instructions into `x87`
+    pub x87: bool,
+    /// Keys of `isa::OTHER_TABLE` to classify into `other_detail`
+    pub extensions: &'a [&'static str],
+    /// Run the `transitions` checks
+    pub transitions: bool,
+    /// Split AVX-512 instructions into heavy and light (see `heaviness`)
+    pub avx512_heaviness: bool,
+    /// `--ignore-mnemonic` globs: lines with a matching mnemonic are dropped
+    /// from every count, including `total_insts`
+    pub ignore: &'a [String],
+    /// `--function`: when non-empty, only instructions under a symbol whose
+    /// mangled or demangled name matches one of these are read at all,
+    /// like `sections`
+    pub functions: &'a [Regex],
+    /// `--start`/`--end`: only instructions at these addresses are read
+    pub range: Option<AddressRange>,
+    /// `--entry`: only instructions under these raw parent symbols (see
+    /// [`parent_symbol`]) are read, like `functions`
+    pub reachable: Option<&'a HashSet<String>>,
+    /// Skip `.plt*` sections, linker/runtime stubs (see `is_default_stub`)
+    /// and suspected data (see `text_data`)
+    pub default_excludes: bool,
+    /// `--exclude-function`: skip symbols whose mangled or demangled name
+    /// matches
+    pub exclude_functions: &'a [Regex],
+    /// Collect `simd_addresses` for `--by-source`
+    pub by_source: bool,
+    /// `--show-addresses`: how many addresses to keep per mnemonic in
+    /// `inst_addresses`, 0 for none
+    pub address_samples: usize,
+    /// Give each address in `inst_addresses` as `symbol+0xoffset` too
+    pub symbol_offsets: bool,
+    /// Runtime library routine patterns (see `runtime`): symbols whose
+    /// mangled or demangled name matches are counted into `runtime` only;
+    /// `None` with `--no-split-runtime`
+    pub runtime_routines: Option<&'a RegexSet>,
+    /// `STT_GNU_IFUNC` symbol names, for `dispatch`
+    pub ifuncs: &'a [String],
+    /// Profile samples per instruction address, to fill `weighted`
+    pub weights: Option<&'a HashMap<u64, usize>>,
+    /// Split SIMD counts by loop context (see `loops`)
+    pub loops: bool,
+    /// `--target-cpu` / `--target-features`: collect `faults` against it
+    pub target: Option<&'a compat::Target>,
+    /// Faulting addresses to keep per ISA key in `faults`
+    pub target_samples: usize,
+    /// How function names are demangled
+    pub demangle: Demangle,
+}
+
+/// Instruction addresses to classify, `start` inclusive and `end` exclusive.
+/// Without an `end`, classification runs to the next symbol after `start`,
+/// i.e. the end of the function containing it.
+#[derive(Clone, Copy)]
+pub struct AddressRange {
+    pub start: u64,
+    pub end: Option<u64>,
+}
+
+pub fn classify(lines: &[String], opts: &Options) -> Classification {
+    let mut isa_counts = IndexMap::new();
+    let mut isa_bytes: IndexMap<String, usize> = IndexMap::new();
+    let mut inst_detail: HashMap<String, HashMap<String, usize>> = HashMap::new();
+    let mut inst_addresses: HashMap<String, HashMap<String, Vec<InstAddress>>> = HashMap::new();
+    // Name and address of the enclosing (non-`.L`) symbol, for `symbol_offsets`
+    let mut symbol_start: Option<(String, u64)> = None;
+    let mut total_insts = 0;
+    let mut register_widths = RegisterWidths::default();
+    let mut functions: IndexMap<String, FunctionCounts> = IndexMap::new();
+    let mut function = UNATTRIBUTED.to_string();
+    let mut parent = UNATTRIBUTED.to_string();
+    let mut in_scope = opts.functions.is_empty() && opts.reachable.is_none();
+    let mut matched_functions: Vec<String> = Vec::new();
+    let mut mangled_names: HashMap<String, String> = HashMap::new();
+    let mut parent_functions: IndexMap<String, FunctionCounts> = IndexMap::new();
+    let mut sections: IndexMap<String, SectionCounts> = IndexMap::new();
+    let mut diagnostics = Diagnostics::default();
+    let mut unclassified: HashMap<String, usize> = HashMap::new();
+    let mut memory_moves: HashMap<String, usize> = HashMap::new();
+    let (mut scalar_fp, mut packed_fp, mut packed_int) = (0, 0, 0);
+    let mut cpu_features: HashSet<String> = HashSet::new();
+    let mut dispatch: IndexMap<String, Vec<DispatchVariant>> = IndexMap::new();
+    let mut guarded_detail: HashMap<String, HashMap<String, usize>> = HashMap::new();
+    let mut baseline_features: HashSet<String> = HashSet::new();
+    // Index into `dispatch` and its variants of the current symbol, if a
+    // variant, and whether that variant is guarded
+    let mut variant: Option<(usize, usize)> = None;
+    let mut guarded = false;
+    let mut checker = opts
+        .target
+        .map(|target| compat::Tracker::new(target, opts.target_samples));
+    let mut scalar_detail: HashMap<String, HashMap<String, usize>> = HashMap::new();
+    let mut other_detail: HashMap<String, HashMap<String, usize>> = HashMap::new();
+    let mut mask_usage = MaskUsage::default();
+    let mut evex_operands = EvexOperands::default();
+    let mut categories: HashMap<String, HashMap<String, usize>> = HashMap::new();
+    let mut gather_scatter = Attributed::default();
+    let mut non_temporal = Attributed::default();
+    let mut x87 = Attributed::default();
+    let mut x87_insts = 0;
+    let mut mmx_insts = 0;
+    let mut prefetch = Attributed::default();
+    let mut mxcsr_access = Attributed::default();
+    let mut state_resets = Attributed::default();
+    let mut tracker = transitions::Tracker::default();
+    let mut weighted = Weighted::default();
+    let mut loop_tracker = loops::Tracker::default();
+    let mut avx512_heavy = Attributed::default();
+    let mut avx512_light = 0;
+    let mut vector_widths: IndexMap<String, RegisterWidths> = IndexMap::new();
+    let mut avx512_widths = RegisterWidths::default();
+    let mut avx512_zmm: HashMap<String, usize> = HashMap::new();
+    // objdump always prints a section header first; this only covers bare listings
+    let mut section = opts.sections.is_empty().then(|| "<unknown>".to_string());
+    let mut scanned_sections: Vec<String> = Vec::new();
+    let mut range_end = opts.range.and_then(|range| range.end);
+    let mut range_symbols: Vec<String> = Vec::new();
+    let mut exclusions: Vec<Exclusion> = Vec::new();
+    let mut runtime: IndexMap<String, FunctionCounts> = IndexMap::new();
+    // Whether the current symbol is a runtime routine
+    let mut in_runtime = false;
+    let mut simd_addresses: Vec<(u64, &'static str)> = Vec::new();
+    // Entry in `exclusions` for the current section or symbol, if skipped
+    let mut section_excluded: Option<usize> = None;
+    let mut symbol_excluded: Option<usize> = None;
+    let split = encoding::split_lines(lines);
+    let suspected_data = if opts.default_excludes {
+        text_data::find(&split)
+    } else {
+        HashSet::new()
+    };
+
+    for (index, line) in lines.iter().enumerate() {
+        if BANNER_RE.is_match(line) {
+            continue;
+        }
+        if let Some(captures) = SECTION_RE.captures(line) {
+            let name = &captures[1];
+            let included =
+                opts.sections.is_empty() || opts.sections.iter().any(|p| section_matches(p, name));
+            let excluded = opts
+                .exclude_sections
+                .iter()
+                .any(|p| section_matches(p, name));
+            section = (included && !excluded).then(|| name.to_string());
+            section_excluded = (section.is_some()
+                && opts.default_excludes
+                && section_matches(DEFAULT_EXCLUDED_SECTION, name))
+            .then(|| exclusion(&mut exclusions, ExclusionRule::DefaultSection, name, None));
+            symbol_excluded = None;
+            if section.is_some()
+                && section_excluded.is_none()
+                && !scanned_sections.iter().any(|s| s == name)
+            {
+                scanned_sections.push(name.to_string());
+            }
+            function = UNATTRIBUTED.to_string();
+            parent = UNATTRIBUTED.to_string();
+            symbol_start = None;
+            if opts.loops {
+                loop_tracker.flush(&mut functions, &mut parent_functions);
+                loop_tracker.enter(None);
+            }
+            variant = None;
+            guarded = false;
+            in_runtime = false;
+            in_scope = opts.functions.is_empty() && opts.reachable.is_none();
+            continue;
+        }
+        let Some(section) = &section else {
+            continue;
+        };
+        if let Some(captures) = SYMBOL_RE.captures(line) {
+            let symbol = &captures[2];
+            function = opts.demangle.apply(symbol);
+            if function != symbol {
+                mangled_names.insert(function.clone(), symbol.to_string());
+            }
+            if let Some(range) = opts.range.filter(|_| range_end.is_none()) {
+                let address = u64::from_str_radix(&captures[1], 16).unwrap_or(0);
+                if address > range.start && !symbol.starts_with(".L") {
+                    range_end = Some(address);
+                }
+            }
+            if !opts.functions.is_empty() {
+                in_scope = opts
+                    .functions
+                    .iter()
+                    .any(|re| re.is_match(symbol) || re.is_match(&function));
+                if in_scope && !matched_functions.contains(&function) {
+                    matched_functions.push(function.clone());
+                }
+            }
+            if let Some(reachable) = opts.reachable.filter(|_| !symbol.starts_with(".L")) {
+                in_scope = (in_scope || opts.functions.is_empty())
+                    && reachable.contains(parent_symbol(symbol));
+            }
+            // Local labels stay part of the function they sit in
+            if !symbol.starts_with(".L") {
+                if opts.loops {
+                    loop_tracker.flush(&mut functions, &mut parent_functions);
+                    loop_tracker.enter(u64::from_str_radix(&captures[1], 16).ok());
+                }
+                symbol_excluded = if opts.default_excludes && is_default_stub(symbol) {
+                    Some(exclusion(
+                        &mut exclusions,
+                        ExclusionRule::DefaultStub,
+                        &function,
+                        None,
+                    ))
+                } else {
+                    opts.exclude_functions
+                        .iter()
+                        .find(|re| re.is_match(symbol) || re.is_match(&function))
+                        .map(|re| {
+                            exclusion(
+                                &mut exclusions,
+                                ExclusionRule::ExcludeFunction,
+                                &function,
+                                Some(re.as_str()),
+                            )
+                        })
+                };
+                let raw_parent = parent_symbol(symbol);
+                parent = opts.demangle.apply(raw_parent);
+                if parent != raw_parent {
+                    mangled_names.insert(parent.clone(), raw_parent.to_string());
+                }
+                in_runtime = opts.runtime_routines.is_some_and(|routines| {
+                    routines.is_match(raw_parent) || routines.is_match(&parent)
+                });
+                // Runtime routines are no part of the binary's own dispatch
+                let found = dispatch::variant(raw_parent, opts.ifuncs).filter(|_| !in_runtime);
+                guarded = found.as_ref().is_some_and(dispatch::Variant::guarded);
+                variant = found.map(|found| {
+                    let entry = dispatch.entry(opts.demangle.apply(found.base));
+                    let group = entry.index();
+                    let variants = entry.or_default();
+                    let index = variants
+                        .iter()
+                        .position(|v| v.symbol == parent)
+                        .unwrap_or_else(|| {
+                            variants.push(DispatchVariant {
+                                symbol: parent.clone(),
+                                target: found.target.to_string(),
+                                guarded,
+                                total_simd_insts: 0,
+                                isa_summary: IndexMap::new(),
+                            });
+                            variants.len() - 1
+                        });
+                    (group, index)
+                });
+                symbol_start = Some((
+                    function.clone(),
+                    u64::from_str_radix(&captures[1], 16).unwrap_or(0),
+                ));
+                let address = captures[1].trim_start_matches('0');
+                let counts = if in_runtime {
+                    runtime.entry(parent.clone()).or_default()
+                } else {
+                    parent_functions.entry(parent.clone()).or_default()
+                };
+                counts.address.get_or_insert_with(|| {
+                    if address.is_empty() { "0" } else { address }.to_ascii_lowercase()
+                });
+            }
+            continue;
+        }
+        if !in_scope {
+            continue;
+        }
+        let encoding::Split { bytes, text } = &split[index];
+        // Tail bytes of the instruction on the previous line. The `<...>`
+        // annotations in operands are lowercased too, but never read.
+        let Some(text) = text else {
+            continue;
+        };
+        let line = text.as_ref();
+        if line.contains("(bad)") {
+            diagnostics.bad_instructions += 1;
+            continue;
+        }
+        if !OBJLINE_RE.is_match(line) {
+            // Blank separators and `...` (elided zero fill) are expected
+            let trimmed = line.trim();
+            if !trimmed.is_empty() && trimmed != "..." {
+                diagnostics.skipped_lines += 1;
+            }
+            continue;
+        }
+        if let Some(range) = opts.range {
+            let address = line_address(line);
+            if address < range.start || range_end.is_some_and(|end| address >= end) {
+                continue;
+            }
+            if function != UNATTRIBUTED && !range_symbols.contains(&function) {
+                range_symbols.push(function.clone());
+            }
+        }
+        if let Some(index) = section_excluded.or(symbol_excluded) {
+            exclusions[index].instructions += 1;
+            continue;
+        }
+        if suspected_data.contains(&index) {
+            let index = exclusion(
+                &mut exclusions,
+                ExclusionRule::SuspectedData,
+                &function,
+                None,
+            );
+            exclusions[index].instructions += 1;
+            continue;
+        }
+        if in_runtime {
+            let parsed = mnemonic_at(line, opts.index);
+            if parsed
+                .as_ref()
+                .is_some_and(|(m, _)| isa_table::ignored(opts.ignore, m))
+            {
+                diagnostics.ignored_instructions += 1;
+                continue;
+            }
+            let counts = runtime.entry(parent.clone()).or_default();
+            counts.total_insts += 1;
+            let Some((mnemonic, span)) = parsed else {
+                continue;
+            };
+            let operands = operand_text(&line[span.end..]);
+            let widest = widest_register(operands);
+            let encoding = encoding::from_bytes(bytes).unwrap_or_else(|| {
+                encoding::from_text(&mnemonic, &line[..span.start], operands, widest)
+            });
+            if let Some(isa) = lookup_isa(opts.index, &mnemonic, operands, widest, encoding) {
+                let isa = isa::refine_key(isa, &mnemonic, widest, encoding);
+                *counts.isa_counts.entry(isa.to_string()).or_insert(0) += 1;
+            }
+            continue;
+        }
+        total_insts += 1;
+        let section_counts = sections.entry(section.clone()).or_default();
+        section_counts.total_insts += 1;
+        functions.entry(function.clone()).or_default().total_insts += 1;
+        parent_functions
+            .entry(parent.clone())
+            .or_default()
+            .total_insts += 1;
+
+        let Some((mnemonic, matched)) = mnemonic_at(line, opts.index) else {
+            diagnostics.unmatched_mnemonics += 1;
+            continue;
+        };
+        if isa_table::ignored(opts.ignore, &mnemonic) {
+            diagnostics.ignored_instructions += 1;
+            total_insts -= 1;
+            section_counts.total_insts -= 1;
+            functions[&function].total_insts -= 1;
+            parent_functions[&parent].total_insts -= 1;
+            continue;
+        }
+        let weight = opts
+            .weights
+            .and_then(|weights| weights.get(&line_address(line)).copied())
+            .unwrap_or(0);
+        if weight > 0 {
+            weighted.insts += weight;
+            functions[&function].samples += weight;
+            parent_functions[&parent].samples += weight;
+        }
+
+        let operands = operand_text(&line[matched.end..]);
+        let widest = widest_register(operands);
+        if opts.loops && isa::is_conditional_branch(&mnemonic) {
+            if let Some(target) = branch_target(operands) {
+                loop_tracker.branch(line_address(line), target);
+            }
+        }
+        if let Some(width) = widest {
+            register_widths.record(width);
+        }
+        let decorated = decorations(operands);
+        if decorated.masked {
+            mask_usage.masked += 1;
+            if decorated.zeroing {
+                mask_usage.zero_masked += 1;
+            }
+        }
+        evex_operands.broadcast += usize::from(decorated.broadcast);
+        evex_operands.rounding += usize::from(decorated.rounding);
+        evex_operands.sae += usize::from(decorated.sae);
+
+        if isa::is_non_temporal_store(&mnemonic) {
+            non_temporal.record(&mnemonic, &function);
+        }
+        if isa::is_prefetch(&mnemonic) {
+            prefetch.record(&mnemonic, &function);
+        }
+        if isa::is_mxcsr_access(&mnemonic) {
+            mxcsr_access.record(&mnemonic, &function);
+        }
+        if isa::is_state_reset(&mnemonic) {
+            state_resets.record(&mnemonic, &function);
+        }
+        // `fisttp` is x87 and SSE3 at once; it goes on to be counted as both
+        if isa::is_x87(&mnemonic) {
+            x87_insts += 1;
+            if opts.x87 {
+                x87.record(&mnemonic, &function);
+            }
+        }
+        if on_mmx_registers(operands, widest) {
+            mmx_insts += 1;
+        }
+
+        let at = |isa| compat::Fault {
+            isa,
+            address: line_address(line),
+            symbol: symbol_start.as_ref(),
+            function: &function,
+            guarded,
+        };
+        let scalar = isa::SCALAR_TABLE
+            .iter()
+            .find(|(_, mset)| mset.contains(mnemonic.as_str()))
+            .filter(|(ext, _)| {
+                if isa::was_sse4(ext) {
+                    !opts.legacy_sse4
+                } else {
+                    opts.scalar_ext
+                }
+            });
+        if let Some((&ext, _)) = scalar {
+            let feature = isa::cpu_feature(ext, &mnemonic);
+            if let Some(checker) = &mut checker {
+                checker.check([feature], at(ext));
+            }
+            if !cpu_features.contains(feature) {
+                cpu_features.insert(feature.to_string());
+            }
+            if guarded {
+                let detail = guarded_detail.entry(ext.to_string()).or_default();
+                *detail.entry(mnemonic.clone()).or_insert(0) += 1;
+            } else if !baseline_features.contains(feature) {
+                baseline_features.insert(feature.to_string());
+            }
+            let detail = scalar_detail.entry(ext.to_string()).or_default();
+            *detail.entry(mnemonic).or_insert(0) += 1;
+            continue;
+        }
+        let other = isa::OTHER_TABLE
+            .iter()
+            .find(|(ext, mset)| opts.extensions.contains(ext) && mset.contains(mnemonic.as_str()));
+        if let Some((&ext, _)) = other {
+            let feature = isa::cpu_feature(ext, &mnemonic);
+            if isa::needs_feature(&mnemonic) && !cpu_features.contains(feature) {
+                cpu_features.insert(feature.to_string());
+            }
+            if isa::needs_feature(&mnemonic) && !guarded && !baseline_features.contains(feature) {
+                baseline_features.insert(feature.to_string());
+            }
+            if let Some(checker) = checker.as_mut().filter(|_| isa::needs_feature(&mnemonic)) {
+                checker.check([feature], at(ext));
+            }
+            let detail = other_detail.entry(ext.to_string()).or_default();
+            *detail.entry(mnemonic).or_insert(0) += 1;
+            continue;
+        }
+
+        // Register-to-register copies aren't memory traffic; AT&T and Intel
+        // syntax spell memory operands with `(...)` and `[...]` respectively
+        if isa::memory_access(&mnemonic).is_some() && operands.contains(['(', '[']) {
+            *memory_moves.entry(mnemonic.clone()).or_insert(0) += 1;
+        }
+
+        let encoding = encoding::from_bytes(bytes).unwrap_or_else(|| {
+            encoding::from_text(&mnemonic, &line[..matched.start], operands, widest)
+        });
+        let found = lookup_isa(opts.index, &mnemonic, operands, widest, encoding);
+        if opts.transitions {
+            let step = transitions::Step::of(&mnemonic, operands, encoding, widest, found);
+            if let Some(step) = step {
+                let address = line[..line.find(':').unwrap_or(0)].trim();
+                tracker.step(&function, address, &mnemonic, step);
+            }
+        }
+        if found.is_some() || isa::looks_vector(&mnemonic, widest.is_some()) {
+            match isa::shape(&mnemonic) {
+                Some(isa::Shape::ScalarFp) => scalar_fp += 1,
+                Some(isa::Shape::PackedFp) => packed_fp += 1,
+                Some(isa::Shape::PackedInt) => packed_int += 1,
+                None => {}
+            }
+        }
+        let Some(isa) = found else {
+            if isa::looks_vector(&mnemonic, widest.is_some()) {
+                *unclassified.entry(mnemonic).or_insert(0) += 1;
+            }
+            continue;
+        };
+
+        let isa = isa::refine_key(isa, &mnemonic, widest, encoding);
+        if let Some(width) = widest {
+            // AVX512-VL is AVX512-F at reduced width, which is what this shows
+            let key = if isa == "AVX512-VL" { "AVX512-F" } else { isa };
+            vector_widths
+                .entry(key.to_string())
+                .or_default()
+                .record(width);
+        }
+        *isa_counts.entry(isa.to_string()).or_insert(0) += 1;
+        *isa_bytes.entry(isa.to_string()).or_insert(0) += bytes.len();
+
+        let feature = isa::cpu_feature(isa, &mnemonic);
+        if !cpu_features.contains(feature) {
+            cpu_features.insert(feature.to_string());
+        }
+        for implied in isa::implied_features(isa, &mnemonic, widest) {
+            cpu_features.insert(implied.to_string());
+        }
+        if let Some(checker) = &mut checker {
+            let implied = isa::implied_features(isa, &mnemonic, widest);
+            checker.check(implied.iter().copied().chain([feature]), at(isa));
+        }
+        if guarded {
+            let detail = guarded_detail.entry(isa.to_string()).or_default();
+            *detail.entry(mnemonic.clone()).or_insert(0) += 1;
+        } else {
+            if !baseline_features.contains(feature) {
+                baseline_features.insert(feature.to_string());
+            }
+            for implied in isa::implied_features(isa, &mnemonic, widest) {
+                baseline_features.insert(implied.to_string());
+            }
+        }
+        if let Some((group, index)) = variant {
+            let variant = &mut dispatch[group][index];
+            variant.total_simd_insts += 1;
+            *variant.isa_summary.entry(isa.to_string()).or_insert(0) += 1;
+        }
+
+        if isa::is_avx512(isa) && mnemonic.starts_with('k') {
+            *mask_usage.mask_ops.entry(mnemonic.clone()).or_insert(0) += 1;
+        }
+        let avx512 = isa::is_avx512(isa) || encoding == encoding::Encoding::Evex;
+        if let Some(width) = widest.filter(|_| avx512) {
+            avx512_widths.record(width);
+            if width == b'z' {
+                *avx512_zmm.entry(mnemonic.clone()).or_insert(0) += 1;
+            }
+        }
+        if opts.avx512_heaviness && avx512 {
+            if heaviness::is_heavy(&mnemonic, widest) {
+                avx512_heavy.record(&mnemonic, &function);
+            } else {
+                avx512_light += 1;
+            }
+        }
+
+        let category = isa::category(&mnemonic);
+        let per_category = categories.entry(category.to_string()).or_default();
+        *per_category.entry(mnemonic.clone()).or_insert(0) += 1;
+        if weight > 0 {
+            *weighted.isa_counts.entry(isa.to_string()).or_insert(0) += weight;
+            *weighted.categories.entry(category.to_string()).or_insert(0) += weight;
+        }
+        if category == "gather/scatter" {
+            gather_scatter.record(&mnemonic, &function);
+        }
+
+        if opts.address_samples > 0 {
+            let samples = inst_addresses
+                .entry(isa.to_string())
+                .or_default()
+                .entry(mnemonic.clone())
+                .or_default();
+            if samples.len() < opts.address_samples {
+                let address = line_address(line);
+                samples.push(InstAddress {
+                    address: format!("{address:x}"),
+                    location: symbol_start
+                        .as_ref()
+                        .filter(|_| opts.symbol_offsets)
+                        .map(|(name, start)| format!("{name}+{:#x}", address - start)),
+                });
+            }
+        }
+        let isa_detail = inst_detail.entry(isa.to_string()).or_default();
+        *isa_detail.entry(mnemonic).or_insert(0) += 1;
+
+        let per_function = functions.entry(function.clone());
+        let function_index = per_function.index();
+        let per_function = &mut per_function.or_default().isa_counts;
+        *per_function.entry(isa.to_string()).or_insert(0) += 1;
+        let per_parent = parent_functions.entry(parent.clone());
+        let parent_index = per_parent.index();
+        let per_parent = &mut per_parent.or_default().isa_counts;
+        *per_parent.entry(isa.to_string()).or_insert(0) += 1;
+        if opts.loops {
+            loop_tracker.simd(line_address(line), isa, function_index, parent_index);
+        }
+        if opts.by_source {
+            simd_addresses.push((line_address(line), isa));
+        }
+        section_counts.total_simd_insts += 1;
+    }
+
+    if opts.loops {
+        loop_tracker.flush(&mut functions, &mut parent_functions);
+    }
+
+    // Sort isa_counts by key
+    isa_counts.sort_keys();
+    weighted.isa_counts.sort_keys();
+    vector_widths.sort_keys();
+    for counts in functions.values_mut() {
+        counts.isa_counts.sort_keys();
+    }
+    for counts in parent_functions.values_mut().chain(runtime.values_mut()) {
+        counts.isa_counts.sort_keys();
+    }
+    for variant in dispatch.values_mut().flatten() {
+        variant.isa_summary.sort_keys();
+    }
+    mask_usage
+        .mask_ops
+        .sort_by(|m1, n1, m2, n2| n2.cmp(n1).then_with(|| m1.cmp(m2)));
+    let mut transition_warnings = tracker.finish();
+    for warning in &mut transition_warnings {
+        warning.mangled = mangled_names.get(&warning.function).cloned();
+    }
+
+    Classification {
+        isa_counts,
+        isa_bytes,
+        inst_detail,
+        inst_addresses,
+        total_insts,
+        register_widths,
+        functions,
+        parent_functions,
+        mangled_names,
+        matched_functions,
+        range_end,
+        range_symbols,
+        exclusions,
+        runtime,
+        simd_addresses,
+        sections,
+        scanned_sections,
+        diagnostics,
+        unclassified,
+        memory_moves,
+        scalar_fp,
+        packed_fp,
+        packed_int,
+        cpu_features,
+        dispatch,
+        guarded_detail,
+        baseline_features,
+        scalar_detail,
+        other_detail,
+        mask_usage,
+        evex_operands,
+        categories,
+        gather_scatter,
+        non_temporal,
+        x87,
+        x87_insts,
+        mmx_insts,
+        prefetch,
+        mxcsr_access,
+        state_resets,
+        transition_warnings,
+        weighted,
+        loops: loop_tracker.finish(),
+        faults: checker.map(|checker| checker.faults).unwrap_or_default(),
+        avx512_heavy,
+        avx512_light,
+        vector_widths,
+        avx512_widths,
+        avx512_zmm,
+    }
+}
+
+impl Classification {
+    /// Merge ISA keys into their `granularity` buckets. Run this after anything
+    /// that needs the fine keys (psABI level, minimum CPU).
+    pub fn regroup(&mut self, granularity: isa::Granularity) {
+        if granularity == isa::Granularity::Fine {
+            return;
+        }
+        let regroup_counts = |counts: &mut IndexMap<String, usize>| {
+            let mut merged: IndexMap<String, usize> = IndexMap::new();
+            for (isa, count) in counts.drain(..) {
+                *merged
+                    .entry(isa::report_key(&isa, granularity).to_string())
+                    .or_insert(0) += count;
+            }
+            merged.sort_keys();
+            *counts = merged;
+        };
+        regroup_counts(&mut self.isa_counts);
+        regroup_counts(&mut self.isa_bytes);
+        regroup_counts(&mut self.weighted.isa_counts);
+        let mut by_isa: IndexMap<String, LoopSplit> = IndexMap::new();
+        for (isa, split) in self.loops.by_isa.drain(..) {
+            by_isa
+                .entry(isa::report_key(&isa, granularity).to_string())
+                .or_default()
+                .merge(&split);
+        }
+        by_isa.sort_keys();
+        self.loops.by_isa = by_isa;
+        let mut vector_widths: IndexMap<String, RegisterWidths> = IndexMap::new();
+        for (isa, widths) in self.vector_widths.drain(..) {
+            vector_widths
+                .entry(isa::report_key(&isa, granularity).to_string())
+                .or_default()
+                .merge(&widths);
+        }
+        vector_widths.sort_keys();
+        self.vector_widths = vector_widths;
+        for counts in self.functions.values_mut() {
+            regroup_counts(&mut counts.isa_counts);
+        }
+        for counts in self
+            .parent_functions
+            .values_mut()
+            .chain(self.runtime.values_mut())
+        {
+            regroup_counts(&mut counts.isa_counts);
+        }
+        for variant in self.dispatch.values_mut().flatten() {
+            regroup_counts(&mut variant.isa_summary);
+        }
+        compat::regroup(&mut self.faults, granularity);
+        for (_, isa) in &mut self.simd_addresses {
+            *isa = isa::report_key(isa, granularity);
+        }
+
+        let mut inst_detail: HashMap<String, HashMap<String, usize>> = HashMap::new();
+        for (isa, mnemonics) in self.inst_detail.drain() {
+            let merged = inst_detail
+                .entry(isa::report_key(&isa, granularity).to_string())
+                .or_default();
+            for (mnemonic, count) in mnemonics {
+                *merged.entry(mnemonic).or_insert(0) += count;
+            }
+        }
+        self.inst_detail = inst_detail;
+
+        let mut inst_addresses: HashMap<String, HashMap<String, Vec<InstAddress>>> = HashMap::new();
+        for (isa, mnemonics) in self.inst_addresses.drain() {
+            let merged = inst_addresses
+                .entry(isa::report_key(&isa, granularity).to_string())
+                .or_default();
+            for (mnemonic, samples) in mnemonics {
+                merged.entry(mnemonic).or_default().extend(samples);
+            }
+        }
+        self.inst_addresses = inst_addresses;
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    lazy_static! {
+        static ref INDEX: isa::IsaIndex = isa::index(&isa::ISA_TABLE);
+    }
+
+    /// Classify captured objdump output the way a plain `simdscan` run
+    /// does, after `tweak` adjusts the options.
+    pub fn run_with<'a>(text: &str, tweak: impl FnOnce(&mut Options<'a>)) -> Classification {
+        let lines: Vec<String> = text.lines().map(str::to_string).collect();
+        let mut opts = Options {
+            index: &INDEX,
+            sections: &[],
+            exclude_sections: &[],
+            scalar_ext: false,
+            legacy_sse4: false,
+            x87: false,
+            extensions: &[],
+            transitions: false,
+            avx512_heaviness: false,
+            ignore: &[],
+            functions: &[],
+            range: None,
+            reachable: None,
+            default_excludes: true,
+            exclude_functions: &[],
+            by_source: false,
+            address_samples: 0,
+            symbol_offsets: false,
+            runtime_routines: None,
+            ifuncs: &[],
+            weights: None,
+            loops: false,
+            target: None,
+            target_samples: 0,
+            demangle: Demangle::default(),
+        };
+        tweak(&mut opts);
+        classify(&lines, &opts)
+    }
+
+    pub fn run(text: &str) -> Classification {
+        run_with(text, |_| {})
+    }
+
+    const SECTIONS: &str = include_str!("../testdata/sections.objdump");
+
+    #[test]
+    fn counts_each_section() {
+        let classification = run(SECTIONS);
+        let counts: Vec<(&str, usize, usize)> = classification
+            .sections
+            .iter()
+            .map(|(name, c)| (name.as_str(), c.total_simd_insts, c.total_insts))
+            .collect();
+        assert_eq!(counts, [(".text", 3, 5), (".init", 2, 3)]);
+        assert_eq!(classification.total_insts, 8);
+        assert_eq!(classification.isa_counts.values().sum::<usize>(), 5);
+    }
+
+    #[test]
+    fn diagnostics_count_each_anomaly() {
+        let text = "\
+Disassembly of section .text:
+
+0000000000000000 <f>:
+   0:\t0f 28 c8             \tmovaps %xmm0,%xmm1
+   3:\t06                   \t(bad)
+   4:\t62                   \t(bad)
+warning: stray output
+\t...
+   5:\t0f58c8
+   8:\tc3                   \tret
+";
+        let diagnostics = run(text).diagnostics;
+        assert_eq!(diagnostics.bad_instructions, 2);
+        assert_eq!(diagnostics.skipped_lines, 1);
+        assert_eq!(diagnostics.unmatched_mnemonics, 1);
+        let clean = run(include_str!("../testdata/sections.objdump")).diagnostics;
+        assert_eq!(
+            (
+                clean.skipped_lines,
+                clean.bad_instructions,
+                clean.unmatched_mnemonics
+            ),
+            (0, 0, 0)
+        );
+    }
+
+    /// The mnemonic counts classified under `isa`, by name.
+    pub fn mnemonics<'c>(classification: &'c Classification, isa: &str) -> Vec<(&'c str, usize)> {
+        let mut counts: Vec<(&str, usize)> = classification
+            .inst_detail
+            .get(isa)
+            .into_iter()
+            .flatten()
+            .map(|(mnemonic, count)| (mnemonic.as_str(), *count))
+            .collect();
+        counts.sort_unstable();
+        counts
+    }
+
+    #[test]
+    fn avx2_only_on_ymm_integer_ops() {
+        // The same C compiled with -mavx and -mavx2
+        let avx = run(include_str!("../testdata/mavx.objdump"));
+        assert_eq!(avx.isa_counts.keys().collect::<Vec<_>>(), ["AVX"]);
+        assert_eq!(avx.inst_detail["AVX"]["vpaddd"], 2);
+
+        let mut avx2 = run(include_str!("../testdata/mavx2.objdump"));
+        assert_eq!(
+            mnemonics(&avx2, "AVX2"),
+            [("vpaddd", 1), ("vpbroadcastd", 1)]
+        );
+        assert!(!avx2.inst_detail["AVX"].contains_key("vpaddd"));
+
+        // --isa-granularity coarse folds AVX2 back into AVX
+        avx2.regroup(isa::Granularity::Coarse);
+        assert_eq!(avx2.isa_counts.keys().collect::<Vec<_>>(), ["AVX"]);
+        assert_eq!(avx2.isa_counts["AVX"], 8);
+    }
+
+    #[test]
+    fn fma_split_from_avx() {
+        // The same C compiled with -mavx -mno-fma and with -mavx -mfma
+        let avx = run(include_str!("../testdata/mno-fma.objdump"));
+        assert_eq!(avx.isa_counts.keys().collect::<Vec<_>>(), ["AVX"]);
+
+        let fma = run(include_str!("../testdata/mfma.objdump"));
+        assert_eq!(
+            mnemonics(&fma, "FMA"),
+            [
+                ("vfmadd132ps", 1),
+                ("vfmadd132ss", 1),
+                ("vfmadd213ps", 1),
+                ("vfmadd213ss", 2)
+            ]
+        );
+        assert!(!fma.inst_detail["AVX"].keys().any(|m| m.starts_with("vfm")));
+        // Sandy Bridge and Bulldozer have AVX without FMA3
+        for (classification, cpus) in [
+            (avx, ("Sandy Bridge", "Bulldozer")),
+            (fma, ("Haswell", "Piledriver")),
+        ] {
+            let features: Vec<&str> = classification
+                .isa_counts
+                .keys()
+                .map(String::as_str)
+                .collect();
+            let (intel, amd) = isa::min_cpu(&features);
+            assert_eq!((intel.as_str(), amd.as_str()), cpus);
+        }
+    }
+
+    #[test]
+    fn sha_ni_under_sha() {
+        let sha = run(include_str!("../testdata/sha.objdump"));
+        assert_eq!(sha.isa_counts.keys().collect::<Vec<_>>(), ["SHA"]);
+        assert_eq!(
+            mnemonics(&sha, "SHA"),
+            [
+                ("sha1msg1", 1),
+                ("sha1msg2", 1),
+                ("sha1nexte", 1),
+                ("sha1rnds4", 1),
+                ("sha256msg1", 1),
+                ("sha256msg2", 1),
+                ("sha256rnds2", 1)
+            ]
+        );
+        assert!(sha.inst_detail["SHA"]
+            .keys()
+            .all(|m| isa::category(m) == "crypto"));
+        let (intel, amd) = isa::min_cpu(&["SHA"]);
+        assert_eq!((intel.as_str(), amd.as_str()), ("Goldmont", "Zen"));
+    }
+
+    #[test]
+    fn f16c_apart_from_avx512_fp16() {
+        // The VEX converts are F16C; EVEX on zmm is AVX-512F, and on a
+        // narrower register AVX-512VL
+        let f16c = run(include_str!("../testdata/f16c.objdump"));
+        assert_eq!(
+            mnemonics(&f16c, "F16C"),
+            [("vcvtph2ps", 2), ("vcvtps2ph", 2)]
+        );
+        assert_eq!(
+            mnemonics(&f16c, "AVX512-F"),
+            [("vcvtph2ps", 1), ("vcvtps2ph", 1)]
+        );
+        assert_eq!(mnemonics(&f16c, "AVX512-VL"), [("vcvtph2ps", 1)]);
+        assert!(!f16c.isa_counts.contains_key("AVX512-FP16"));
+
+        let fp16 = run(include_str!("../testdata/fp16.objdump"));
+        assert_eq!(fp16.isa_counts.keys().collect::<Vec<_>>(), ["AVX512-FP16"]);
+        assert_eq!(
+            mnemonics(&fp16, "AVX512-FP16"),
+            [
+                ("vaddph", 2),
+                ("vcvtph2psx", 1),
+                ("vcvtps2phx", 1),
+                ("vfmadd231ph", 1),
+                ("vmulph", 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn amx_by_subset() {
+        let mut amx = run(include_str!("../testdata/amx.objdump"));
+        let counts: Vec<(&str, usize)> = amx
+            .isa_counts
+            .iter()
+            .map(|(isa, count)| (isa.as_str(), *count))
+            .collect();
+        assert_eq!(
+            counts,
+            [
+                ("AMX-BF16", 1),
+                ("AMX-FP16", 1),
+                ("AMX-INT8", 1),
+                ("AMX-TILE", 7)
+            ]
+        );
+        assert_eq!(
+            mnemonics(&amx, "AMX-TILE"),
+            [
+                ("ldtilecfg", 1),
+                ("sttilecfg", 1),
+                ("tileloadd", 2),
+                ("tilerelease", 1),
+                ("tilestored", 1),
+                ("tilezero", 1)
+            ]
+        );
+        // AMX-FP16 arrived a generation after the rest; AMD has none
+        let min_cpu = crate::report::MinCpu::from_features(amx.cpu_features.iter().cloned());
+        assert_eq!(
+            (min_cpu.intel.as_str(), min_cpu.amd.as_str()),
+            ("Granite Rapids", "none")
+        );
+
+        // `--fail-if-found AMX` counts every subset
+        let checks = crate::policy::evaluate(
+            &amx.isa_counts,
+            &IndexMap::new(),
+            &HashMap::new(),
+            &["AMX".to_string()],
+            &[],
+        );
+        assert!(!checks[0].passed);
+        assert_eq!(checks[0].count, 10);
+
+        amx.regroup(isa::Granularity::Coarse);
+        assert_eq!(amx.isa_counts.keys().collect::<Vec<_>>(), ["AMX"]);
+    }
+
+    #[test]
+    fn mmx_register_forms_of_later_extensions() {
+        let mmx = run(include_str!("../testdata/mmx.objdump"));
+        assert_eq!(
+            mnemonics(&mmx, "MMX"),
+            [("emms", 1), ("movq", 1), ("paddw", 1), ("pmullw", 1)]
+        );
+        assert_eq!(
+            mnemonics(&mmx, "SSE"),
+            [
+                ("maskmovq", 1),
+                ("movntq", 1),
+                ("pavgb", 1),
+                ("pmovmskb", 1),
+                ("psadbw", 1),
+                ("pshufw", 1)
+            ]
+        );
+        assert_eq!(
+            mnemonics(&mmx, "SSE2"),
+            [("movq2dq", 1), ("paddq", 1), ("pmuludq", 1)]
+        );
+        assert_eq!(
+            mnemonics(&mmx, "SSSE3"),
+            [("pabsb", 1), ("palignr", 1), ("pshufb", 1)]
+        );
+        // Everything but `emms` and `movq2dq` still works on MMX state
+        assert_eq!(mmx.mmx_insts, 14);
+
+        let pairs = mmx
+            .inst_detail
+            .iter()
+            .flat_map(|(isa, detail)| detail.keys().map(move |m| (isa.as_str(), m.as_str())));
+        assert_eq!(isa::required_level(pairs).0, 2);
+        let min_cpu = crate::report::MinCpu::from_features(mmx.cpu_features.iter().cloned());
+        assert_eq!(min_cpu.intel, "Merom");
+    }
+
+    #[test]
+    fn avx512_memcpy() {
+        let c = run(include_str!("../testdata/memcpy_avx512.objdump"));
+        let counts: Vec<(&str, usize)> = c
+            .isa_counts
+            .iter()
+            .map(|(isa, count)| (isa.as_str(), *count))
+            .collect();
+        assert_eq!(counts, [("AVX512-BW", 3), ("AVX512-F", 10)]);
+        assert_eq!(
+            mnemonics(&c, "AVX512-F"),
+            [("vmovdqu64", 8), ("vmovntdq", 2)]
+        );
+        // The masked byte tail is what needs BW
+        assert_eq!(mnemonics(&c, "AVX512-BW"), [("kmovq", 1), ("vmovdqu8", 2)]);
+        assert_eq!((c.mask_usage.masked, c.mask_usage.zero_masked), (2, 1));
+
+        // Every vector operand is a full zmm register; kmovq has none
+        let widths = &c.register_widths;
+        assert_eq!((widths.xmm, widths.ymm, widths.zmm), (0, 0, 12));
+        let per_isa: Vec<(&str, usize, usize, usize)> = c
+            .vector_widths
+            .iter()
+            .map(|(isa, w)| (isa.as_str(), w.xmm, w.ymm, w.zmm))
+            .collect();
+        assert_eq!(per_isa, [("AVX512-BW", 0, 0, 2), ("AVX512-F", 0, 0, 10)]);
+        assert_eq!(c.non_temporal.mnemonics["vmovntdq"], 2);
+    }
+
+    #[test]
+    fn prefetches_reported() {
+        let c = run(include_str!("../testdata/prefetch.objdump"));
+        let prefetch =
+            crate::report::build_attributed(c.prefetch.mnemonics, c.prefetch.functions, true)
+                .unwrap();
+        let mnemonics: Vec<(&str, usize)> = prefetch
+            .mnemonics
+            .iter()
+            .map(|(mnemonic, count)| (mnemonic.as_str(), *count))
+            .collect();
+        assert_eq!(
+            mnemonics,
+            [
+                ("prefetchw", 2),
+                ("prefetchnta", 1),
+                ("prefetcht0", 1),
+                ("prefetcht1", 1),
+                ("prefetcht2", 1),
+                ("prefetchwt1", 1),
+                ("vgatherpf0dps", 1)
+            ]
+        );
+        let functions = prefetch.functions.unwrap();
+        let functions: Vec<(&str, usize)> = functions
+            .iter()
+            .map(|(function, count)| (function.as_str(), *count))
+            .collect();
+        assert_eq!(
+            functions,
+            [
+                ("sum_prefetched", 5),
+                ("prefetch_wt1", 2),
+                ("gather_prefetch", 1)
+            ]
+        );
+
+        // Only the AVX512-PF gather prefetch (and the mask set-up before it)
+        // is SIMD; the scalar hints leave `has_simd` to it
+        let counts: Vec<(&str, usize)> = c
+            .isa_counts
+            .iter()
+            .map(|(isa, count)| (isa.as_str(), *count))
+            .collect();
+        assert_eq!(counts, [("AVX512-F", 1), ("AVX512-PF", 1)]);
+        assert!(c.unclassified.is_empty());
+        let without_pf = run(&include_str!("../testdata/prefetch.objdump")
+            .lines()
+            .take_while(|line| !line.contains("<gather_prefetch>:"))
+            .collect::<Vec<_>>()
+            .join("\n"));
+        assert!(without_pf.isa_counts.is_empty());
+        assert_eq!(without_pf.prefetch.mnemonics.values().sum::<usize>(), 7);
+    }
+
+    #[test]
+    fn uppercase_intel_syntax() {
+        // `objdump -M intel` of the FMA and memcpy fixtures with every
+        // instruction line uppercased, as some vendor tools print them
+        let upper = run(include_str!("../testdata/upper_intel.objdump"));
+        let att = run(&[
+            include_str!("../testdata/mfma.objdump"),
+            include_str!("../testdata/memcpy_avx512.objdump"),
+        ]
+        .concat());
+        let counts: Vec<(&str, usize)> = upper
+            .isa_counts
+            .iter()
+            .map(|(isa, count)| (isa.as_str(), *count))
+            .collect();
+        assert_eq!(
+            counts,
+            [("AVX", 15), ("AVX512-BW", 3), ("AVX512-F", 10), ("FMA", 5)]
+        );
+        assert_eq!(upper.isa_counts, att.isa_counts);
+        assert_eq!(upper.inst_detail, att.inst_detail);
+        assert_eq!(upper.total_insts, att.total_insts);
+        assert!(upper.unclassified.is_empty());
+        assert_eq!(upper.diagnostics.skipped_lines, 0);
+
+        // `YMMWORD PTR [RDI]{K1}` is read like `(%rdi){%k1}`
+        let widths = |c: &Classification| {
+            let w = &c.register_widths;
+            (w.xmm, w.ymm, w.zmm)
+        };
+        assert_eq!(widths(&upper), widths(&att));
+        assert_eq!(
+            (upper.mask_usage.masked, upper.mask_usage.zero_masked),
+            (2, 1)
+        );
+        // Symbol names keep their case
+        assert_eq!(
+            upper.functions.keys().collect::<Vec<_>>(),
+            ["axpy", "memcpy_avx512"]
+        );
+    }
+
+    #[test]
+    fn prefer_vector_width() {
+        // The same two loops from `gcc -O3 -march=skylake-avx512`, with
+        // `-mprefer-vector-width=256` and `=512`
+        let narrow = run(include_str!("../testdata/prefer256.objdump"));
+        let wide = run(include_str!("../testdata/prefer512.objdump"));
+        let per_isa = |c: &Classification| -> Vec<(String, usize, usize, usize)> {
+            c.vector_widths
+                .iter()
+                .map(|(isa, w)| (isa.clone(), w.xmm, w.ymm, w.zmm))
+                .collect()
+        };
+        let owned = |rows: &[(&str, usize, usize, usize)]| -> Vec<(String, usize, usize, usize)> {
+            rows.iter()
+                .map(|&(isa, xmm, ymm, zmm)| (isa.to_string(), xmm, ymm, zmm))
+                .collect()
+        };
+
+        // EVEX-only instructions, all on xmm and ymm
+        assert_eq!(
+            mnemonics(&narrow, "AVX512-VL"),
+            [("vpabsq", 1), ("vpminuq", 4), ("vpsraq", 3)]
+        );
+        assert_eq!(
+            per_isa(&narrow),
+            owned(&[
+                ("AVX", 15, 3, 0),
+                ("AVX512-DQ", 0, 1, 0),
+                ("AVX512-F", 6, 2, 0)
+            ])
+        );
+        assert_eq!(narrow.avx512_widths.max_bits(), Some(256));
+        assert!(narrow.avx512_zmm.is_empty());
+
+        assert_eq!(
+            per_isa(&wide),
+            owned(&[
+                ("AVX", 15, 3, 0),
+                ("AVX512-DQ", 0, 1, 1),
+                ("AVX512-F", 6, 2, 5)
+            ])
+        );
+        assert_eq!(wide.avx512_widths.max_bits(), Some(512));
+        let mut zmm: Vec<(&str, usize)> = wide
+            .avx512_zmm
+            .iter()
+            .map(|(mnemonic, count)| (mnemonic.as_str(), *count))
+            .collect();
+        zmm.sort();
+        assert_eq!(
+            zmm,
+            [
+                ("vmovdqu64", 3),
+                ("vpabsq", 1),
+                ("vpminuq", 1),
+                ("vpmullq", 1)
+            ]
+        );
+
+        // `--fail-if-found avx512-512bit` tells them apart; plain AVX-512
+        // doesn't
+        for (c, zmm_insts) in [(&narrow, 0), (&wide, 6)] {
+            let categories =
+                HashMap::from([(isa::AVX512_512BIT.to_string(), c.avx512_zmm.clone())]);
+            let checks = crate::policy::evaluate(
+                &c.isa_counts,
+                &IndexMap::new(),
+                &categories,
+                &[isa::AVX512_512BIT.to_string(), "AVX-512".to_string()],
+                &[],
+            );
+            assert_eq!(checks[0].count, zmm_insts);
+            assert_eq!(checks[0].passed, zmm_insts == 0);
+            assert!(!checks[1].passed);
+        }
+    }
+
+    #[test]
+    fn runtime_routines_split_off() {
+        let text = [
+            include_str!("../testdata/strlen_avx2.objdump"),
+            include_str!("../testdata/memcpy_avx512.objdump"),
+            include_str!("../testdata/mfma.objdump"),
+        ]
+        .concat();
+        // `__strlen_avx2` is built in, `memcpy_avx512` from a table file
+        let routines =
+            crate::runtime::patterns(&["^memcpy_avx512$".to_string()], "a test").unwrap();
+        let split = run_with(&text, |opts| opts.runtime_routines = Some(&routines));
+        let mut runtime: Vec<&str> = split.runtime.keys().map(String::as_str).collect();
+        runtime.sort();
+        assert_eq!(runtime, ["__strlen_avx2", "memcpy_avx512"]);
+        assert_eq!(
+            split
+                .functions
+                .keys()
+                .map(String::as_str)
+                .collect::<Vec<_>>(),
+            ["axpy"]
+        );
+        assert_eq!(
+            split.isa_counts,
+            run(include_str!("../testdata/mfma.objdump")).isa_counts
+        );
+
+        // `--no-split-runtime`
+        let whole = run(&text);
+        assert!(whole.runtime.is_empty());
+        assert_eq!(whole.functions.len(), 3);
+
+        let invalid = crate::runtime::patterns(&["(".to_string()], "'isa.toml'");
+        assert_eq!(
+            invalid.unwrap_err().to_string(),
+            "Invalid runtime routine pattern in 'isa.toml'"
+        );
+    }
+
+    #[test]
+    fn pclmul_under_both_spellings() {
+        // binutils 2.40 names the immediate in the mnemonic
+        let pseudo = run(include_str!("../testdata/pclmul.objdump"));
+        assert_eq!(
+            mnemonics(&pseudo, "PCLMUL"),
+            [
+                ("pclmulhqhqdq", 2),
+                ("pclmulhqlqdq", 1),
+                ("pclmullqhqdq", 1),
+                ("pclmullqlqdq", 1),
+                ("vpclmullqlqdq", 1)
+            ]
+        );
+        assert_eq!(
+            mnemonics(&pseudo, "VPCLMULQDQ"),
+            [("vpclmulhqhqdq", 1), ("vpclmulhqlqdq", 1)]
+        );
+
+        // Other disassemblers keep the immediate operand
+        let canonical = run("\
+Disassembly of section .text:
+
+0000000000000040 <ghash>:
+  40:\tpclmulqdq $0x0,%xmm1,%xmm0
+  46:\tpclmulqdq $0x11,(%rdi),%xmm3
+  4c:\tvpclmulqdq $0x1,%xmm1,%xmm2,%xmm3
+  52:\tvpclmulqdq $0x10,%ymm1,%ymm2,%ymm3
+  58:\tret
+");
+        assert_eq!(
+            mnemonics(&canonical, "PCLMUL"),
+            [("pclmulqdq", 2), ("vpclmulqdq", 1)]
+        );
+        assert_eq!(mnemonics(&canonical, "VPCLMULQDQ"), [("vpclmulqdq", 1)]);
+    }
+
+    /// `text` as `objdump --no-show-raw-insn` would print it.
+    fn without_bytes(text: &str) -> String {
+        text.lines()
+            .map(|line| match encoding::split_raw(line) {
+                Some((_, Some(text))) => text + "\n",
+                Some((_, None)) => String::new(),
+                None => format!("{line}\n"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn gfni_by_encoding() {
+        let text = include_str!("../testdata/gfni.objdump");
+        for listing in [text.to_string(), without_bytes(text)] {
+            let classification = run(&listing);
+            let counts: Vec<(&str, usize)> = classification
+                .isa_counts
+                .iter()
+                .map(|(isa, count)| (isa.as_str(), *count))
+                .collect();
+            assert_eq!(
+                counts,
+                [("GFNI (AVX)", 2), ("GFNI (AVX-512)", 3), ("GFNI (SSE)", 3)]
+            );
+        }
+    }
+
+    #[test]
+    fn vnni_by_encoding() {
+        // objdump marks the VEX forms `{vex}`, so the split holds without
+        // the bytes too
+        let text = include_str!("../testdata/vnni.objdump");
+        for listing in [text.to_string(), without_bytes(text)] {
+            let classification = run(&listing);
+            assert_eq!(
+                mnemonics(&classification, "AVX-VNNI"),
+                [
+                    ("vpdpbusd", 1),
+                    ("vpdpbusds", 1),
+                    ("vpdpwssd", 1),
+                    ("vpdpwssds", 1)
+                ]
+            );
+            assert_eq!(
+                mnemonics(&classification, "AVX512-VNNI"),
+                [("vpdpbusd", 2), ("vpdpwssd", 1), ("vpdpwssds", 1)]
+            );
+        }
+    }
+
+    #[test]
+    fn scalar_extensions_are_not_simd() {
+        let text = include_str!("../testdata/popcnt.objdump");
+        let classification = run(text);
+        // `has_simd` is `total_simd_insts > 0`, the sum of `isa_counts`
+        assert!(classification.isa_counts.is_empty());
+        assert_eq!(classification.total_insts, 5);
+        let mut scalar: Vec<(&str, usize)> = classification
+            .scalar_detail
+            .values()
+            .flatten()
+            .map(|(mnemonic, count)| (mnemonic.as_str(), *count))
+            .collect();
+        scalar.sort_unstable();
+        assert_eq!(scalar, [("crc32", 1), ("lzcnt", 1), ("popcnt", 1)]);
+
+        let legacy = run_with(text, |opts| opts.legacy_sse4 = true);
+        assert_eq!(
+            mnemonics(&legacy, "SSE4.2"),
+            [("crc32", 1), ("lzcnt", 1), ("popcnt", 1)]
+        );
+    }
+
+    #[test]
+    fn strlen_avx2_fully_counted() {
+        // glibc 2.36's __strlen_avx2, from a static binary
+        let classification = run(include_str!("../testdata/strlen_avx2.objdump"));
+        assert_eq!(
+            mnemonics(&classification, "AVX2"),
+            [("vpcmpeqb", 10), ("vpminub", 3), ("vpmovmskb", 10)]
+        );
+        assert_eq!(
+            mnemonics(&classification, "AVX"),
+            [("vmovdqa", 2), ("vpxor", 1), ("vzeroupper", 9)]
+        );
+        assert_eq!(classification.isa_counts.values().sum::<usize>(), 35);
+        assert!(classification.unclassified.is_empty());
+    }
+
+    /// (masked, zeroing, broadcast, rounding, sae) for each instruction of
+    /// the evex fixtures, the same in either syntax
+    const EVEX_DECORATIONS: [(bool, bool, bool, bool, bool); 15] = [
+        (true, false, false, false, false),
+        (true, true, false, false, false),
+        (false, false, true, false, false),
+        (true, true, true, false, false),
+        (true, false, true, false, false),
+        (false, false, false, true, false),
+        (true, false, false, true, false),
+        (true, true, false, true, false),
+        (false, false, false, true, false),
+        (false, false, false, false, true),
+        (true, false, false, false, true),
+        (true, true, false, false, true),
+        (false, false, true, false, false),
+        (true, false, true, false, false),
+        (false, false, false, false, false),
+    ];
+
+    #[test]
+    fn every_evex_decoration_parsed() {
+        for text in [
+            include_str!("../testdata/evex_att.objdump"),
+            include_str!("../testdata/evex_intel.objdump"),
+        ] {
+            let found: Vec<_> = text
+                .lines()
+                .filter_map(|line| line.split('\t').nth(2)?.split_once(' '))
+                .map(|(_, operands)| decorations(&operands.to_lowercase()))
+                .map(|d| (d.masked, d.zeroing, d.broadcast, d.rounding, d.sae))
+                .collect();
+            assert_eq!(found, EVEX_DECORATIONS);
+
+            let classification = run(text);
+            let evex = &classification.evex_operands;
+            assert_eq!((evex.broadcast, evex.rounding, evex.sae), (5, 4, 3));
+            let masks = &classification.mask_usage;
+            assert_eq!((masks.masked, masks.zero_masked), (9, 4));
+            // `{%k1}` and `{1to16}` aren't registers, nor do they widen one
+            let widths = &classification.register_widths;
+            assert_eq!((widths.xmm, widths.ymm, widths.zmm), (1, 2, 12));
+        }
+    }
+
+    #[test]
+    fn section_filter_skips_the_rest() {
+        let only_text = [".text".to_string()];
+        let classification = run_with(SECTIONS, |opts| opts.sections = &only_text);
+        assert_eq!(
+            classification.sections.keys().collect::<Vec<_>>(),
+            [".text"]
+        );
+        assert_eq!(classification.total_insts, 5);
+        assert_eq!(classification.scanned_sections, [".text"]);
+
+        let no_init = [".init".to_string()];
+        let classification = run_with(SECTIONS, |opts| opts.exclude_sections = &no_init);
+        assert_eq!(classification.total_insts, 5);
+        assert!(!classification.inst_detail["SSE"].contains_key("movaps"));
+    }
+}
@@ -0,0 +1,203 @@
+//! Rust functions built for specific CPU features, recognized by name:
+//! hand-written `#[target_feature(enable = "avx2")]` variants such as
+//! `sum_avx2` or `simd::avx2::sum`, and the `multiversion` crate's
+//! `x86_64_avx2_fma` clones. Unlike `dispatch`, nothing marks these in the
+//! binary, so a name only counts when the symbol is Rust and at least one
+//! other symbol shares its base name.
+//!
+//! A clone whose body has no instruction from the ISAs its name claims, nor
+//! from one they imply (an `avx2` clone using only AVX is fine), is flagged:
+//! usually the attribute never reached it and it is plain baseline code.
+
+use crate::classify::FunctionCounts;
+use crate::isa::{self, Granularity};
+use crate::report::{CloneGroup, FeatureClone, FeatureClones};
+use indexmap::IndexMap;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::HashMap;
+
+/// Feature names as they appear in symbols, `#[target_feature]` spelling or
+/// without the underscore.
+const FEATURE: &str = r"(?:avx512(?:f|bw|cd|dq|vl|vnni|vbmi2?|ifma|bitalg|vpopcntdq|bf16|fp16)?|avxvnni|avx2|avx|sse4_?[12]|sse4a|ssse3|sse[23]?|fma|f16c|aes|pclmulqdq|sha|gfni|vaes|vpclmulqdq|bmi[12]|popcnt|lzcnt)";
+
+lazy_static! {
+    static ref FEATURE_RE: Regex = Regex::new(FEATURE).unwrap();
+    // A feature list, optionally after the architecture as `multiversion`
+    // writes it
+    static ref FEATURES_RE: Regex =
+        Regex::new(&format!(r"^(?:x86(?:_64)?_)?{FEATURE}(?:_{FEATURE})*$")).unwrap();
+    static ref SUFFIX_RE: Regex =
+        Regex::new(&format!(r"^(\w+?)_((?:x86(?:_64)?_)?{FEATURE}(?:_{FEATURE})*)$")).unwrap();
+    static ref RUST_HASH_RE: Regex = Regex::new(r"::h[0-9a-f]{16}$").unwrap();
+    static ref LEGACY_RUST_RE: Regex = Regex::new(r"^_ZN.*17h[0-9a-f]{16}E").unwrap();
+}
+
+/// ISA key each feature name claims; `None` for the x86-64 baseline, which
+/// every function may use, and for scalar features the per-function counts
+/// don't include.
+const CLAIMS: &[(&str, Option<&str>)] = &[
+    ("sse", None),
+    ("sse2", None),
+    ("sse3", Some("SSE3")),
+    ("ssse3", Some("SSSE3")),
+    ("sse41", Some("SSE4.1")),
+    ("sse4_1", Some("SSE4.1")),
+    ("sse42", Some("SSE4.2")),
+    ("sse4_2", Some("SSE4.2")),
+    ("sse4a", Some("SSE4a")),
+    ("avx", Some("AVX")),
+    ("avx2", Some("AVX2")),
+    ("fma", Some("FMA")),
+    ("f16c", Some("F16C")),
+    ("aes", Some("AES-NI")),
+    ("pclmulqdq", Some("PCLMUL")),
+    ("sha", Some("SHA")),
+    ("gfni", Some("GFNI")),
+    ("vaes", Some("VAES")),
+    ("vpclmulqdq", Some("VPCLMULQDQ")),
+    ("avxvnni", Some("AVX-VNNI")),
+    ("avx512", Some("AVX-512")),
+    ("avx512f", Some("AVX512-F")),
+    ("avx512bw", Some("AVX512-BW")),
+    ("avx512cd", Some("AVX512-CD")),
+    ("avx512dq", Some("AVX512-DQ")),
+    ("avx512vl", Some("AVX512-VL")),
+    ("avx512vnni", Some("AVX512-VNNI")),
+    ("avx512vbmi", Some("AVX512-VBMI")),
+    ("avx512vbmi2", Some("AVX512-VBMI2")),
+    ("avx512ifma", Some("AVX512-IFMA")),
+    ("avx512bitalg", Some("AVX512-BITALG")),
+    ("avx512vpopcntdq", Some("AVX512-VPOPCNTDQ")),
+    ("avx512bf16", Some("AVX512-BF16")),
+    ("avx512fp16", Some("AVX512-FP16")),
+    ("bmi1", None),
+    ("bmi2", None),
+    ("popcnt", None),
+    ("lzcnt", None),
+];
+
+/// ISAs whose instructions also show a claimed one took effect: enabling a
+/// feature enables the ones below it, which the compiler may use instead.
+fn evidence(claimed: &str) -> &'static [&'static str] {
+    match claimed {
+        "AVX2" | "FMA" | "F16C" | "VAES" | "VPCLMULQDQ" | "AVX-VNNI" => &["AVX"],
+        "SSE4.2" => &["SSE4.1", "SSSE3", "SSE3"],
+        "SSE4.1" => &["SSSE3", "SSE3"],
+        "SSSE3" => &["SSE3"],
+        avx512 if avx512 == "AVX-512" || isa::is_avx512(avx512) => {
+            &["AVX-512", "AVX512-F", "AVX512-VL", "AVX2", "FMA", "AVX"]
+        }
+        _ => &[],
+    }
+}
+
+/// Whether `raw` is a Rust symbol, legacy or v0 mangled.
+fn is_rust(raw: &str) -> bool {
+    raw.starts_with("_R") || LEGACY_RUST_RE.is_match(raw)
+}
+
+/// The base path and feature names of demangled Rust `name`, if it is a
+/// clone: a `_avx2`-style suffix on the last path segment, a segment that is
+/// only features (`simd::avx2::sum`, `sum::x86_64_avx2_fma`), with the
+/// segment dropped from the base.
+fn parse(name: &str) -> Option<(String, Vec<String>)> {
+    let name = RUST_HASH_RE.replace(name, "");
+    let segments: Vec<&str> = name.split("::").collect();
+    let (last, parents) = segments.split_last()?;
+    let features = |list: &str| -> Vec<String> {
+        FEATURE_RE
+            .find_iter(
+                list.trim_start_matches("x86_64_")
+                    .trim_start_matches("x86_"),
+            )
+            .map(|m| m.as_str().to_string())
+            .collect()
+    };
+    if FEATURES_RE.is_match(last) {
+        return Some((parents.join("::"), features(last)));
+    }
+    if let Some(captures) = SUFFIX_RE.captures(last) {
+        let mut base = parents.to_vec();
+        base.push(captures.get(1)?.as_str());
+        return Some((base.join("::"), features(&captures[2])));
+    }
+    let index = parents
+        .iter()
+        .rposition(|segment| FEATURES_RE.is_match(segment))?;
+    let mut base = segments.clone();
+    let list = base.remove(index);
+    Some((base.join("::"), features(list)))
+}
+
+/// Group `functions` (keyed by demangled name) into clone sets; `None` when
+/// there are none. Counts are at `granularity`.
+pub fn find(
+    functions: &IndexMap<String, FunctionCounts>,
+    mangled: &HashMap<String, String>,
+    granularity: Granularity,
+) -> Option<FeatureClones> {
+    let rust: Vec<&String> = functions
+        .keys()
+        .filter(|name| mangled.get(*name).is_some_and(|raw| is_rust(raw)))
+        .collect();
+    let mut groups: IndexMap<String, Vec<(&String, Vec<String>)>> = IndexMap::new();
+    for &name in &rust {
+        if let Some((base, features)) = parse(name) {
+            groups.entry(base).or_default().push((name, features));
+        }
+    }
+    // The function the clones stand in for, when it has its own symbol
+    for &name in &rust {
+        let base = RUST_HASH_RE.replace(name, "");
+        if let Some(clones) = groups.get_mut(base.as_ref()) {
+            clones.push((name, Vec::new()));
+        }
+    }
+    groups.retain(|_, clones| clones.len() > 1);
+    if groups.is_empty() {
+        return None;
+    }
+
+    let mut flagged = 0;
+    let groups: Vec<CloneGroup> = groups
+        .into_iter()
+        .map(|(function, clones)| CloneGroup {
+            function,
+            clones: clones
+                .into_iter()
+                .map(|(symbol, features)| {
+                    let counts = &functions[symbol].isa_counts;
+                    let found = |key: &str| {
+                        let key = isa::report_key(key, granularity);
+                        counts.keys().any(|isa| isa::covers(key, isa))
+                    };
+                    let mut claimed: Vec<String> = features
+                        .iter()
+                        .filter_map(|feature| CLAIMS.iter().find(|(name, _)| name == feature)?.1)
+                        .map(|key| isa::report_key(key, granularity).to_string())
+                        .collect();
+                    // `avx2_fma` claims AVX twice at coarse granularity
+                    claimed.dedup();
+                    let missing: Vec<String> =
+                        claimed.iter().filter(|key| !found(key)).cloned().collect();
+                    let took = claimed
+                        .iter()
+                        .any(|key| found(key) || evidence(key).iter().any(|key| found(key)));
+                    let suspect = !claimed.is_empty() && !took;
+                    flagged += usize::from(suspect);
+                    FeatureClone {
+                        symbol: symbol.clone(),
+                        features,
+                        claimed_isas: claimed,
+                        total_simd_insts: counts.values().sum(),
+                        isa_summary: counts.clone(),
+                        missing_isas: missing,
+                        flagged: suspect,
+                    }
+                })
+                .collect(),
+        })
+        .collect();
+    Some(FeatureClones { groups, flagged })
+}
@@ -0,0 +1,204 @@
+//! `--target-cpu` / `--target-features` / `--check-cpu`: which instructions
+//! found would raise #UD on another CPU, and in which functions.
+//!
+//! A microarchitecture's features come from the same data as `min_cpu`
+//! (see `isa::target_cpu`). Only instructions simdscan classifies are
+//! checked, so BMI1/BMI2 need `--include-scalar-ext` and TSX and friends
+//! their `--extensions` group; keys a custom ISA table adds have no known
+//! CPUID feature and are never reported.
+
+use crate::host;
+use crate::isa::{self, Granularity};
+use crate::report::{self, Compatibility, FaultingIsa, InstAddress};
+use anyhow::{bail, Result};
+use indexmap::IndexMap;
+use std::collections::{BTreeSet, HashMap};
+
+/// What the target CPU has.
+pub struct Target {
+    /// Canonical microarchitecture or psABI level name, or `features`
+    pub name: String,
+    pub features: BTreeSet<&'static str>,
+}
+
+impl Target {
+    /// A CPU by name: a psABI level, microarchitecture or `-march` name.
+    pub fn cpu(name: &str) -> Result<Self> {
+        let Some((canonical, features)) = isa::target_cpu(name) else {
+            bail!(
+                "Unknown --target-cpu '{name}'; known: {}",
+                isa::target_cpu_names().join(", ")
+            );
+        };
+        Ok(Target {
+            name: canonical.to_string(),
+            features: features.into_iter().collect(),
+        })
+    }
+
+    /// An explicit feature list, on top of the x86-64 baseline.
+    pub fn features(names: &[String]) -> Result<Self> {
+        let (_, mut features) = isa::target_cpu("x86-64").expect("x86-64 is a known level");
+        for name in names {
+            let Some(feature) = isa::parse_feature(name) else {
+                bail!("Unknown --target-features entry '{name}'");
+            };
+            features.push(feature);
+        }
+        Ok(Target {
+            name: "features".to_string(),
+            features: features.into_iter().collect(),
+        })
+    }
+
+    /// The CPU simdscan runs on, per `host`.
+    pub fn host() -> Result<Self> {
+        Ok(Target {
+            name: "host".to_string(),
+            features: host::features()?.into_iter().collect(),
+        })
+    }
+}
+
+/// Faulting instructions of one ISA key.
+#[derive(Default)]
+pub struct Faults {
+    pub count: usize,
+    pub guarded: usize,
+    pub missing: BTreeSet<&'static str>,
+    pub addresses: Vec<InstAddress>,
+    pub functions: HashMap<String, usize>,
+}
+
+impl Faults {
+    fn merge(&mut self, other: Faults) {
+        self.count += other.count;
+        self.guarded += other.guarded;
+        self.missing.extend(other.missing);
+        self.addresses.extend(other.addresses);
+        for (function, count) in other.functions {
+            *self.functions.entry(function).or_insert(0) += count;
+        }
+    }
+}
+
+/// One faulting instruction, as classify sees it.
+pub struct Fault<'a> {
+    pub isa: &'a str,
+    pub address: u64,
+    /// Enclosing symbol and its start address
+    pub symbol: Option<&'a (String, u64)>,
+    pub function: &'a str,
+    /// Inside a guarded dispatch variant
+    pub guarded: bool,
+}
+
+/// Collects faulting instructions during classification.
+pub struct Tracker<'a> {
+    target: &'a Target,
+    samples: usize,
+    pub faults: IndexMap<String, Faults>,
+}
+
+impl<'a> Tracker<'a> {
+    /// Keep up to `samples` addresses per ISA key, before regrouping.
+    pub fn new(target: &'a Target, samples: usize) -> Self {
+        Tracker {
+            target,
+            samples,
+            faults: IndexMap::new(),
+        }
+    }
+
+    /// Check one instruction needing `needed`. Features the data doesn't
+    /// know (custom table keys) are taken as present.
+    pub fn check<'f>(&mut self, needed: impl IntoIterator<Item = &'f str>, fault: Fault) {
+        let missing: Vec<&'static str> = needed
+            .into_iter()
+            .filter(|feature| !self.target.features.contains(feature))
+            .filter_map(isa::known_feature)
+            .collect();
+        if missing.is_empty() {
+            return;
+        }
+        let faults = self.faults.entry(fault.isa.to_string()).or_default();
+        faults.count += 1;
+        faults.guarded += usize::from(fault.guarded);
+        faults.missing.extend(missing);
+        if faults.addresses.len() < self.samples {
+            faults.addresses.push(InstAddress {
+                address: format!("{:x}", fault.address),
+                location: fault
+                    .symbol
+                    .map(|(name, start)| format!("{name}+{:#x}", fault.address - start)),
+            });
+        }
+        *faults
+            .functions
+            .entry(fault.function.to_string())
+            .or_insert(0) += 1;
+    }
+}
+
+/// Merge `faults` under the report keys of `granularity`.
+pub fn regroup(faults: &mut IndexMap<String, Faults>, granularity: Granularity) {
+    let mut merged: IndexMap<String, Faults> = IndexMap::new();
+    for (isa, faults) in faults.drain(..) {
+        merged
+            .entry(isa::report_key(&isa, granularity).to_string())
+            .or_default()
+            .merge(faults);
+    }
+    *faults = merged;
+}
+
+/// The `compatibility` section, keeping the lowest `samples` addresses per
+/// ISA. Guarded faults only count against `compatible` without
+/// `assume_dispatch`.
+pub fn build(
+    target: &Target,
+    faults: IndexMap<String, Faults>,
+    samples: usize,
+    assume_dispatch: bool,
+) -> Compatibility {
+    let mut missing: BTreeSet<&str> = BTreeSet::new();
+    let mut functions: HashMap<String, usize> = HashMap::new();
+    let mut isas: Vec<(String, FaultingIsa)> = Vec::new();
+    let mut blocking = 0;
+    for (isa, mut faults) in faults {
+        // Regrouping appends one fine key's samples after another's
+        faults
+            .addresses
+            .sort_by_cached_key(|sample| u64::from_str_radix(&sample.address, 16).unwrap_or(0));
+        faults.addresses.truncate(samples);
+        missing.extend(&faults.missing);
+        for (function, count) in &faults.functions {
+            *functions.entry(function.clone()).or_insert(0) += count;
+        }
+        blocking += if assume_dispatch {
+            faults.count - faults.guarded
+        } else {
+            faults.count
+        };
+        isas.push((
+            isa,
+            FaultingIsa {
+                count: faults.count,
+                in_dispatch_variants: faults.guarded,
+                missing_features: faults.missing.iter().map(|f| f.to_string()).collect(),
+                addresses: faults.addresses,
+                functions: report::by_count(faults.functions),
+            },
+        ));
+    }
+    isas.sort_by(|a, b| b.1.count.cmp(&a.1.count).then_with(|| a.0.cmp(&b.0)));
+    Compatibility {
+        target: target.name.clone(),
+        target_features: target.features.iter().map(|f| f.to_string()).collect(),
+        compatible: blocking == 0,
+        missing_features: missing.into_iter().map(str::to_string).collect(),
+        faulting_insts: isas.iter().map(|(_, isa)| isa.count).sum(),
+        isas: isas.into_iter().collect(),
+        functions: report::by_count(functions),
+    }
+}
@@ -0,0 +1,71 @@
+//! Shared libraries a binary links against (ELF `DT_NEEDED`, Mach-O
+//! `LC_LOAD_DYLIB`, PE imports), with the ones known to carry their own
+//! SIMD kernels marked. A binary that hands its vector math to such a
+//! library shows little SIMD itself; the mark says the picture is partial.
+
+use crate::report::Dependency;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+/// `(what, pattern)`: kinds of SIMD-heavy library and the file names they
+/// go by, matched lowercased and without directories.
+const PROVIDERS: &[(&str, &str)] = &[
+    (
+        "BLAS/LAPACK",
+        r"^(?:lib)?(?:openblas|c?blas|lapacke?|blis|flame|[st]?atlas|mkl_\w+|accelerate)\b",
+    ),
+    (
+        "vector math",
+        r"^(?:lib)?(?:mvec|sleef\w*|svml|imf|amdlibm|alm|yeppp)\b",
+    ),
+    ("Intel IPP", r"^(?:lib)?ipp\w*\b"),
+    ("oneDNN", r"^(?:lib)?(?:dnnl|mkldnn)\b"),
+    ("FFT", r"^(?:lib)?fftw3\w*\b"),
+    ("VOLK", r"^(?:lib)?volk\b"),
+    ("simdjson", r"^(?:lib)?simdjson\b"),
+    ("Highway", r"^(?:lib)?hwy\w*\b"),
+    ("ISA-L", r"^(?:lib)?isal\w*\b"),
+    ("Hyperscan", r"^(?:lib)?(?:hs|hs_runtime|vectorscan)\b"),
+    ("crypto", r"^(?:lib)?(?:crypto|sodium|gcrypt|nettle)\b"),
+    ("compression", r"^(?:lib)?(?:z-ng|zstd|lz4)\b"),
+    (
+        "media codec",
+        r"^(?:lib)?(?:turbojpeg|jpeg|png16|webp|x264|x265|dav1d|aom|vpx|avcodec|swscale|openh264)\b",
+    ),
+    (
+        "ML runtime",
+        r"^(?:lib)?(?:onnxruntime|torch_cpu|tensorflow\w*|xnnpack|ggml\w*|llama)\b",
+    ),
+    ("OpenCV", r"^(?:lib)?opencv_\w+\b"),
+];
+
+lazy_static! {
+    static ref PROVIDER_RES: Vec<(&'static str, Regex)> = PROVIDERS
+        .iter()
+        .map(|&(what, pattern)| (what, Regex::new(pattern).unwrap()))
+        .collect();
+}
+
+/// What kind of SIMD provider library `name` is, if a known one.
+fn provider(name: &str) -> Option<&'static str> {
+    let file = name
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or(name)
+        .to_lowercase();
+    PROVIDER_RES
+        .iter()
+        .find(|(_, re)| re.is_match(&file))
+        .map(|&(what, _)| what)
+}
+
+/// The `dependencies` section for `libraries`, in link order.
+pub fn list(libraries: &[String]) -> Vec<Dependency> {
+    libraries
+        .iter()
+        .map(|name| Dependency {
+            name: name.clone(),
+            simd_provider: provider(name).map(str::to_string),
+        })
+        .collect()
+}
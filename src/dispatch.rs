@@ -0,0 +1,69 @@
+//! Function multiversioning: symbols that are ISA-specific variants of one
+//! function, one of which a resolver picks at load time from CPUID.
+//!
+//! Three spellings are recognized:
+//! - GCC/Clang `target_clones` and `target` clones: `foo.avx2.0`,
+//!   `foo.arch_skylake_avx512.1`, `foo.default.2`, picked by `foo.resolver`
+//! - glibc's hand-written variants: `__memmove_avx_unaligned_erms`,
+//!   `__strlen_evex`, `__strchr_sse2`
+//! - `STT_GNU_IFUNC` symbols (`memmove` in a static binary), whose code is
+//!   the resolver itself
+//!
+//! `default` and resolver code runs on any CPU; every other variant is
+//! guarded by the resolver's CPUID check.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+/// Target of the variant every CPU may run when no other one matches.
+pub const DEFAULT: &str = "default";
+/// Target of resolver code.
+pub const RESOLVER: &str = "resolver";
+
+lazy_static! {
+    // The clone number after the target is optional: `target` clones and
+    // Clang's `target_clones` have none
+    static ref CLONE_RE: Regex = Regex::new(
+        r"^(.+?)\.(default|resolver|ifunc|arch_[\w-]+|(?:sse|ssse|avx|fma|bmi|popcnt|lzcnt|f16c|aes|pclmul|sha|vaes|vpclmul|gfni|movbe|amx|x86[-_]64)[\w-]*)(?:\.\d+)?$"
+    )
+    .unwrap();
+    static ref GLIBC_RE: Regex = Regex::new(
+        r"^__(\w+?)_((?:sse2|ssse3|sse4_1|sse4_2|sse42|avx|avx2|avx512[a-z]*|evex\d*)(?:_\w+)?)$"
+    )
+    .unwrap();
+}
+
+/// One variant of a multiversioned function.
+pub struct Variant<'a> {
+    /// Raw symbol of the function the variant belongs to
+    pub base: &'a str,
+    /// What it was built for: `avx2`, `arch_skylake_avx512`,
+    /// `evex_unaligned_erms`, [`DEFAULT`] or [`RESOLVER`]
+    pub target: &'a str,
+}
+
+impl Variant<'_> {
+    /// Whether only CPUs that passed the resolver's check run this variant.
+    pub fn guarded(&self) -> bool {
+        self.target != DEFAULT && self.target != RESOLVER
+    }
+}
+
+/// The variant `symbol` is, if any; `ifuncs` are the binary's
+/// `STT_GNU_IFUNC` symbol names.
+pub fn variant<'a>(symbol: &'a str, ifuncs: &[String]) -> Option<Variant<'a>> {
+    if ifuncs.iter().any(|ifunc| ifunc == symbol) {
+        return Some(Variant {
+            base: symbol,
+            target: RESOLVER,
+        });
+    }
+    let captures = CLONE_RE
+        .captures(symbol)
+        .or_else(|| GLIBC_RE.captures(symbol))?;
+    let (base, target) = (captures.get(1)?.as_str(), captures.get(2)?.as_str());
+    Some(Variant {
+        base,
+        target: if target == "ifunc" { RESOLVER } else { target },
+    })
+}
@@ -0,0 +1,201 @@
+//! Instruction encoding (legacy, VEX, EVEX), from objdump's raw byte column
+//! when it is there and from the instruction text otherwise.
+
+use crate::isa;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::borrow::Cow;
+
+lazy_static! {
+    // `  1f:\tc5 f9 6f 07          \tvmovdqa (%rdi),%xmm0`. A continuation line
+    // (bytes only, no instruction) ends right after the bytes. Hex digits in
+    // either case, since this runs before `classify` lowercases the line.
+    static ref RAW_RE: Regex =
+        Regex::new(r"^(\s*[0-9a-fA-F]+:)\t((?:[0-9a-fA-F]{2} )+)\s*(?:\t(.*))?$").unwrap();
+    // Registers 16-31 and operand decorations (opmask, zeroing, broadcast,
+    // rounding, SAE) only exist with EVEX. Operands arrive lowercased, so
+    // Intel's `BCST` is `bcst` here.
+    static ref EVEX_OPERAND_RE: Regex = Regex::new(
+        r"\b[xyz]mm(1[6-9]|2[0-9]|3[01])\b|\{(%?k[1-7]|z|1to\d+|r[nduz]-sae|sae)\}|\bbcst\b"
+    )
+    .unwrap();
+}
+
+/// How an instruction was encoded.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// Legacy SSE encoding, no `v` prefix
+    Legacy,
+    /// VEX, and AMD's XOP which shares its layout
+    Vex,
+    Evex,
+}
+
+/// Split the raw byte column off an instruction line. `None` when the line
+/// has no byte column (`--no-show-raw-insn` or not an instruction line);
+/// otherwise the bytes and the line without them (`addr:\tmnemonic operands`),
+/// which is itself `None` on a continuation line holding only the tail of a
+/// long instruction's bytes.
+pub fn split_raw(line: &str) -> Option<(Vec<u8>, Option<String>)> {
+    let captures = RAW_RE.captures(line)?;
+    let bytes = captures[2]
+        .split_whitespace()
+        .filter_map(|byte| u8::from_str_radix(byte, 16).ok())
+        .collect();
+    let text = captures
+        .get(3)
+        .map(|insn| format!("{}\t{}", &captures[1], insn.as_str()));
+    Some((bytes, text))
+}
+
+/// One line of a listing, split by [`split_raw`] once for both the
+/// suspected-data pass (`text_data`) and classification.
+pub struct Split<'a> {
+    /// The raw bytes, empty without a byte column
+    pub bytes: Vec<u8>,
+    /// The line without its bytes, lowercased; `None` on a continuation line
+    pub text: Option<Cow<'a, str>>,
+}
+
+/// Every line of `lines` split. Everything downstream matches lowercase:
+/// objdump's Intel syntax has `XMMWORD PTR` in its operands, and other tools
+/// print `VADDPS` or uppercase hex. Symbol headers are read from the original
+/// lines, so their names keep their case.
+pub fn split_lines(lines: &[String]) -> Vec<Split<'_>> {
+    fn lowercase(text: Cow<'_, str>) -> Cow<'_, str> {
+        if text.bytes().any(|b| b.is_ascii_uppercase()) {
+            Cow::Owned(text.to_ascii_lowercase())
+        } else {
+            text
+        }
+    }
+    lines
+        .iter()
+        .map(|line| match split_raw(line) {
+            Some((bytes, text)) => Split {
+                bytes,
+                text: text.map(|text| lowercase(Cow::Owned(text))),
+            },
+            None => Split {
+                bytes: Vec::new(),
+                text: Some(lowercase(Cow::Borrowed(line.as_str()))),
+            },
+        })
+        .collect()
+}
+
+/// Legacy prefixes that may precede a VEX/EVEX escape byte.
+const LEGACY_PREFIXES: &[u8] = &[
+    0x26, 0x2e, 0x36, 0x3e, 0x64, 0x65, 0x66, 0x67, 0xf0, 0xf2, 0xf3,
+];
+
+/// Encoding from the instruction's bytes. `c4`/`c5`/`62` also start
+/// LES/LDS/BOUND in 32-bit code, but those never reach SIMD classification.
+pub fn from_bytes(bytes: &[u8]) -> Option<Encoding> {
+    let mut rest = bytes
+        .iter()
+        .copied()
+        .skip_while(|byte| LEGACY_PREFIXES.contains(byte))
+        // REX
+        .skip_while(|byte| (0x40..=0x4f).contains(byte));
+    let first = rest.next()?;
+    Some(match first {
+        0xc4 | 0xc5 => Encoding::Vex,
+        0x62 => Encoding::Evex,
+        // XOP's map select is at least 8; below that it is a `pop r/m`
+        0x8f if rest.next().is_some_and(|byte| byte & 0x1f >= 8) => Encoding::Vex,
+        _ => Encoding::Legacy,
+    })
+}
+
+/// Best guess from the instruction text alone, for listings without raw
+/// bytes. `prefix` is the text before the mnemonic, where objdump puts
+/// `{vex}` / `{evex}` markers.
+pub fn from_text(mnemonic: &str, prefix: &str, operands: &str, widest: Option<u8>) -> Encoding {
+    if prefix.contains("{vex}") {
+        Encoding::Vex
+    } else if prefix.contains("{evex}")
+        || widest == Some(b'z')
+        || EVEX_OPERAND_RE.is_match(operands)
+        || isa::EVEX_BY_DEFAULT.contains(&mnemonic)
+    {
+        Encoding::Evex
+    } else if mnemonic.starts_with('v') {
+        Encoding::Vex
+    } else {
+        Encoding::Legacy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encoding of a captured objdump line, through its raw bytes.
+    fn encoding(line: &str) -> Option<Encoding> {
+        let (bytes, _) = split_raw(line)?;
+        from_bytes(&bytes)
+    }
+
+    #[test]
+    fn splits_bytes_from_text() {
+        let (bytes, text) =
+            split_raw("   4:\tc5 f9 6f 07          \tvmovdqa (%rdi),%xmm0").unwrap();
+        assert_eq!(bytes, [0xc5, 0xf9, 0x6f, 0x07]);
+        assert_eq!(text.as_deref(), Some("   4:\tvmovdqa (%rdi),%xmm0"));
+        // Uppercase hex, as some tools print it
+        let (bytes, _) =
+            split_raw("  1D:\tC4 41 29 FE D9       \tVPADDD XMM11,XMM10,XMM9").unwrap();
+        assert_eq!(bytes, [0xc4, 0x41, 0x29, 0xfe, 0xd9]);
+        // `--no-show-raw-insn` and non-instruction lines have no byte column
+        assert!(split_raw("   4:\tvmovdqa (%rdi),%xmm0").is_none());
+        assert!(split_raw("0000000000000000 <f>:").is_none());
+    }
+
+    #[test]
+    fn continuation_line_has_no_text() {
+        // objdump's default --insn-width wraps this 11-byte EVEX instruction
+        let first =
+            "   e:\t62 f1 74 c9 58 94 98 \tvaddps 0x12345678(%rax,%rbx,4),%zmm1,%zmm2{%k1}{z}";
+        let (bytes, text) = split_raw(first).unwrap();
+        assert_eq!(bytes.len(), 7);
+        assert!(text
+            .unwrap()
+            .ends_with("vaddps 0x12345678(%rax,%rbx,4),%zmm1,%zmm2{%k1}{z}"));
+        let (bytes, text) = split_raw("  15:\t78 56 34 12 ").unwrap();
+        assert_eq!(bytes, [0x78, 0x56, 0x34, 0x12]);
+        assert_eq!(text, None);
+    }
+
+    #[test]
+    fn encoding_from_bytes() {
+        let lines = [
+            ("   0:\t66 0f 6f 07          \tmovdqa (%rdi),%xmm0", Encoding::Legacy),
+            ("   0:\t66 41 0f 6f 07       \tmovdqa (%r15),%xmm0", Encoding::Legacy),
+            ("  19:\tf0 83 07 01          \tlock addl $0x1,(%rdi)", Encoding::Legacy),
+            ("   4:\tc5 f9 6f 07          \tvmovdqa (%rdi),%xmm0", Encoding::Vex),
+            ("   8:\tc4 e3 fd 00 d1 4e    \tvpermq $0x4e,%ymm1,%ymm2", Encoding::Vex),
+            ("  1d:\tc4 41 29 fe d9       \tvpaddd %xmm9,%xmm10,%xmm11", Encoding::Vex),
+            (
+                "   e:\t62 f1 74 c9 58 94 98 78 56 34 12 \tvaddps 0x12345678(%rax,%rbx,4),%zmm1,%zmm2{%k1}{z}",
+                Encoding::Evex,
+            ),
+            ("  25:\t62 a2 6d 20 cf d9    \tvgf2p8mulb %ymm17,%ymm18,%ymm19", Encoding::Evex),
+            ("   0:\t8f e8 78 c0 c8 03    \tvprotb $0x3,%xmm0,%xmm1", Encoding::Vex),
+            ("   0:\t8f 07                \tpop    (%rdi)", Encoding::Legacy),
+        ];
+        for (line, expected) in lines {
+            assert!(encoding(line) == Some(expected), "{line}");
+        }
+    }
+
+    #[test]
+    fn encoding_from_text() {
+        assert!(from_text("vmovdqa", "", "(%rdi),%xmm0", Some(b'x')) == Encoding::Vex);
+        assert!(from_text("vpdpbusd", "{vex} ", "%xmm0,%xmm1,%xmm2", Some(b'x')) == Encoding::Vex);
+        assert!(from_text("vaddps", "", "%zmm1,%zmm2,%zmm3", Some(b'z')) == Encoding::Evex);
+        assert!(from_text("vpaddd", "", "%xmm17,%xmm1,%xmm2", Some(b'x')) == Encoding::Evex);
+        assert!(from_text("vaddps", "", "(%rax){1to8},%ymm1,%ymm2", Some(b'y')) == Encoding::Evex);
+        assert!(from_text("movdqa", "", "(%rdi),%xmm0", Some(b'x')) == Encoding::Legacy);
+    }
+}
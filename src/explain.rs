@@ -0,0 +1,172 @@
+//! `explain <mnemonic>`: where a mnemonic lands in the ISA table and what
+//! running it requires.
+
+use crate::encoding::Encoding;
+use crate::isa::{self, IsaIndex, IsaTable};
+use crate::list::ListFormat;
+use anyhow::Result;
+use indexmap::IndexMap;
+use serde::Serialize;
+use std::fmt::Write;
+
+#[derive(Serialize)]
+struct Explanation {
+    mnemonic: String,
+    /// ISA key it is counted under with plain (xmm or no vector) operands
+    isa: Option<&'static str>,
+    description: Option<&'static str>,
+    /// CPUID features needed, as used for `min_cpu`
+    features: Vec<String>,
+    intel: Option<String>,
+    amd: Option<String>,
+    /// psABI level name, e.g. `x86-64-v3`
+    level: Option<String>,
+    /// Other keys the mnemonic is counted under depending on its operands or
+    /// encoding, e.g. `{"ymm": "AVX2"}`
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    variants: IndexMap<&'static str, &'static str>,
+    /// For mnemonics missing from the table, what its spelling suggests
+    #[serde(skip_serializing_if = "Option::is_none")]
+    guess: Option<String>,
+}
+
+/// The table key `mnemonic` is classified under with default options.
+fn lookup(index: &IsaIndex, mnemonic: &str) -> Option<&'static str> {
+    let scalar = |sse4: bool| {
+        isa::SCALAR_TABLE
+            .iter()
+            .find(|(ext, mset)| isa::was_sse4(ext) == sse4 && mset.contains(mnemonic))
+            .map(|(&ext, _)| ext)
+    };
+    let other = || {
+        isa::OTHER_TABLE
+            .iter()
+            .find(|(_, mset)| mset.contains(mnemonic))
+            .map(|(&ext, _)| ext)
+    };
+    scalar(true)
+        .or_else(|| isa::lookup(index, mnemonic))
+        .or_else(|| scalar(false))
+        .or_else(other)
+}
+
+/// What a mnemonic outside the table probably is, from its spelling alone.
+fn guess(mnemonic: &str) -> String {
+    let kind = if mnemonic.starts_with('k') {
+        "an AVX-512 opmask instruction"
+    } else if isa::looks_vector(mnemonic, false) {
+        "a VEX/EVEX vector instruction (AVX or later)"
+    } else {
+        match isa::shape(mnemonic) {
+            Some(isa::Shape::PackedFp) => "a packed floating-point SSE instruction",
+            Some(isa::Shape::ScalarFp) => "a scalar floating-point SSE instruction",
+            Some(isa::Shape::PackedInt) => "a packed integer MMX/SSE instruction",
+            None => "not a SIMD instruction",
+        }
+    };
+    format!("not in table; looks like {kind}")
+}
+
+fn explain_one(index: &IsaIndex, mnemonic: &str) -> Explanation {
+    let mut mnemonic = mnemonic.to_lowercase();
+    let bare = isa::strip_size_suffix(&mnemonic, |m| isa::is_known(index, m)).len();
+    mnemonic.truncate(bare);
+    let Some(key) = lookup(index, &mnemonic) else {
+        return Explanation {
+            description: isa::describe(&mnemonic),
+            guess: Some(guess(&mnemonic)),
+            mnemonic,
+            isa: None,
+            features: Vec::new(),
+            intel: None,
+            amd: None,
+            level: None,
+            variants: IndexMap::new(),
+        };
+    };
+
+    let vector = mnemonic.starts_with('v');
+    let plain = if isa::EVEX_BY_DEFAULT.contains(&mnemonic.as_str()) {
+        Encoding::Evex
+    } else if vector {
+        Encoding::Vex
+    } else {
+        Encoding::Legacy
+    };
+    let base = isa::refine_key(key, &mnemonic, None, plain);
+
+    // Only VEX/EVEX mnemonics come in other widths and encodings
+    let mut forms = Vec::new();
+    if vector {
+        forms.push(("ymm", Some(b'y'), plain));
+        forms.push(("zmm", Some(b'z'), Encoding::Evex));
+    }
+    if plain == Encoding::Evex {
+        forms.push(("{vex}", Some(b'y'), Encoding::Vex));
+    }
+    let mut variants = IndexMap::new();
+    for (label, widest, encoding) in forms {
+        let refined = isa::refine_key(key, &mnemonic, widest, encoding);
+        if refined != base && !variants.values().any(|v| *v == refined) {
+            variants.insert(label, refined);
+        }
+    }
+
+    let mut features = vec![isa::cpu_feature(base, &mnemonic).to_string()];
+    features.extend(
+        isa::implied_features(base, &mnemonic, None)
+            .iter()
+            .map(|f| f.to_string()),
+    );
+    let feature_refs: Vec<&str> = features.iter().map(String::as_str).collect();
+    let (intel, amd) = isa::min_cpu(&feature_refs);
+
+    Explanation {
+        description: isa::describe(&mnemonic),
+        level: isa::mnemonic_level(base, &mnemonic).map(isa::level_name),
+        mnemonic,
+        isa: Some(base),
+        features,
+        intel: Some(intel),
+        amd: Some(amd),
+        variants,
+        guess: None,
+    }
+}
+
+pub fn render(table: &IsaTable, mnemonic: &str, format: ListFormat) -> Result<String> {
+    let explanation = explain_one(&isa::index(table), mnemonic);
+    if format == ListFormat::Json {
+        return Ok(serde_json::to_string_pretty(&explanation)? + "\n");
+    }
+
+    let mut out = String::new();
+    let _ = writeln!(out, "{}", explanation.mnemonic);
+    let fields = [
+        ("isa", explanation.isa.map(str::to_string)),
+        ("description", explanation.description.map(str::to_string)),
+        (
+            "features",
+            (!explanation.features.is_empty()).then(|| explanation.features.join(", ")),
+        ),
+        (
+            "first CPUs",
+            explanation
+                .intel
+                .as_ref()
+                .zip(explanation.amd.as_ref())
+                .map(|(intel, amd)| format!("Intel {intel} / AMD {amd}")),
+        ),
+        ("psABI level", explanation.level.clone()),
+        ("guess", explanation.guess.clone()),
+    ];
+    for (name, value) in fields {
+        if let Some(value) = value {
+            let _ = writeln!(out, "  {name:<12} {value}");
+        }
+    }
+    for (form, isa) in &explanation.variants {
+        let _ = writeln!(out, "  {:<12} {isa}", format!("with {form}"));
+    }
+    Ok(out)
+}
@@ -0,0 +1,191 @@
+//! The x86 ISA and feature bits toolchains record in an ELF file's
+//! `.note.gnu.property`, checked against what the disassembly shows.
+//!
+//! Only the binutils 2.35+ encoding is read: `ISA_1_USED`/`ISA_1_NEEDED` as
+//! psABI level bits and `FEATURE_2_USED`/`FEATURE_2_NEEDED` as register
+//! state bits. The linker drops a `USED` property unless every input object
+//! has one, so linked programs often carry only `ISA_1_NEEDED`.
+
+use crate::isa;
+use crate::report::{GnuProperty, MismatchKind, PropertyMismatch};
+
+/// `n_type` of the note.
+pub const NT_GNU_PROPERTY_TYPE_0: u32 = 5;
+
+const ISA_1_NEEDED: u32 = 0xc000_8002;
+const FEATURE_2_NEEDED: u32 = 0xc000_8001;
+const ISA_1_USED: u32 = 0xc001_0002;
+const FEATURE_2_USED: u32 = 0xc001_0001;
+
+/// `FEATURE_2` bits that the disassembly can confirm or refute, by name.
+const FEATURES: &[(u32, &str)] = &[
+    (1 << 1, "x87"),
+    (1 << 2, "mmx"),
+    (1 << 3, "xmm"),
+    (1 << 4, "ymm"),
+    (1 << 5, "zmm"),
+    (1 << 11, "mask"),
+];
+
+/// The x86 properties of one file, as raw bit sets.
+#[derive(Default, Clone, Copy)]
+pub struct Declared {
+    /// Whether the file has a GNU property note at all
+    pub found: bool,
+    pub isa_used: Option<u32>,
+    pub isa_needed: Option<u32>,
+    pub features_used: Option<u32>,
+    pub features_needed: Option<u32>,
+}
+
+impl Declared {
+    /// Read the properties from one note's descriptor: `(type, size, data)`
+    /// records, each padded to 8 bytes in 64-bit files and 4 in 32-bit ones.
+    pub fn add_note(&mut self, desc: &[u8], is_64: bool, little_endian: bool) {
+        self.found = true;
+        let align = if is_64 { 8 } else { 4 };
+        let word = |bytes: &[u8]| {
+            let bytes: [u8; 4] = bytes.try_into().unwrap();
+            if little_endian {
+                u32::from_le_bytes(bytes)
+            } else {
+                u32::from_be_bytes(bytes)
+            }
+        };
+        let mut rest = desc;
+        while rest.len() >= 8 {
+            let kind = word(&rest[..4]);
+            let size = word(&rest[4..8]) as usize;
+            let Some(data) = rest.get(8..8 + size) else {
+                break;
+            };
+            if size == 4 {
+                let value = Some(word(data));
+                match kind {
+                    ISA_1_USED => self.isa_used = value,
+                    ISA_1_NEEDED => self.isa_needed = value,
+                    FEATURE_2_USED => self.features_used = value,
+                    FEATURE_2_NEEDED => self.features_needed = value,
+                    _ => {}
+                }
+            }
+            let padded = (8 + size).div_ceil(align) * align;
+            rest = rest.get(padded..).unwrap_or(&[]);
+        }
+    }
+}
+
+/// What the disassembly showed, in the terms of the note.
+pub struct Observed {
+    /// psABI level of every instruction found, dispatch variants included
+    pub level: u8,
+    pub x87: bool,
+    pub mmx: bool,
+    pub xmm: bool,
+    pub ymm: bool,
+    pub zmm: bool,
+    pub mask: bool,
+}
+
+impl Observed {
+    fn features(&self) -> Vec<&'static str> {
+        let flags = [self.x87, self.mmx, self.xmm, self.ymm, self.zmm, self.mask];
+        FEATURES
+            .iter()
+            .zip(flags)
+            .filter(|&(_, seen)| seen)
+            .map(|(&(_, name), _)| name)
+            .collect()
+    }
+}
+
+/// psABI levels set in an `ISA_1` bit set: bit 0 is the baseline (v1).
+fn levels(bits: u32) -> Vec<String> {
+    (1..=4)
+        .filter(|level| bits & (1 << (level - 1)) != 0)
+        .map(isa::level_name)
+        .collect()
+}
+
+fn features(bits: u32) -> Vec<String> {
+    FEATURES
+        .iter()
+        .filter(|&&(bit, _)| bits & bit != 0)
+        .map(|&(_, name)| name.to_string())
+        .collect()
+}
+
+/// Compare `declared` with `observed`: a level or feature declared but never
+/// seen, or seen but not declared, is a mismatch.
+pub fn compare(declared: &Declared, observed: &Observed) -> GnuProperty {
+    let mut mismatches = Vec::new();
+    let mut mismatch = |property: &str, kind: MismatchKind, what: String| {
+        mismatches.push(PropertyMismatch {
+            property: property.to_string(),
+            kind,
+            what,
+        });
+    };
+    for (property, bits) in [
+        ("isa_used", declared.isa_used),
+        ("isa_needed", declared.isa_needed),
+    ] {
+        let Some(bits) = bits.filter(|&bits| bits & 0xf != 0) else {
+            continue;
+        };
+        let highest = (32 - (bits & 0xf).leading_zeros()) as u8;
+        if observed.level > highest {
+            mismatch(
+                property,
+                MismatchKind::ObservedNotDeclared,
+                isa::level_name(observed.level),
+            );
+        } else if highest > observed.level {
+            mismatch(
+                property,
+                MismatchKind::DeclaredNotObserved,
+                isa::level_name(highest),
+            );
+        }
+    }
+    let seen = observed.features();
+    if let Some(bits) = declared.features_used {
+        for &(bit, name) in FEATURES {
+            match (bits & bit != 0, seen.contains(&name)) {
+                (true, false) => mismatch(
+                    "features_used",
+                    MismatchKind::DeclaredNotObserved,
+                    name.to_string(),
+                ),
+                (false, true) => mismatch(
+                    "features_used",
+                    MismatchKind::ObservedNotDeclared,
+                    name.to_string(),
+                ),
+                _ => {}
+            }
+        }
+    }
+    // Needed is a floor, not a list of everything used
+    if let Some(bits) = declared.features_needed {
+        for &(bit, name) in FEATURES {
+            if bits & bit != 0 && !seen.contains(&name) {
+                mismatch(
+                    "features_needed",
+                    MismatchKind::DeclaredNotObserved,
+                    name.to_string(),
+                );
+            }
+        }
+    }
+    GnuProperty {
+        present: declared.found,
+        isa_used: declared.isa_used.map(levels),
+        isa_needed: declared.isa_needed.map(levels),
+        features_used: declared.features_used.map(features),
+        features_needed: declared.features_needed.map(features),
+        observed_level: observed.level,
+        observed_features: seen.into_iter().map(str::to_string).collect(),
+        mismatches,
+    }
+}
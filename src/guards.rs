@@ -0,0 +1,136 @@
+//! `--dispatch-analysis`: which SIMD only runs after a CPUID check, and
+//! which runs unconditionally.
+//!
+//! A heuristic over the direct-call graph (see `callgraph`). A *checker* is a
+//! function that runs `cpuid` or `xgetbv`, reads a compiler or libc CPU
+//! feature cache (`__cpu_model`, `__cpu_features2`, `_dl_x86_cpu_features`),
+//! or is a dispatch resolver. A function is *guarded* when it is a guarded
+//! dispatch variant, or when something refers to it (a direct call, a tail
+//! jump or an address load) and everything that does is a checker or
+//! guarded itself. Everything else counts as unconditional, so:
+//! - SIMD in the checker's own body, e.g. a kernel inlined right after the
+//!   check, is unconditional
+//! - a feature cache read through the GOT, as Rust's
+//!   `is_x86_feature_detected!` compiles in PIE builds, goes unseen
+//! - functions nothing refers to by name (reached only through data, such
+//!   as vtables or pointers stored by non-PIE `mov $addr`) are unconditional
+//! - the checker's other callees, `printf` included, count as guarded
+
+use crate::callgraph::CallGraph;
+use crate::classify::{Demangle, FunctionCounts};
+use crate::dispatch;
+use crate::report::{self, CpuChecker, DispatchGuards};
+use indexmap::IndexMap;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+
+lazy_static! {
+    // GCC/Clang's `__builtin_cpu_supports` data and initializer, and glibc's
+    static ref FEATURE_CACHE_RE: Regex = Regex::new(
+        r"^(?:__cpu_model|__cpu_features2|__cpu_indicator_init|_dl_x86_cpu_features|_dl_x86_get_cpu_features|__x86_get_cpu_features)$"
+    )
+    .unwrap();
+}
+
+/// `guarded_isas`, `unconditional_isas` and `dispatch_guards`, from the
+/// graph and the per-function counts (keyed by demangled parent name).
+pub fn analyze(
+    graph: &CallGraph,
+    ifuncs: &[String],
+    demangle: Demangle,
+    parent_functions: &IndexMap<String, FunctionCounts>,
+) -> (
+    IndexMap<String, usize>,
+    IndexMap<String, usize>,
+    DispatchGuards,
+) {
+    let mut checkers: Vec<CpuChecker> = Vec::new();
+    let mut is_checker: HashSet<&str> = HashSet::new();
+    for (symbol, node) in &graph.functions {
+        let feature_reads: Vec<String> = node
+            .references
+            .iter()
+            .map(String::as_str)
+            .chain(node.callees())
+            .filter(|name| FEATURE_CACHE_RE.is_match(name))
+            .map(str::to_string)
+            .collect();
+        let resolver = ifuncs.contains(symbol)
+            || dispatch::variant(symbol, ifuncs).is_some_and(|v| v.target == dispatch::RESOLVER);
+        if node.cpuid + node.xgetbv > 0 || !feature_reads.is_empty() || resolver {
+            is_checker.insert(symbol);
+            checkers.push(CpuChecker {
+                function: demangle.apply(symbol),
+                cpuid: node.cpuid,
+                xgetbv: node.xgetbv,
+                feature_reads,
+                resolver,
+            });
+        }
+    }
+
+    let mut referrers: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for (symbol, node) in &graph.functions {
+        for target in node
+            .callees()
+            .chain(node.references.iter().map(String::as_str))
+        {
+            if target != symbol && graph.functions.contains_key(target) {
+                referrers.entry(target).or_default().insert(symbol);
+            }
+        }
+    }
+    let mut guarded: HashSet<&str> = graph
+        .functions
+        .keys()
+        .map(String::as_str)
+        .filter(|symbol| dispatch::variant(symbol, ifuncs).is_some_and(|v| v.guarded()))
+        .collect();
+    // Least fixed point: a cycle of functions only referring to each other
+    // stays unconditional
+    loop {
+        let before = guarded.len();
+        for (&symbol, from) in &referrers {
+            if !guarded.contains(symbol)
+                && !is_checker.contains(symbol)
+                && from
+                    .iter()
+                    .all(|referrer| is_checker.contains(referrer) || guarded.contains(referrer))
+            {
+                guarded.insert(symbol);
+            }
+        }
+        if guarded.len() == before {
+            break;
+        }
+    }
+
+    let guarded_names: HashSet<String> = guarded.iter().map(|s| demangle.apply(s)).collect();
+    let mut guarded_isas: HashMap<String, usize> = HashMap::new();
+    let mut unconditional_isas: HashMap<String, usize> = HashMap::new();
+    for (name, counts) in parent_functions {
+        let tally = if guarded_names.contains(name) {
+            &mut guarded_isas
+        } else {
+            &mut unconditional_isas
+        };
+        for (isa, count) in &counts.isa_counts {
+            *tally.entry(isa.clone()).or_insert(0) += count;
+        }
+    }
+    let guarded_functions = graph
+        .functions
+        .keys()
+        .filter(|symbol| guarded.contains(symbol.as_str()))
+        .map(|symbol| demangle.apply(symbol))
+        .collect();
+    (
+        report::by_count(guarded_isas),
+        report::by_count(unconditional_isas),
+        DispatchGuards {
+            checkers,
+            guarded_functions,
+        },
+    )
+}
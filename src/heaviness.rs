@@ -0,0 +1,34 @@
+//! `--avx512-heaviness`: which AVX-512 instructions are "heavy", i.e. make
+//! Skylake-SP and Cascade Lake cores drop to the lowest AVX-512 frequency
+//! license while they run.
+//!
+//! The rule, from Intel's optimization manual: 512-bit floating-point math,
+//! FMA and integer multiplies are heavy; everything else (loads, stores,
+//! shuffles, logic, integer adds, and any AVX-512 instruction on xmm/ymm
+//! registers) is light. The split is by mnemonic and register width only, so
+//! the same binary always gives the same counts.
+
+use crate::isa::{self, Shape};
+
+/// Categories whose packed floating-point forms are heavy on zmm.
+const HEAVY_FP_CATEGORIES: &[&str] = &["arithmetic", "fma"];
+
+/// Integer multiply families, which run on the FP multipliers and are heavy
+/// on zmm like FP math. Matched with the `v` prefix dropped.
+const HEAVY_INT_FAMILIES: &[&str] = &["pmul", "pmadd", "pdp"];
+
+/// Whether an AVX-512 instruction is heavy, given its widest register (see
+/// the module docs).
+pub fn is_heavy(mnemonic: &str, widest: Option<u8>) -> bool {
+    if widest != Some(b'z') {
+        return false;
+    }
+    let base = mnemonic.strip_prefix('v').unwrap_or(mnemonic);
+    match isa::shape(mnemonic) {
+        Some(Shape::PackedFp) => HEAVY_FP_CATEGORIES.contains(&isa::category(mnemonic)),
+        Some(Shape::PackedInt) => HEAVY_INT_FAMILIES
+            .iter()
+            .any(|family| base.starts_with(family)),
+        _ => false,
+    }
+}
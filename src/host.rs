@@ -0,0 +1,146 @@
+//! `--check-cpu`: the CPUID features of the CPU simdscan runs on.
+//!
+//! Read with the `cpuid` and `xgetbv` instructions directly, so every
+//! feature `min_cpu` knows is covered. A feature whose register state the
+//! OS hasn't enabled in XCR0 (AVX, AVX-512, AMX) counts as absent, since
+//! its instructions fault just the same.
+
+use anyhow::Result;
+
+/// `(feature, leaf, subleaf, register, bit)`, registers numbered EAX=0,
+/// EBX=1, ECX=2, EDX=3.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+const BITS: &[(&str, u32, u32, usize, u32)] = &[
+    ("MMX", 1, 0, 3, 23),
+    ("SSE2", 1, 0, 3, 26),
+    ("SSE3", 1, 0, 2, 0),
+    ("PCLMUL", 1, 0, 2, 1),
+    ("SSSE3", 1, 0, 2, 9),
+    ("FMA", 1, 0, 2, 12),
+    ("SSE4.1", 1, 0, 2, 19),
+    ("SSE4.2", 1, 0, 2, 20),
+    ("POPCNT", 1, 0, 2, 23),
+    ("AES", 1, 0, 2, 25),
+    ("AVX", 1, 0, 2, 28),
+    ("F16C", 1, 0, 2, 29),
+    ("RDRAND", 1, 0, 2, 30),
+    ("BMI1", 7, 0, 1, 3),
+    ("AVX2", 7, 0, 1, 5),
+    ("BMI2", 7, 0, 1, 8),
+    ("RTM", 7, 0, 1, 11),
+    ("AVX512F", 7, 0, 1, 16),
+    ("AVX512DQ", 7, 0, 1, 17),
+    ("RDSEED", 7, 0, 1, 18),
+    ("AVX512IFMA", 7, 0, 1, 21),
+    ("CLFLUSHOPT", 7, 0, 1, 23),
+    ("CLWB", 7, 0, 1, 24),
+    ("AVX512PF", 7, 0, 1, 26),
+    ("AVX512CD", 7, 0, 1, 28),
+    ("SHA", 7, 0, 1, 29),
+    ("AVX512BW", 7, 0, 1, 30),
+    ("AVX512VL", 7, 0, 1, 31),
+    ("AVX512VBMI", 7, 0, 2, 1),
+    ("WAITPKG", 7, 0, 2, 5),
+    ("AVX512VBMI2", 7, 0, 2, 6),
+    ("SHSTK", 7, 0, 2, 7),
+    ("GFNI", 7, 0, 2, 8),
+    ("VAES", 7, 0, 2, 9),
+    ("VPCLMULQDQ", 7, 0, 2, 10),
+    ("AVX512VNNI", 7, 0, 2, 11),
+    ("AVX512BITALG", 7, 0, 2, 12),
+    ("AVX512VPOPCNTDQ", 7, 0, 2, 14),
+    ("CLDEMOTE", 7, 0, 2, 25),
+    ("IBT", 7, 0, 3, 20),
+    ("AMXBF16", 7, 0, 3, 22),
+    ("AVX512FP16", 7, 0, 3, 23),
+    ("AMXTILE", 7, 0, 3, 24),
+    ("AMXINT8", 7, 0, 3, 25),
+    ("SHA512", 7, 1, 0, 0),
+    ("AVXVNNI", 7, 1, 0, 4),
+    ("AVX512BF16", 7, 1, 0, 5),
+    ("AMXFP16", 7, 1, 0, 21),
+    ("LZCNT", 0x8000_0001, 0, 2, 5),
+    ("SSE4a", 0x8000_0001, 0, 2, 6),
+    ("XOP", 0x8000_0001, 0, 2, 11),
+    ("FMA4", 0x8000_0001, 0, 2, 16),
+];
+
+/// XCR0 bits the OS must set for `feature`'s registers: SSE and AVX state
+/// for VEX, plus opmask/ZMM state for AVX-512 and tile state for AMX.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn os_state(feature: &str) -> u64 {
+    const VEX: &[&str] = &[
+        "AVX",
+        "AVX2",
+        "FMA",
+        "F16C",
+        "VAES",
+        "VPCLMULQDQ",
+        "AVXVNNI",
+        "FMA4",
+        "XOP",
+    ];
+    if feature.starts_with("AVX512") {
+        0xe6
+    } else if feature.starts_with("AMX") {
+        0x6_0000
+    } else if VEX.contains(&feature) {
+        0x6
+    } else {
+        0
+    }
+}
+
+/// The CPUID features of the host CPU that the OS lets code use, named as in
+/// data/isa.toml.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+pub fn features() -> Result<Vec<&'static str>> {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::{__cpuid, __cpuid_count, _xgetbv};
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::{__cpuid, __cpuid_count, _xgetbv};
+
+    let (max_basic, max_extended) = (__cpuid(0).eax, __cpuid(0x8000_0000).eax);
+    let registers = |leaf: u32, subleaf: u32| {
+        let max = if leaf >= 0x8000_0000 {
+            max_extended
+        } else {
+            max_basic
+        };
+        if leaf > max {
+            return [0; 4];
+        }
+        let r = __cpuid_count(leaf, subleaf);
+        [r.eax, r.ebx, r.ecx, r.edx]
+    };
+    let max_subleaf = registers(7, 0)[0];
+    // OSXSAVE: the OS manages extended state and XGETBV is available
+    let xcr0 = if registers(1, 0)[2] & (1 << 27) != 0 {
+        // SAFETY: OSXSAVE guarantees XGETBV
+        unsafe { xgetbv() }
+    } else {
+        0
+    };
+    #[target_feature(enable = "xsave")]
+    unsafe fn xgetbv() -> u64 {
+        _xgetbv(0)
+    }
+
+    Ok(BITS
+        .iter()
+        .filter(|&&(_, leaf, subleaf, _, _)| leaf != 7 || subleaf <= max_subleaf)
+        .filter(|&&(_, leaf, subleaf, register, bit)| {
+            registers(leaf, subleaf)[register] & (1 << bit) != 0
+        })
+        .map(|&(feature, ..)| feature)
+        .filter(|feature| xcr0 & os_state(feature) == os_state(feature))
+        .collect())
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+pub fn features() -> Result<Vec<&'static str>> {
+    anyhow::bail!(
+        "--check-cpu reads the host's x86 CPUID, but this host is {}; use --target-cpu or --target-features instead",
+        std::env::consts::ARCH
+    )
+}
@@ -0,0 +1,1489 @@
+use crate::encoding::Encoding;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+
+/// ISA key -> mnemonics.
+pub type IsaTable = HashMap<&'static str, HashSet<&'static str>>;
+
+// INTEL_GENERATIONS, AMD_GENERATIONS, INTRODUCTIONS, LACKS,
+// REFINED_FEATURES, CATEGORY_FAMILIES, ISA_DATA, SCALAR_DATA, OTHER_DATA,
+// X87_MNEMONICS and CPUID_OVERRIDES, generated from data/isa.toml
+include!(concat!(env!("OUT_DIR"), "/isa_data.rs"));
+
+fn build_table(data: &[(&'static str, &'static str, &[&'static str])]) -> IsaTable {
+    data.iter()
+        .map(|&(isa, _, mnemonics)| (isa, mnemonics.iter().copied().collect()))
+        .collect()
+}
+
+lazy_static! {
+    /// The built-in table, from `data/isa.toml`; `--isa-table` can extend or
+    /// replace it (see `isa_table::load`).
+    pub static ref ISA_TABLE: IsaTable = build_table(ISA_DATA);
+
+    /// Scalar (general-purpose register) extensions. Reported apart from SIMD;
+    /// BMI1, BMI2 and MOVNTI only with `--include-scalar-ext`.
+    pub static ref SCALAR_TABLE: IsaTable = build_table(SCALAR_DATA);
+
+    /// Other non-vector extensions (RDRAND, TSX, CET, ...), each classified
+    /// only when enabled with `--extensions` or named by a policy flag.
+    pub static ref OTHER_TABLE: IsaTable = build_table(OTHER_DATA);
+
+    /// x87 floating-point mnemonics, classified only with `--include-x87`.
+    static ref X87: HashSet<&'static str> = X87_MNEMONICS.iter().copied().collect();
+
+    // objdump folds the predicate immediate of `cmpps` / `vcmpps` and friends
+    // into the mnemonic: `cmpltps`, `vcmpnge_uqpd`, `vcmpunord_ssh`
+    static ref CMP_PREDICATE_RE: Regex = Regex::new(concat!(
+        r"^(v?cmp)(?:eq|lt|le|unord|neq|nlt|nle|ord|eq_uq|nge|ngt|false|neq_oq|ge|gt|true",
+        r"|eq_os|lt_oq|le_oq|unord_s|neq_us|nlt_uq|nle_uq|ord_s|eq_us|nge_uq|ngt_uq",
+        r"|false_os|neq_os|ge_oq|gt_oq|true_us)(ps|pd|ss|sd|ph|sh)$"
+    ))
+    .unwrap();
+}
+
+/// The table mnemonic behind one of objdump's compare pseudo-ops
+/// (`vcmplt_oqps` -> `vcmpps`), or `None` for any other spelling. The integer
+/// compare pseudo-ops (`vpcmpltud`, `vpcomgeb`) are listed in the table.
+pub fn base_mnemonic(mnemonic: &str) -> Option<String> {
+    let captures = CMP_PREDICATE_RE.captures(mnemonic)?;
+    Some(format!("{}{}", &captures[1], &captures[2]))
+}
+
+/// `mnemonic` without the suffix AT&T syntax adds when the operands leave the
+/// size open: `b`/`w`/`l`/`q` for the integer operand (`cvtsi2sdl`) and
+/// `x`/`y`/`z` for the memory width of narrowing converts (`vcvtpd2dqy`).
+/// Only stripped when the bare spelling is `known` and the suffixed one isn't,
+/// so `orq` or `pslldq` are left alone.
+pub fn strip_size_suffix(mnemonic: &str, known: impl Fn(&str) -> bool) -> &str {
+    if known(mnemonic) {
+        return mnemonic;
+    }
+    match mnemonic.strip_suffix(['b', 'w', 'l', 'q', 'x', 'y', 'z']) {
+        Some(bare) if known(bare) => bare,
+        _ => mnemonic,
+    }
+}
+
+/// Whether `mnemonic` is an x87 floating-point instruction, without its size
+/// suffix (see [`strip_x87_suffix`]).
+pub fn is_x87(mnemonic: &str) -> bool {
+    X87.contains(mnemonic)
+}
+
+/// `mnemonic` without the AT&T suffix of an x87 memory operand: `s`/`l`/`t`
+/// for single, double and extended reals (`flds`, `fstpt`), `s`/`l`/`ll`/`q`
+/// for 16-, 32- and 64-bit integers (`fistps`, `fildll`). Mnemonics that are
+/// x87 as spelled (`fabs`, `fcos`) are left alone.
+pub fn strip_x87_suffix(mnemonic: &str) -> &str {
+    if is_x87(mnemonic) {
+        return mnemonic;
+    }
+    ["ll", "s", "l", "t", "q"]
+        .iter()
+        .filter_map(|suffix| mnemonic.strip_suffix(suffix))
+        .find(|bare| is_x87(bare))
+        .unwrap_or(mnemonic)
+}
+
+/// Whether `mnemonic` is in the SIMD index or a scalar-extension table.
+pub fn is_known(index: &IsaIndex, mnemonic: &str) -> bool {
+    lookup(index, mnemonic).is_some()
+        || SCALAR_TABLE
+            .values()
+            .chain(OTHER_TABLE.values())
+            .any(|set| set.contains(mnemonic))
+}
+
+/// Every mnemonic `table` and the scalar, other-extension and x87 tables
+/// list.
+pub fn known_mnemonics(table: &IsaTable) -> impl Iterator<Item = &'static str> + '_ {
+    table
+        .values()
+        .chain(SCALAR_TABLE.values())
+        .chain(OTHER_TABLE.values())
+        .flatten()
+        .copied()
+        .chain(X87_MNEMONICS.iter().copied())
+}
+
+/// The ISA key `mnemonic` is indexed under, as spelled or as its
+/// [`base_mnemonic`].
+pub fn lookup(index: &IsaIndex, mnemonic: &str) -> Option<&'static str> {
+    index
+        .get(mnemonic)
+        .or_else(|| index.get(base_mnemonic(mnemonic)?.as_str()))
+        .copied()
+}
+
+/// One-line descriptions by mnemonic family. A mnemonic takes the longest
+/// family it starts with, tried with and without its VEX `v` prefix (see
+/// [`describe`]).
+const DESCRIPTIONS: &[(&str, &str)] = &[
+    ("add", "Add floating-point lanes"),
+    (
+        "addsub",
+        "Alternately subtract and add floating-point lanes",
+    ),
+    ("aes", "One step of an AES encryption or decryption round"),
+    (
+        "align",
+        "Concatenate two vectors and shift right by whole elements",
+    ),
+    ("and", "Bitwise AND"),
+    ("andn", "Bitwise AND NOT"),
+    (
+        "blend",
+        "Pick lanes from two sources by an immediate or vector mask",
+    ),
+    (
+        "blendm",
+        "Pick lanes from two sources by an opmask register",
+    ),
+    (
+        "broadcast",
+        "Replicate a scalar or subvector into every lane",
+    ),
+    ("cmp", "Compare floating-point lanes, producing a mask"),
+    ("comi", "Compare scalars and set flags"),
+    (
+        "compress",
+        "Pack the lanes selected by an opmask contiguously",
+    ),
+    ("crc32", "Accumulate a CRC-32C checksum"),
+    ("cvt", "Convert between numeric formats"),
+    ("cvtne", "Convert to bfloat16 with round-to-nearest-even"),
+    (
+        "cvtph2ps",
+        "Convert half-precision floats to single precision",
+    ),
+    (
+        "cvtps2ph",
+        "Convert single-precision floats to half precision",
+    ),
+    ("cvtt", "Convert to integer, truncating toward zero"),
+    (
+        "dbpsadbw",
+        "Sum of absolute byte differences over selected dword blocks",
+    ),
+    ("div", "Divide floating-point lanes"),
+    ("dp", "Dot product of floating-point lanes under a mask"),
+    (
+        "dpbf16ps",
+        "Dot product of bfloat16 pairs accumulated into single precision",
+    ),
+    ("emms", "Leave MMX state so x87 floating-point code can run"),
+    (
+        "expand",
+        "Spread contiguous elements into the lanes selected by an opmask",
+    ),
+    ("extract", "Extract a lane or subvector"),
+    ("extrq", "Extract a bit field from the low quadword (SSE4a)"),
+    (
+        "fixupimm",
+        "Fix up special floating-point values through a lookup table",
+    ),
+    ("fmadd", "Fused multiply-add"),
+    ("fmaddsub", "Fused multiply, alternately add and subtract"),
+    ("fmsub", "Fused multiply-subtract"),
+    ("fmsubadd", "Fused multiply, alternately subtract and add"),
+    ("fnmadd", "Fused negated multiply-add"),
+    ("fnmsub", "Fused negated multiply-subtract"),
+    (
+        "fpclass",
+        "Test floating-point lanes for special value classes",
+    ),
+    ("frcz", "Extract the fractional part (XOP)"),
+    (
+        "gather",
+        "Load floating-point lanes from vector-indexed addresses",
+    ),
+    ("getexp", "Extract the exponent of floating-point lanes"),
+    (
+        "getmant",
+        "Extract the normalized mantissa of floating-point lanes",
+    ),
+    ("gf2p8", "Galois-field GF(2^8) multiply or affine transform"),
+    ("hadd", "Add adjacent lanes horizontally"),
+    ("hsub", "Subtract adjacent lanes horizontally"),
+    ("insert", "Insert a lane or subvector"),
+    (
+        "insertq",
+        "Insert a bit field into the low quadword (SSE4a)",
+    ),
+    ("k", "AVX-512 opmask register operation"),
+    (
+        "lddqu",
+        "Unaligned 128-bit load that may read extra cache lines",
+    ),
+    ("ldmxcsr", "Load the SSE control/status register"),
+    ("ldtilecfg", "Configure AMX tile shapes"),
+    ("maskmov", "Load or store lanes selected by a vector mask"),
+    ("max", "Maximum of floating-point lanes"),
+    ("min", "Minimum of floating-point lanes"),
+    ("mov", "Move data between vector registers and memory"),
+    (
+        "movmsk",
+        "Gather lane sign bits into a general-purpose register",
+    ),
+    ("movnt", "Store that bypasses the cache (non-temporal)"),
+    (
+        "mpsadbw",
+        "Sums of absolute byte differences over sliding windows",
+    ),
+    ("mul", "Multiply floating-point lanes"),
+    ("or", "Bitwise OR"),
+    ("pabs", "Absolute value of packed integers"),
+    ("pack", "Narrow packed integers with saturation"),
+    ("padd", "Add packed integers"),
+    (
+        "palignr",
+        "Concatenate two vectors and shift right by bytes",
+    ),
+    ("pand", "Bitwise AND of integer vectors"),
+    ("pandn", "Bitwise AND NOT of integer vectors"),
+    ("pavg", "Rounded average of packed unsigned integers"),
+    ("pblend", "Pick integer lanes from two sources"),
+    (
+        "pblendm",
+        "Pick integer lanes from two sources by an opmask register",
+    ),
+    ("pbroadcast", "Replicate an integer or mask into every lane"),
+    ("pclmul", "Carry-less (polynomial) 64-bit multiply"),
+    ("pcmov", "Bitwise select between two vectors (XOP)"),
+    ("pcmp", "Compare packed integers, producing a mask"),
+    ("pcmpestr", "Compare explicit-length strings (SSE4.2)"),
+    (
+        "pcmpistr",
+        "Compare implicit-length (NUL-terminated) strings (SSE4.2)",
+    ),
+    (
+        "pcom",
+        "Compare packed integers by an immediate predicate (XOP)",
+    ),
+    (
+        "pcompress",
+        "Pack the integer lanes selected by an opmask contiguously",
+    ),
+    (
+        "pconflict",
+        "Find earlier duplicates of each element (conflict detection)",
+    ),
+    (
+        "pdpbusd",
+        "Multiply unsigned by signed bytes and accumulate into dwords (VNNI)",
+    ),
+    (
+        "pdpwssd",
+        "Multiply signed words and accumulate into dwords (VNNI)",
+    ),
+    ("perm", "Permute lanes across the whole vector"),
+    (
+        "permi2",
+        "Permute lanes from two tables, overwriting the index",
+    ),
+    (
+        "permt2",
+        "Permute lanes from two tables, overwriting a table",
+    ),
+    (
+        "pexpand",
+        "Spread contiguous integers into the lanes selected by an opmask",
+    ),
+    (
+        "pextr",
+        "Extract an integer lane into a general-purpose register or memory",
+    ),
+    (
+        "pgather",
+        "Load integer lanes from vector-indexed addresses",
+    ),
+    ("phadd", "Add adjacent integer lanes horizontally"),
+    ("phminposuw", "Find the minimum unsigned word and its index"),
+    ("phsub", "Subtract adjacent integer lanes horizontally"),
+    (
+        "pinsr",
+        "Insert an integer from a general-purpose register or memory",
+    ),
+    ("plzcnt", "Count leading zeros in each lane"),
+    ("pmacs", "Multiply and accumulate packed integers (XOP)"),
+    ("pmadd", "Multiply packed integers and add adjacent pairs"),
+    (
+        "pmadd52",
+        "Multiply 52-bit integers and add the low or high half (IFMA)",
+    ),
+    (
+        "pmaskmov",
+        "Load or store integer lanes selected by a vector mask",
+    ),
+    ("pmax", "Maximum of packed integers"),
+    ("pmin", "Minimum of packed integers"),
+    ("pmov", "Convert packed integers to a narrower type"),
+    ("pmovm2", "Expand an opmask register into vector lanes"),
+    (
+        "pmovmskb",
+        "Gather byte sign bits into a general-purpose register",
+    ),
+    ("pmovsx", "Sign-extend packed integers"),
+    ("pmovzx", "Zero-extend packed integers"),
+    ("pmul", "Multiply packed integers"),
+    ("pmultishiftqb", "Select unaligned bytes from each quadword"),
+    ("popcnt", "Count set bits"),
+    ("por", "Bitwise OR of integer vectors"),
+    ("pperm", "Byte permute with per-byte operations (XOP)"),
+    ("prol", "Rotate packed integers left"),
+    ("pror", "Rotate packed integers right"),
+    ("prot", "Rotate packed integers (XOP)"),
+    ("psadbw", "Sum of absolute byte differences"),
+    (
+        "pscatter",
+        "Store integer lanes to vector-indexed addresses",
+    ),
+    ("psha", "Arithmetic shift by per-lane counts (XOP)"),
+    ("pshl", "Logical shift by per-lane counts (XOP)"),
+    ("pshld", "Concatenate lanes and shift left (VBMI2)"),
+    ("pshrd", "Concatenate lanes and shift right (VBMI2)"),
+    ("pshuf", "Shuffle packed integers by an immediate"),
+    ("pshufb", "Shuffle bytes by a control vector"),
+    (
+        "pshufbitqmb",
+        "Gather bits from each quadword into an opmask (BITALG)",
+    ),
+    (
+        "psign",
+        "Negate, keep, or zero lanes by the sign of another vector",
+    ),
+    ("psll", "Shift packed integers left"),
+    ("psra", "Shift packed integers right, arithmetic"),
+    ("psrl", "Shift packed integers right, logical"),
+    ("psub", "Subtract packed integers"),
+    (
+        "pternlog",
+        "Bitwise ternary logic of three vectors, selected by an 8-bit truth table",
+    ),
+    ("ptest", "Test bits of two vectors, setting flags"),
+    ("ptestm", "Test bits of two vectors, setting an opmask"),
+    (
+        "ptestnm",
+        "Test bits of two vectors for zero, setting an opmask",
+    ),
+    ("punpck", "Interleave integer lanes from two sources"),
+    ("pxor", "Bitwise XOR of integer vectors"),
+    (
+        "range",
+        "Select the min/max of floating-point lanes by an immediate",
+    ),
+    ("rcp", "Approximate reciprocal"),
+    (
+        "reduce",
+        "Subtract the rounded value, leaving the reduced argument",
+    ),
+    ("rndscale", "Round to a given number of fraction bits"),
+    ("round", "Round to integer with a chosen mode"),
+    ("rsqrt", "Approximate reciprocal square root"),
+    ("scalef", "Scale floating-point lanes by powers of two"),
+    (
+        "scatter",
+        "Store floating-point lanes to vector-indexed addresses",
+    ),
+    ("sha1", "One step of a SHA-1 round or message schedule"),
+    ("sha256", "One step of a SHA-256 round or message schedule"),
+    ("sha512", "One step of a SHA-512 round or message schedule"),
+    ("shuf", "Shuffle lanes by an immediate"),
+    ("sqrt", "Square root"),
+    ("stmxcsr", "Store the SSE control/status register"),
+    ("sttilecfg", "Store the AMX tile configuration"),
+    ("sub", "Subtract floating-point lanes"),
+    ("tdp", "AMX tile dot product with accumulate"),
+    ("test", "Test sign bits of two vectors, setting flags"),
+    ("tile", "AMX tile load, store, or reset"),
+    ("ucomi", "Compare scalars and set flags, quiet on NaN"),
+    ("unpck", "Interleave floating-point lanes from two sources"),
+    ("xor", "Bitwise XOR"),
+    (
+        "zero",
+        "Clear the upper halves (or all) of the ymm registers",
+    ),
+];
+
+/// The one-line description of `mnemonic`'s family, if there is one.
+pub fn describe(mnemonic: &str) -> Option<&'static str> {
+    let base = mnemonic.strip_prefix('v').unwrap_or(mnemonic);
+    DESCRIPTIONS
+        .iter()
+        .filter(|(family, _)| mnemonic.starts_with(family) || base.starts_with(family))
+        .max_by_key(|(family, _)| family.len())
+        .map(|(_, description)| *description)
+}
+
+/// What kind of operation `mnemonic` is (`arithmetic`, `shuffle`, `fma`, ...),
+/// by its longest family prefix in `[categories]`. Every built-in SIMD
+/// mnemonic has one; anything else, such as a `--isa-table` addition, is
+/// `other`.
+pub fn category(mnemonic: &str) -> &'static str {
+    let base = mnemonic.strip_prefix('v').unwrap_or(mnemonic);
+    CATEGORY_FAMILIES
+        .iter()
+        .filter(|(family, _)| mnemonic.starts_with(family) || base.starts_with(family))
+        .max_by_key(|(family, _)| family.len())
+        .map_or("other", |(_, category)| *category)
+}
+
+/// Scalar-extension keys whose mnemonics are also in the SSE4.2 table. They
+/// are classified as scalar by default, unlike the opt-in BMI1, BMI2 and
+/// MOVNTI.
+pub fn was_sse4(ext: &str) -> bool {
+    matches!(ext, "ABM" | "CRC32")
+}
+
+/// How a SIMD move instruction treats memory alignment.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MemoryAccess {
+    Aligned,
+    Unaligned,
+    NonTemporal,
+}
+
+const ALIGNED_MOVES: &[&str] = &[
+    "movaps",
+    "movapd",
+    "movdqa",
+    "vmovaps",
+    "vmovapd",
+    "vmovdqa",
+    "vmovdqa32",
+    "vmovdqa64",
+];
+
+const UNALIGNED_MOVES: &[&str] = &[
+    "movups",
+    "movupd",
+    "movdqu",
+    "lddqu",
+    "vmovups",
+    "vmovupd",
+    "vmovdqu",
+    "vmovdqu8",
+    "vmovdqu16",
+    "vmovdqu32",
+    "vmovdqu64",
+    "vlddqu",
+];
+
+const NON_TEMPORAL_MOVES: &[&str] = &[
+    "movntps",
+    "movntpd",
+    "movntdq",
+    "movntdqa",
+    "movntss",
+    "movntsd",
+    "vmovntps",
+    "vmovntpd",
+    "vmovntdq",
+    "vmovntdqa",
+];
+
+/// Alignment class of a full-width SIMD move, or `None` for anything else.
+pub fn memory_access(mnemonic: &str) -> Option<MemoryAccess> {
+    if ALIGNED_MOVES.contains(&mnemonic) {
+        Some(MemoryAccess::Aligned)
+    } else if UNALIGNED_MOVES.contains(&mnemonic) {
+        Some(MemoryAccess::Unaligned)
+    } else if NON_TEMPORAL_MOVES.contains(&mnemonic) {
+        Some(MemoryAccess::NonTemporal)
+    } else {
+        None
+    }
+}
+
+/// Stores that bypass the cache. `movntdqa` is a load and `movnti` a GPR
+/// store, but only the latter belongs here.
+const NON_TEMPORAL_STORES: &[&str] = &[
+    "movntps",
+    "movntpd",
+    "movntdq",
+    "movntss",
+    "movntsd",
+    "movntq",
+    "movnti",
+    "maskmovq",
+    "maskmovdqu",
+    "vmovntps",
+    "vmovntpd",
+    "vmovntdq",
+    "vmaskmovdqu",
+];
+
+/// Whether `mnemonic` is a non-temporal (streaming) store.
+pub fn is_non_temporal_store(mnemonic: &str) -> bool {
+    NON_TEMPORAL_STORES.contains(&mnemonic)
+}
+
+/// Software prefetch hints. The AVX512-PF gather/scatter prefetches
+/// (`vgatherpf0dps`, `vscatterpf1qpd`, ...) count too; unlike these they are
+/// also SIMD.
+const PREFETCHES: &[&str] = &[
+    "prefetch",
+    "prefetchw",
+    "prefetchwt1",
+    "prefetcht0",
+    "prefetcht1",
+    "prefetcht2",
+    "prefetchnta",
+];
+
+/// Whether `mnemonic` is a software prefetch.
+pub fn is_prefetch(mnemonic: &str) -> bool {
+    PREFETCHES.contains(&mnemonic)
+        || mnemonic.starts_with("vgatherpf")
+        || mnemonic.starts_with("vscatterpf")
+}
+
+/// Loads and stores of MXCSR, the SSE control/status register.
+const MXCSR_ACCESS: &[&str] = &["ldmxcsr", "stmxcsr", "vldmxcsr", "vstmxcsr"];
+
+/// Whether `mnemonic` reads or writes MXCSR.
+pub fn is_mxcsr_access(mnemonic: &str) -> bool {
+    MXCSR_ACCESS.contains(&mnemonic)
+}
+
+/// Whether `mnemonic` loads MXCSR, which is how code switches on
+/// flush-to-zero / denormals-are-zero or changes the rounding mode.
+pub fn writes_mxcsr(mnemonic: &str) -> bool {
+    matches!(mnemonic, "ldmxcsr" | "vldmxcsr")
+}
+
+/// Conditional branches: `jcc`, `jrcxz` and friends, and the `loop` family.
+pub fn is_conditional_branch(mnemonic: &str) -> bool {
+    (mnemonic.starts_with('j') && !mnemonic.starts_with("jmp")) || mnemonic.starts_with("loop")
+}
+
+/// Instructions that put SIMD register state back in order: `vzeroupper`
+/// and `vzeroall` before legacy SSE code, `emms` before x87 code.
+pub fn is_state_reset(mnemonic: &str) -> bool {
+    matches!(mnemonic, "vzeroupper" | "vzeroall" | "emms")
+}
+
+/// Policy name for the non-temporal stores, alongside the `category` names.
+pub const NON_TEMPORAL: &str = "non-temporal";
+
+/// Policy name for AVX-512 instructions on zmm registers, as opposed to the
+/// AVX512-VL 128/256-bit forms.
+pub const AVX512_512BIT: &str = "avx512-512bit";
+
+/// Names `--fail-if-found` and `--fail-if-missing` accept besides ISA keys:
+/// every operation category, [`NON_TEMPORAL`], [`AVX512_512BIT`], and the
+/// [`other_keys`].
+pub fn policy_categories() -> Vec<&'static str> {
+    let mut names: Vec<&'static str> = CATEGORY_FAMILIES
+        .iter()
+        .map(|(_, category)| *category)
+        .collect();
+    names.dedup();
+    names.push(NON_TEMPORAL);
+    names.push(AVX512_512BIT);
+    names.extend(other_keys());
+    names
+}
+
+/// The `--extensions` group names: keys of [`OTHER_TABLE`], in table order.
+pub fn other_keys() -> impl Iterator<Item = &'static str> + Clone {
+    OTHER_DATA.iter().map(|&(key, _, _)| key)
+}
+
+/// Mnemonics from [`OTHER_TABLE`] that older CPUs execute as NOPs, so they
+/// don't raise the minimum CPU: `endbr`, the shadow-stack reads that CET
+/// placed in the NOP space, and the `cldemote` hint.
+const NOP_ON_OLDER: &[&str] = &[
+    "endbr32", "endbr64", "rdsspd", "rdsspq", "incsspd", "incsspq", "cldemote",
+];
+
+/// Whether `mnemonic` needs its CPUID feature to run at all (see
+/// [`NOP_ON_OLDER`]).
+pub fn needs_feature(mnemonic: &str) -> bool {
+    !NOP_ON_OLDER.contains(&mnemonic)
+}
+
+/// Whether a SIMD instruction operates on one lane or a whole vector.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    /// `ss`/`sd`/`sh` forms: SSE/AVX used as a scalar FPU
+    ScalarFp,
+    /// `ps`/`pd`/`ph` forms
+    PackedFp,
+    /// `p*`/`vp*` integer vector instructions
+    PackedInt,
+}
+
+/// SIMD-table entries that only touch general-purpose registers.
+const GPR_ONLY: &[&str] = &["crc32", "lzcnt", "popcnt"];
+
+/// AMX instructions, which work on `tmm` tiles rather than vector registers.
+fn is_tile_op(mnemonic: &str) -> bool {
+    mnemonic.starts_with("tdp") || mnemonic.starts_with("tile") || mnemonic.ends_with("tilecfg")
+}
+
+/// Scalar/packed shape of a SIMD mnemonic, judged by its prefix and suffix.
+/// Mnemonics that fit no pattern (`vzeroupper`, mask ops) and AMX tile
+/// instructions return `None`.
+pub fn shape(mnemonic: &str) -> Option<Shape> {
+    if GPR_ONLY.contains(&mnemonic) || is_tile_op(mnemonic) {
+        return None;
+    }
+    let base = mnemonic.strip_prefix('v').unwrap_or(mnemonic);
+    if base.starts_with('p') {
+        Some(Shape::PackedInt)
+    } else if base.starts_with("broadcast") {
+        // Scalar suffix, but fills every lane
+        Some(Shape::PackedFp)
+    } else if ["ps", "pd", "ph"].iter().any(|s| base.ends_with(s)) {
+        Some(Shape::PackedFp)
+    } else if ["ss", "sd", "sh"].iter().any(|s| base.ends_with(s)) {
+        Some(Shape::ScalarFp)
+    } else {
+        None
+    }
+}
+
+/// `v*` mnemonics that aren't vector instructions (segment verification, VMX, SVM).
+const NON_VECTOR_V: &[&str] = &[
+    "verr", "verw", "vmcall", "vmclear", "vmfunc", "vmlaunch", "vmload", "vmmcall", "vmptrld",
+    "vmptrst", "vmread", "vmresume", "vmrun", "vmsave", "vmwrite", "vmxoff", "vmxon",
+];
+
+/// Whether an unclassified mnemonic still looks like a vector instruction: a
+/// VEX/EVEX-style `v` prefix, or vector register operands.
+pub fn looks_vector(mnemonic: &str, has_vector_operand: bool) -> bool {
+    has_vector_operand || (mnemonic.starts_with('v') && !NON_VECTOR_V.contains(&mnemonic))
+}
+
+/// ISA keys from oldest to newest. Anything that needs to compare extensions
+/// ("highest ISA used", psABI level, policy checks) goes through this ordering.
+pub const ISA_RANKING: &[&str] = &[
+    "MMX",
+    "SSE",
+    "SSE2",
+    "SSE3",
+    "SSSE3",
+    "SSE4",
+    "SSE4.1",
+    "SSE4.2",
+    "SSE4a",
+    "AVX",
+    "F16C",
+    "XOP",
+    "FMA4",
+    "FMA",
+    "AVX2",
+    "AVX-VNNI",
+    "AVX-512",
+    "AVX512-F",
+    "AVX512-VL",
+    "AVX512-CD",
+    "AVX512-PF",
+    "AVX512-BW",
+    "AVX512-DQ",
+    "AVX512-IFMA",
+    "AVX512-VBMI",
+    "AVX512-VNNI",
+    "AVX512-VBMI2",
+    "AVX512-BITALG",
+    "AVX512-VPOPCNTDQ",
+    "AVX512-BF16",
+    "AVX512-FP16",
+    "AMX-TILE",
+    "AMX-INT8",
+    "AMX-BF16",
+    "AMX-FP16",
+];
+
+/// How finely ISA keys are split in the report.
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Granularity {
+    /// One key per CPUID extension (SSE4.1, SSE4.2, SSE4a, ...)
+    #[default]
+    Fine,
+    /// The original buckets: a single SSE4, FMA and AVX2 folded into AVX, and
+    /// one AVX-512
+    Coarse,
+}
+
+/// The key `isa` is reported under at `granularity`.
+pub fn report_key(isa: &str, granularity: Granularity) -> &str {
+    match (granularity, isa) {
+        (Granularity::Coarse, "SSE4.1" | "SSE4.2" | "SSE4a") => "SSE4",
+        (Granularity::Coarse, "FMA" | "AVX2") => "AVX",
+        (Granularity::Coarse, avx512) if is_avx512(avx512) => "AVX-512",
+        (Granularity::Coarse, amx) if is_amx(amx) => "AMX",
+        (Granularity::Coarse, "GFNI (SSE)" | "GFNI (AVX)" | "GFNI (AVX-512)") => "GFNI",
+        _ => isa,
+    }
+}
+
+/// Whether `isa` is one of the AVX-512 subset keys.
+pub fn is_avx512(isa: &str) -> bool {
+    isa.starts_with("AVX512-")
+}
+
+/// Whether `isa` is one of the AMX keys.
+pub fn is_amx(isa: &str) -> bool {
+    isa.starts_with("AMX-")
+}
+
+/// Whether counts under `isa` belong to `key`: the key itself, any subset
+/// when `key` is the `AVX-512` or `AMX` rollup, or any encoding split of
+/// `key` (e.g. `GFNI` covers `GFNI (AVX)`).
+pub fn covers(key: &str, isa: &str) -> bool {
+    key == isa
+        || (key == "AVX-512" && is_avx512(isa))
+        || (key == "AMX" && is_amx(isa))
+        || isa
+            .strip_prefix(key)
+            .is_some_and(|rest| rest.starts_with(" ("))
+}
+
+/// Mnemonics shared by a VEX and an EVEX extension. objdump decodes them as
+/// EVEX unless it prints a `{vex}` marker, so without raw bytes a plain
+/// `vpdpbusd %ymm...` is still AVX512-VNNI.
+pub const EVEX_BY_DEFAULT: &[&str] = &["vpdpbusd", "vpdpbusds", "vpdpwssd", "vpdpwssds"];
+
+/// VEX `vp*` mnemonics from the AVX table that are AVX1 even on ymm registers.
+const AVX1_YMM_VP: &[&str] = &["vpermilps", "vpermilpd", "vptest"];
+
+/// Integer instructions from the AVX table without the `vp` prefix that are
+/// still AVX2 on ymm registers.
+const AVX2_YMM: &[&str] = &["vmovntdqa", "vmpsadbw"];
+
+/// The SSE2 integer mnemonics MMX had first, which are MMX on `%mm`
+/// registers. `paddq`, `psubq`, `pmuludq` and `pmulhuw` gained their `%mm`
+/// forms later, with SSE or SSE2.
+pub const MMX_SHARED: &[&str] = &[
+    "movd",
+    "movq",
+    "packssdw",
+    "packsswb",
+    "packuswb",
+    "paddb",
+    "paddw",
+    "paddd",
+    "paddsb",
+    "paddsw",
+    "paddusb",
+    "paddusw",
+    "pand",
+    "pandn",
+    "por",
+    "pxor",
+    "pcmpeqb",
+    "pcmpeqw",
+    "pcmpeqd",
+    "pcmpgtb",
+    "pcmpgtw",
+    "pcmpgtd",
+    "pmaddwd",
+    "pmulhw",
+    "pmullw",
+    "psllw",
+    "pslld",
+    "psllq",
+    "psraw",
+    "psrad",
+    "psrlw",
+    "psrld",
+    "psrlq",
+    "psubb",
+    "psubw",
+    "psubd",
+    "psubsb",
+    "psubsw",
+    "psubusb",
+    "psubusw",
+    "punpckhbw",
+    "punpckhwd",
+    "punpckhdq",
+    "punpcklbw",
+    "punpcklwd",
+    "punpckldq",
+];
+
+/// SIMD-table mnemonics that AT&T syntax also uses for a general-purpose
+/// instruction (`movq %rax,%rbx`), so they only count with a vector or MMX
+/// operand.
+pub const GPR_NAMESAKES: &[&str] = &["movq"];
+
+/// Whether `mnemonic` is a 256-bit integer operation once it has ymm operands.
+fn is_avx2_on_ymm(mnemonic: &str) -> bool {
+    (mnemonic.starts_with("vp") && !AVX1_YMM_VP.contains(&mnemonic)) || AVX2_YMM.contains(&mnemonic)
+}
+
+/// AVX/AVX2 byte and word integer ops, whose EVEX forms are AVX512BW rather
+/// than AVX512F.
+const EVEX_BW: &[&str] = &[
+    "vpabsb",
+    "vpabsw",
+    "vpackssdw",
+    "vpacksswb",
+    "vpackusdw",
+    "vpackuswb",
+    "vpaddb",
+    "vpaddsb",
+    "vpaddsw",
+    "vpaddusb",
+    "vpaddusw",
+    "vpaddw",
+    "vpalignr",
+    "vpavgb",
+    "vpavgw",
+    "vpbroadcastb",
+    "vpbroadcastw",
+    "vpcmpeqb",
+    "vpcmpeqw",
+    "vpcmpgtb",
+    "vpcmpgtw",
+    "vpextrb",
+    "vpextrw",
+    "vpinsrb",
+    "vpinsrw",
+    "vpmaddubsw",
+    "vpmaddwd",
+    "vpmaxsb",
+    "vpmaxsw",
+    "vpmaxub",
+    "vpmaxuw",
+    "vpminsb",
+    "vpminsw",
+    "vpminub",
+    "vpminuw",
+    "vpmovsxbw",
+    "vpmovzxbw",
+    "vpmulhrsw",
+    "vpmulhuw",
+    "vpmulhw",
+    "vpmullw",
+    "vpsadbw",
+    "vpshufb",
+    "vpshufhw",
+    "vpshuflw",
+    "vpslldq",
+    "vpsllw",
+    "vpsraw",
+    "vpsrldq",
+    "vpsrlw",
+    "vpsubb",
+    "vpsubsb",
+    "vpsubsw",
+    "vpsubusb",
+    "vpsubusw",
+    "vpsubw",
+    "vpunpckhbw",
+    "vpunpckhwd",
+    "vpunpcklbw",
+    "vpunpcklwd",
+];
+
+/// AVX floating-point logic and dword/qword lane moves, whose EVEX forms are
+/// AVX512DQ.
+const EVEX_DQ: &[&str] = &[
+    "vandnpd", "vandnps", "vandpd", "vandps", "vorpd", "vorps", "vxorpd", "vxorps", "vpextrd",
+    "vpextrq", "vpinsrd", "vpinsrq",
+];
+
+/// 128-bit-only or scalar instructions whose EVEX forms ignore vector length,
+/// so they never need AVX512VL.
+const EVEX_NO_VL: &[&str] = &[
+    "vextractps",
+    "vinsertps",
+    "vmovd",
+    "vmovq",
+    "vmovhlps",
+    "vmovhpd",
+    "vmovhps",
+    "vmovlhps",
+    "vmovlpd",
+    "vmovlps",
+    "vpextrb",
+    "vpextrd",
+    "vpextrq",
+    "vpextrw",
+    "vpinsrb",
+    "vpinsrd",
+    "vpinsrq",
+    "vpinsrw",
+];
+
+/// AVX-512 subset of the EVEX form of a VEX-era mnemonic (`vaddps %zmm...`,
+/// `vpaddw %xmm17...`). Also the guess for EVEX instructions missing from the
+/// table altogether.
+pub fn evex_key(mnemonic: &str) -> &'static str {
+    if EVEX_BW.contains(&mnemonic) {
+        "AVX512-BW"
+    } else if EVEX_DQ.contains(&mnemonic) {
+        "AVX512-DQ"
+    } else {
+        "AVX512-F"
+    }
+}
+
+/// Whether an EVEX instruction on xmm/ymm registers needs AVX512VL, i.e. it
+/// is a packed operation at a reduced vector length.
+fn needs_vl(mnemonic: &str, widest: Option<u8>) -> bool {
+    matches!(widest, Some(b'x' | b'y'))
+        && shape(mnemonic) != Some(Shape::ScalarFp)
+        && !mnemonic.ends_with("2si")
+        && !mnemonic.ends_with("2usi")
+        && !EVEX_NO_VL.contains(&mnemonic)
+}
+
+/// Refine a table match using the instruction's widest register and
+/// encoding, for extensions that share mnemonics and differ only in operand
+/// width or encoding:
+/// 256-bit VEX integer ops (`vpaddd %ymm...`) need AVX2 while the 128-bit
+/// forms are AVX, `vaesenc` / `vpclmulqdq` on ymm/zmm are VAES / VPCLMULQDQ
+/// rather than AES-NI / PCLMUL, EVEX forms of AVX/AVX2/FMA/F16C mnemonics
+/// are AVX-512 (see [`evex_key`]), packed AVX512F instructions on xmm/ymm
+/// need AVX512VL, and `{vex} vpdpbusd` is AVX-VNNI rather than AVX512-VNNI.
+/// GFNI is split by encoding since each one needs a different CPU.
+pub fn refine_key(
+    isa: &'static str,
+    mnemonic: &str,
+    widest: Option<u8>,
+    encoding: Encoding,
+) -> &'static str {
+    let isa = match (isa, encoding) {
+        ("AVX512-VNNI", Encoding::Vex) => return "AVX-VNNI",
+        ("GFNI", Encoding::Legacy) => return "GFNI (SSE)",
+        ("GFNI", Encoding::Vex) => return "GFNI (AVX)",
+        ("GFNI", Encoding::Evex) => return "GFNI (AVX-512)",
+        ("AVX" | "AVX2" | "FMA" | "F16C", Encoding::Evex) => evex_key(mnemonic),
+        _ => isa,
+    };
+    match (isa, widest) {
+        ("AVX", Some(b'y')) if is_avx2_on_ymm(mnemonic) => "AVX2",
+        ("AES-NI", Some(b'y' | b'z')) => "VAES",
+        ("PCLMUL", Some(b'y' | b'z')) => "VPCLMULQDQ",
+        ("AVX512-F", _) if needs_vl(mnemonic, widest) => "AVX512-VL",
+        _ => isa,
+    }
+}
+
+/// Every key a report at `granularity` can contain, plus the `AVX-512` and
+/// `AMX` rollups, sorted.
+pub fn report_keys(table: &IsaTable, granularity: Granularity) -> Vec<&'static str> {
+    let mut keys: Vec<&'static str> = table
+        .keys()
+        .chain(REFINED_FEATURES.iter().map(|(key, _)| key))
+        .map(|isa| report_key(isa, granularity))
+        .chain(["AVX-512", "AMX"])
+        .collect();
+    keys.sort_unstable();
+    keys.dedup();
+    keys
+}
+
+/// Sort key for ordering ISA keys by generation: ranked keys oldest first,
+/// the coarse `AMX` key with the AMX subsets, then unranked extensions
+/// (AES-NI, SHA, GFNI, ...) by name.
+pub fn generation_order(isa: &str) -> (usize, &str) {
+    let rank = rank(isa).or_else(|| (isa == "AMX").then(|| rank("AMX-TILE")).flatten());
+    (rank.unwrap_or(ISA_RANKING.len()), isa)
+}
+
+/// Position of an ISA key in [`ISA_RANKING`], or `None` if it isn't ranked.
+pub fn rank(isa: &str) -> Option<usize> {
+    ISA_RANKING.iter().position(|known| *known == isa)
+}
+
+/// Mnemonic -> the single ISA key it is classified under.
+pub type IsaIndex = HashMap<&'static str, &'static str>;
+
+/// Flatten `table` into an [`IsaIndex`]. A mnemonic listed under several keys
+/// goes to the oldest by [`ISA_RANKING`], so the result never depends on
+/// `HashMap` iteration order. Two unranked keys cannot be ordered that way
+/// and fall back to name order; `isa_table::load` rejects them in user tables
+/// and a test keeps them out of the built-in one.
+pub fn index(table: &IsaTable) -> IsaIndex {
+    let mut index = IsaIndex::new();
+    for (&isa, mnemonics) in table {
+        for &mnemonic in mnemonics {
+            let owner = index.entry(mnemonic).or_insert(isa);
+            if *owner == isa {
+                continue;
+            }
+            let priority = |key: &str| (rank(key).unwrap_or(usize::MAX), key.to_string());
+            if priority(isa) < priority(owner) {
+                *owner = isa;
+            }
+        }
+    }
+    index
+}
+
+/// The newest ISA among `isas`, by [`ISA_RANKING`].
+pub fn highest<'a>(isas: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    isas.into_iter()
+        .filter(|isa| rank(isa).is_some())
+        .max_by_key(|isa| rank(isa))
+}
+
+/// The ISA key a function using `isas` requires: the newest ranked one, or
+/// with none ranked the first by name (AES-NI, SHA, ...).
+pub fn requirement<'a>(isas: impl IntoIterator<Item = &'a str> + Clone) -> Option<&'a str> {
+    highest(isas.clone()).or_else(|| isas.into_iter().min())
+}
+
+/// Extensions no current CPU implements. Finding them usually means an old
+/// build that will SIGILL on newer hardware.
+const LEGACY: &[&str] = &["FMA4", "XOP", "AVX512-PF"];
+
+/// Whether `isa` is a discontinued extension (see [`LEGACY`]).
+pub fn is_legacy(isa: &str) -> bool {
+    LEGACY.contains(&isa)
+}
+
+/// x86-64 psABI microarchitecture level (1-4) that guarantees each ISA key.
+const LEVELS: &[(&str, u8)] = &[
+    ("MMX", 1),
+    ("SSE", 1),
+    ("SSE2", 1),
+    ("SSE3", 2),
+    ("SSSE3", 2),
+    ("SSE4.1", 2),
+    ("SSE4.2", 2),
+    ("AVX", 3),
+    ("F16C", 3),
+    ("FMA", 3),
+    ("AVX2", 3),
+    ("AVX512-F", 4),
+    ("AVX512-VL", 4),
+    ("AVX512-CD", 4),
+    ("AVX512-BW", 4),
+    ("AVX512-DQ", 4),
+    ("BMI1", 3),
+    ("BMI2", 3),
+    // popcnt; lzcnt is overridden to 3 below
+    ("ABM", 2),
+    ("CRC32", 2),
+];
+
+/// Mnemonics whose level differs from their ISA key's.
+const LEVEL_OVERRIDES: &[(&str, Option<u8>)] = &[("lzcnt", Some(3))];
+
+/// The psABI level that guarantees `mnemonic` (classified under `isa`), or
+/// `None` if no level includes it. ISA keys missing from [`LEVELS`], such as
+/// AMD's SSE4a, have no level.
+pub fn mnemonic_level(isa: &str, mnemonic: &str) -> Option<u8> {
+    if let Some((_, level)) = LEVEL_OVERRIDES.iter().find(|(m, _)| *m == mnemonic) {
+        return *level;
+    }
+    isa_level(isa)
+}
+
+/// The psABI level that guarantees every mnemonic of `isa`, ignoring
+/// per-mnemonic overrides.
+pub fn isa_level(isa: &str) -> Option<u8> {
+    LEVELS
+        .iter()
+        .find(|(key, _)| *key == isa)
+        .map(|(_, level)| *level)
+}
+
+/// Minimum psABI level needed to run every `(isa, mnemonic)` pair, plus the
+/// mnemonics that no level covers, sorted. Plain x86-64 is level 1.
+pub fn required_level<'a>(
+    insts: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> (u8, Vec<String>) {
+    let mut level = 1;
+    let mut unleveled = Vec::new();
+    for (isa, mnemonic) in insts {
+        match mnemonic_level(isa, mnemonic) {
+            Some(l) => level = level.max(l),
+            None => unleveled.push(mnemonic.to_string()),
+        }
+    }
+    unleveled.sort_unstable();
+    unleveled.dedup();
+    (level, unleveled)
+}
+
+/// Display name of a psABI level, e.g. `x86-64-v3`.
+pub fn level_name(level: u8) -> String {
+    format!("x86-64-v{level}")
+}
+
+/// `(feature, first Intel, first AMD)`. `None` means the vendor never shipped it.
+type Introduction = (&'static str, Option<&'static str>, Option<&'static str>);
+
+/// The CPUID feature an instruction needs, refined from its (fine) ISA key
+/// using the mnemonic. Keys this function doesn't know are returned unchanged.
+pub fn cpu_feature<'a>(isa: &'a str, mnemonic: &str) -> &'a str {
+    if let Some(&(_, _, feature)) = CPUID_OVERRIDES
+        .iter()
+        .find(|&&(key, overridden, _)| key == isa && overridden == mnemonic)
+    {
+        return feature;
+    }
+    ISA_DATA
+        .iter()
+        .chain(SCALAR_DATA)
+        .chain(OTHER_DATA)
+        .find(|&&(key, _, _)| key == isa)
+        .map(|&(_, feature, _)| feature)
+        .or_else(|| {
+            REFINED_FEATURES
+                .iter()
+                .find(|&&(key, _)| key == isa)
+                .map(|&(_, feature)| feature)
+        })
+        .unwrap_or(isa)
+}
+
+/// Features an instruction needs beyond [`cpu_feature`] because of how it was
+/// encoded: packed EVEX below 512 bits needs AVX512VL, and the VEX/EVEX GFNI
+/// forms need AVX/AVX512F as well as GFNI.
+pub fn implied_features(isa: &str, mnemonic: &str, widest: Option<u8>) -> &'static [&'static str] {
+    let narrow = needs_vl(mnemonic, widest);
+    match isa {
+        "GFNI (AVX)" => &["AVX"],
+        "GFNI (AVX-512)" if narrow => &["AVX512F", "AVX512VL"],
+        "GFNI (AVX-512)" => &["AVX512F"],
+        avx512 if is_avx512(avx512) && narrow => &["AVX512VL"],
+        _ => &[],
+    }
+}
+
+/// Earliest microarchitecture in `generations` with every feature, or
+/// `"unknown"` if a feature isn't in [`INTRODUCTIONS`] and `"none"` if the
+/// vendor never shipped one of them.
+fn earliest(
+    features: &[&str],
+    generations: &[&str],
+    pick: impl Fn(&Introduction) -> Option<&'static str>,
+) -> String {
+    let mut newest = 0;
+    for feature in features {
+        let Some(entry) = INTRODUCTIONS.iter().find(|entry| entry.0 == *feature) else {
+            return "unknown".to_string();
+        };
+        let Some(name) = pick(entry) else {
+            return "none".to_string();
+        };
+        let index = generations
+            .iter()
+            .position(|generation| *generation == name)
+            .expect("INTRODUCTIONS names a listed generation");
+        newest = newest.max(index);
+    }
+    generations[newest].to_string()
+}
+
+/// Earliest Intel and AMD microarchitectures that support all of `features`.
+/// With no features this is the x86-64 baseline.
+pub fn min_cpu(features: &[&str]) -> (String, String) {
+    (
+        earliest(features, INTEL_GENERATIONS, |entry| entry.1),
+        earliest(features, AMD_GENERATIONS, |entry| entry.2),
+    )
+}
+
+/// `-march` names (GCC and Clang) that don't reduce to a listed
+/// microarchitecture by [`loose_name`].
+const CPU_ALIASES: &[(&str, &str)] = &[
+    ("core2", "Merom"),
+    ("corei7", "Nehalem"),
+    ("corei7-avx", "Sandy Bridge"),
+    ("core-avx-i", "Ivy Bridge"),
+    ("core-avx2", "Haswell"),
+    ("skylake-avx512", "Skylake-SP"),
+    ("icelake-client", "Ice Lake"),
+    ("icelake-server", "Ice Lake"),
+    ("knl", "Knights Landing"),
+    ("k8-sse3", "K8"),
+    ("opteron", "K8"),
+    ("amdfam10", "K10"),
+    ("barcelona", "K10"),
+    ("bdver1", "Bulldozer"),
+    ("bdver2", "Piledriver"),
+    ("bdver3", "Piledriver"),
+    ("bdver4", "Excavator"),
+    ("znver1", "Zen"),
+    ("znver2", "Zen 2"),
+    ("znver3", "Zen 3"),
+    ("znver4", "Zen 4"),
+    ("znver5", "Zen 5"),
+];
+
+/// CPUID features of each psABI level, cumulatively: `x86-64-v2` has the
+/// `x86-64` ones too.
+const LEVEL_FEATURES: &[(&str, &[&str])] = &[
+    ("x86-64", &["MMX", "SSE2"]),
+    (
+        "x86-64-v2",
+        &["SSE3", "SSSE3", "SSE4.1", "SSE4.2", "POPCNT"],
+    ),
+    (
+        "x86-64-v3",
+        &["AVX", "AVX2", "BMI1", "BMI2", "F16C", "FMA", "LZCNT"],
+    ),
+    (
+        "x86-64-v4",
+        &["AVX512F", "AVX512BW", "AVX512CD", "AVX512DQ", "AVX512VL"],
+    ),
+];
+
+/// `name` lowercased without spaces, `-`, `_` or `.`, so `sandybridge`,
+/// `Sandy-Bridge` and `sandy_bridge` compare equal, as do `sse4.2` and
+/// `SSE4_2`.
+fn loose_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '_' | '.'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// `feature` if it is a CPUID feature [`min_cpu`] knows.
+pub fn known_feature(feature: &str) -> Option<&'static str> {
+    INTRODUCTIONS
+        .iter()
+        .map(|entry| entry.0)
+        .find(|known| *known == feature)
+}
+
+/// The known CPUID feature `name` names, loosely matched (`sse4_2` for
+/// `SSE4.2`).
+pub fn parse_feature(name: &str) -> Option<&'static str> {
+    let loose = loose_name(name);
+    INTRODUCTIONS
+        .iter()
+        .map(|entry| entry.0)
+        .find(|feature| loose_name(feature) == loose)
+}
+
+/// Every name [`target_cpu`] accepts, for error messages.
+pub fn target_cpu_names() -> Vec<&'static str> {
+    LEVEL_FEATURES
+        .iter()
+        .map(|&(level, _)| level)
+        .chain(INTEL_GENERATIONS.iter().copied())
+        .chain(AMD_GENERATIONS.iter().copied())
+        .chain(CPU_ALIASES.iter().map(|&(alias, _)| alias))
+        .collect()
+}
+
+/// The canonical name and CPUID features of CPU `name`: a psABI level, a
+/// listed microarchitecture (loosely matched) or a `-march` alias for one.
+/// A microarchitecture has every feature its vendor introduced up to it,
+/// less its [`LACKS`] entry, so the result is as approximate as the list
+/// order (see data/isa.toml).
+pub fn target_cpu(name: &str) -> Option<(&'static str, Vec<&'static str>)> {
+    let lowercase = name.to_lowercase();
+    if let Some(last) = LEVEL_FEATURES
+        .iter()
+        .position(|&(level, _)| level == lowercase)
+    {
+        let features = LEVEL_FEATURES[..=last]
+            .iter()
+            .flat_map(|&(_, features)| features.iter().copied())
+            .collect();
+        return Some((LEVEL_FEATURES[last].0, features));
+    }
+    let loose = loose_name(name);
+    let generation = CPU_ALIASES
+        .iter()
+        .find(|&&(alias, _)| alias == lowercase)
+        .map(|&(_, generation)| generation)
+        .or_else(|| {
+            INTEL_GENERATIONS
+                .iter()
+                .chain(AMD_GENERATIONS)
+                .copied()
+                .find(|generation| loose_name(generation) == loose)
+        })?;
+    let (generations, pick): (_, fn(&Introduction) -> Option<&'static str>) =
+        if INTEL_GENERATIONS.contains(&generation) {
+            (INTEL_GENERATIONS, |entry| entry.1)
+        } else {
+            (AMD_GENERATIONS, |entry| entry.2)
+        };
+    let position = |name: &str| generations.iter().position(|g| *g == name);
+    let index = position(generation)?;
+    let lacks = LACKS
+        .iter()
+        .find(|&&(listed, _)| listed == generation)
+        .map_or(&[][..], |&(_, features)| features);
+    let features = INTRODUCTIONS
+        .iter()
+        .filter(|entry| {
+            pick(entry)
+                .and_then(position)
+                .is_some_and(|first| first <= index)
+        })
+        .map(|entry| entry.0)
+        .filter(|feature| !lacks.contains(feature))
+        .collect();
+    Some((generation, features))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classify::tests::{mnemonics, run, run_with};
+    use crate::report;
+
+    #[test]
+    fn builtin_table_has_no_unranked_collisions() {
+        let mut owners: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (&isa, mnemonics) in ISA_TABLE.iter() {
+            for &mnemonic in mnemonics {
+                owners.entry(mnemonic).or_default().push(isa);
+            }
+        }
+        let mut unresolved: Vec<String> = owners
+            .iter()
+            .filter(|(_, isas)| isas.iter().filter(|isa| rank(isa).is_none()).count() > 1)
+            .map(|(mnemonic, isas)| format!("{mnemonic}: {isas:?}"))
+            .collect();
+        unresolved.sort();
+        assert!(unresolved.is_empty(), "{unresolved:?}");
+    }
+
+    #[test]
+    fn shared_mnemonic_goes_to_the_oldest_isa() {
+        let index = index(&ISA_TABLE);
+        assert_eq!(index["pextrw"], "SSE");
+    }
+
+    #[test]
+    fn size_suffixes_stripped_only_when_known() {
+        let index = index(&ISA_TABLE);
+        let known = |mnemonic: &str| is_known(&index, mnemonic);
+        // Spellings binutils 2.40 prints for memory operands
+        let suffixed = [
+            ("cvtsi2sdl", "cvtsi2sd"),
+            ("cvtsi2ssq", "cvtsi2ss"),
+            ("vcvtsi2sdl", "vcvtsi2sd"),
+            ("vcvtsi2ssq", "vcvtsi2ss"),
+            ("vcvtpd2dqx", "vcvtpd2dq"),
+            ("vcvtpd2dqy", "vcvtpd2dq"),
+            ("vcvtpd2psx", "vcvtpd2ps"),
+            ("vcvttpd2dqy", "vcvttpd2dq"),
+            ("vcvtusi2sdl", "vcvtusi2sd"),
+            ("vcvtusi2ssq", "vcvtusi2ss"),
+            ("vcvtqq2psy", "vcvtqq2ps"),
+            ("vfpclasspdz", "vfpclasspd"),
+            ("vfpclasspsx", "vfpclassps"),
+            ("vcvtneps2bf16y", "vcvtneps2bf16"),
+        ];
+        for (spelled, bare) in suffixed {
+            assert_eq!(strip_size_suffix(spelled, known), bare);
+        }
+        for kept in [
+            "orq",
+            "addl",
+            "pslldq",
+            "movq",
+            "vpsllq",
+            "vcvtuqq2ps",
+            "vpermq",
+        ] {
+            assert_eq!(strip_size_suffix(kept, known), kept);
+        }
+    }
+
+    #[test]
+    fn suffixed_conversions_counted() {
+        let classification = run(include_str!("../testdata/suffixes.objdump"));
+        // Every line but `orq`, `addl` and `ret`
+        assert_eq!(classification.isa_counts.values().sum::<usize>(), 16);
+        assert!(classification.unclassified.is_empty());
+    }
+
+    #[test]
+    fn predicate_pseudo_ops() {
+        // Spellings binutils 2.40 prints, one per line of the fixture
+        let text = include_str!("../testdata/predicates.objdump");
+        let folded: Vec<(&str, Option<String>)> = text
+            .lines()
+            .filter_map(|line| line.split('\t').nth(2))
+            .filter_map(|inst| inst.split_whitespace().next())
+            .map(|mnemonic| (mnemonic, base_mnemonic(mnemonic)))
+            .collect();
+        let expected = [
+            ("cmpltps", Some("cmpps")),
+            ("cmpnltps", Some("cmpps")),
+            ("cmpunordsd", Some("cmpsd")),
+            ("vcmplt_oqps", Some("vcmpps")),
+            ("vcmpgepd", Some("vcmppd")),
+            ("vcmptrue_ussd", Some("vcmpsd")),
+            ("vcmpneq_oqps", Some("vcmpps")),
+            ("vcmpltph", Some("vcmpph")),
+            ("vcmpunordsh", Some("vcmpsh")),
+            // Integer compares and carry-less multiplies are listed as spelled
+            ("vpcmpnequb", None),
+            ("vpcmpltd", None),
+            ("vpcmpnltuq", None),
+            ("pclmulhqhqdq", None),
+            ("pclmullqlqdq", None),
+            ("vpclmullqhqdq", None),
+            ("vpclmulhqlqdq", None),
+            ("cmpxchg", None),
+            ("cmpxchg16b", None),
+            ("cmpsb", None),
+            ("ret", None),
+        ];
+        let expected: Vec<(&str, Option<String>)> = expected
+            .iter()
+            .map(|(mnemonic, base)| (*mnemonic, base.map(str::to_string)))
+            .collect();
+        assert_eq!(folded, expected);
+
+        let classification = run(text);
+        assert!(classification.unclassified.is_empty());
+        let counts: Vec<(&str, usize)> = classification
+            .isa_counts
+            .iter()
+            .map(|(isa, count)| (isa.as_str(), *count))
+            .collect();
+        assert_eq!(
+            counts,
+            [
+                ("AVX", 3),
+                ("AVX512-BW", 1),
+                ("AVX512-F", 2),
+                ("AVX512-FP16", 2),
+                ("AVX512-VL", 1),
+                ("PCLMUL", 3),
+                ("SSE", 2),
+                ("SSE2", 1),
+                ("VPCLMULQDQ", 1)
+            ]
+        );
+        // Counted under the spelling objdump printed
+        assert_eq!(
+            mnemonics(&classification, "SSE"),
+            [("cmpltps", 1), ("cmpnltps", 1)]
+        );
+        assert_eq!(mnemonics(&classification, "AVX512-BW"), [("vpcmpnequb", 1)]);
+    }
+
+    #[test]
+    fn classification_is_identical_across_runs() {
+        let text = include_str!("../testdata/kernels.objdump");
+        let serialized = |index: &IsaIndex| {
+            let classification = run_with(text, |opts| opts.index = index);
+            let details = report::build_details(
+                classification.inst_detail,
+                classification.inst_addresses,
+                usize::MAX,
+                0,
+            );
+            serde_json::to_string(&(classification.isa_counts, details)).unwrap()
+        };
+        // A fresh table and index each time, so `HashMap` order varies
+        // between runs
+        let first = serialized(&index(&build_table(ISA_DATA)));
+        for _ in 0..99 {
+            assert_eq!(serialized(&index(&build_table(ISA_DATA))), first);
+        }
+    }
+}
@@ -0,0 +1,288 @@
+//! User-supplied ISA tables (`--isa-table`), merged into or replacing the
+//! built-in [`isa::ISA_TABLE`].
+//!
+//! ```toml
+//! mode = "extend"   # or "replace"
+//! TierA = ["vaddps", "vmulps"]
+//! AVX2 = ["vpmaskmovd"]
+//! runtime_routines = ["^__my_memcpy_avx2$"]
+//! ignore_mnemonics = ["pshufb"]
+//! ```
+//!
+//! `runtime_routines` adds symbol regexes to the built-in
+//! [`runtime::BUILTIN`] list in either mode, and `ignore_mnemonics` works
+//! like `--ignore-mnemonic`.
+//!
+//! `--reclassify` and `--ignore-mnemonic` are applied on top, see
+//! [`Overrides`].
+
+use crate::isa::{self, IsaTable};
+use crate::runtime;
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+enum Mode {
+    /// Add to the built-in table
+    #[default]
+    Extend,
+    /// Use only the categories in the file
+    Replace,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TableFile {
+    #[serde(default)]
+    mode: Mode,
+    /// Extra runtime routine patterns (see `runtime`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    runtime_routines: Vec<String>,
+    /// Mnemonic globs left out of every count, before `--ignore-mnemonic`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    ignore_mnemonics: Vec<String>,
+    /// Category name -> mnemonics
+    #[serde(flatten)]
+    categories: IndexMap<String, Vec<String>>,
+}
+
+/// `$XDG_CONFIG_HOME/simdscan/isa-table.toml` (or `~/.config/...`), if it exists.
+pub fn default_path() -> Option<PathBuf> {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    let path = config.join("simdscan").join("isa-table.toml");
+    path.is_file().then_some(path)
+}
+
+/// The table lives for the whole run, like the built-in one.
+fn leak(text: String) -> &'static str {
+    Box::leak(text.into_boxed_str())
+}
+
+fn parse(path: &Path) -> Result<TableFile> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read ISA table '{}'", path.display()))?;
+    let is_json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    if is_json {
+        serde_json::from_str(&text)
+            .with_context(|| format!("Invalid ISA table '{}'", path.display()))
+    } else {
+        toml::from_str(&text).with_context(|| format!("Invalid ISA table '{}'", path.display()))
+    }
+}
+
+/// The effective ISA table: the built-in one, with the file at `path` merged
+/// in or substituted. A mnemonic listed under two categories is an error, in
+/// the file or between the file and the built-in categories it extends.
+pub fn load(path: Option<&Path>) -> Result<IsaTable> {
+    let Some(path) = path else {
+        return Ok(isa::ISA_TABLE.clone());
+    };
+    let file = parse(path)?;
+
+    let mut owner: HashMap<String, &'static str> = HashMap::new();
+    let mut table = match file.mode {
+        Mode::Extend => isa::ISA_TABLE.clone(),
+        Mode::Replace => IsaTable::new(),
+    };
+    for (&isa, mnemonics) in &table {
+        for mnemonic in mnemonics {
+            owner.insert(mnemonic.to_string(), isa);
+        }
+    }
+
+    for (category, mnemonics) in file.categories {
+        let category: &'static str = match table.get_key_value(category.as_str()) {
+            Some((&existing, _)) => existing,
+            None => leak(category),
+        };
+        for mnemonic in mnemonics {
+            let mnemonic = mnemonic.to_lowercase();
+            match owner.get(&mnemonic) {
+                Some(&other) if other != category => anyhow::bail!(
+                    "ISA table '{}': '{}' is listed under both '{}' and '{}'",
+                    path.display(),
+                    mnemonic,
+                    other,
+                    category
+                ),
+                Some(_) => {}
+                None => {
+                    owner.insert(mnemonic.clone(), category);
+                    table.entry(category).or_default().insert(leak(mnemonic));
+                }
+            }
+        }
+        table.entry(category).or_default();
+    }
+    Ok(table)
+}
+
+/// The keys of a table file that aren't ISA categories.
+pub struct Settings {
+    /// The built-in runtime routine patterns plus the file's
+    pub runtime_routines: regex::RegexSet,
+    pub ignore_mnemonics: Vec<String>,
+}
+
+/// The [`Settings`] of the file at `path`, the built-in ones without a file.
+pub fn settings(path: Option<&Path>) -> Result<Settings> {
+    let Some(path) = path else {
+        return Ok(Settings {
+            runtime_routines: runtime::patterns(&[], "")?,
+            ignore_mnemonics: Vec::new(),
+        });
+    };
+    let file = parse(path)?;
+    Ok(Settings {
+        runtime_routines: runtime::patterns(
+            &file.runtime_routines,
+            &format!("'{}'", path.display()),
+        )?,
+        ignore_mnemonics: file.ignore_mnemonics,
+    })
+}
+
+/// Whether `text` matches `pattern`, where `*` stands for any run of
+/// characters and `?` for any one.
+pub fn glob_matches(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|skip| glob_matches(rest, &text[skip..])),
+        Some((&c, rest)) => text
+            .split_first()
+            .is_some_and(|(&t, text)| (c == b'?' || c == t) && glob_matches(rest, text)),
+    }
+}
+
+/// Whether one of the `--ignore-mnemonic` globs in `patterns` matches
+/// `mnemonic`.
+pub fn ignored(patterns: &[String], mnemonic: &str) -> bool {
+    patterns
+        .iter()
+        .any(|pattern| glob_matches(pattern.as_bytes(), mnemonic.as_bytes()))
+}
+
+/// Per-run changes from `--reclassify <mnemonic>=<category>` and
+/// `--ignore-mnemonic`, applied after any table file.
+#[derive(Default)]
+pub struct Overrides {
+    /// Mnemonic -> the category it moves to, in command-line order
+    pub reclassify: IndexMap<String, String>,
+    /// Mnemonic globs (`pmov*`) dropped from every count
+    pub ignore: Vec<String>,
+}
+
+impl Overrides {
+    /// Parses the flag values. Giving one mnemonic two different categories,
+    /// or both reclassifying and ignoring it, is an error.
+    pub fn parse(reclassify: &[String], ignore: &[String]) -> Result<Self> {
+        let mut overrides = Overrides::default();
+        for spec in reclassify {
+            let Some((mnemonic, category)) = spec
+                .split_once('=')
+                .map(|(m, c)| (m.trim().to_lowercase(), c.trim()))
+                .filter(|(m, c)| !m.is_empty() && !c.is_empty())
+            else {
+                anyhow::bail!("Invalid --reclassify '{spec}': expected <mnemonic>=<category>");
+            };
+            match overrides.reclassify.get(&mnemonic) {
+                Some(other) if other != category => anyhow::bail!(
+                    "Conflicting overrides: '{mnemonic}' reclassified as both '{other}' and '{category}'"
+                ),
+                Some(_) => {}
+                None => {
+                    overrides.reclassify.insert(mnemonic, category.to_string());
+                }
+            }
+        }
+        for pattern in ignore {
+            let pattern = pattern.trim().to_lowercase();
+            let reclassified = overrides
+                .reclassify
+                .iter()
+                .find(|(mnemonic, _)| glob_matches(pattern.as_bytes(), mnemonic.as_bytes()));
+            if let Some((mnemonic, category)) = reclassified {
+                anyhow::bail!(
+                    "Conflicting overrides: '{mnemonic}' is both reclassified as '{category}' and ignored"
+                );
+            }
+            if !pattern.is_empty() && !overrides.ignore.contains(&pattern) {
+                overrides.ignore.push(pattern);
+            }
+        }
+        Ok(overrides)
+    }
+
+    /// Fails on an ignore pattern that matches no mnemonic `table` (or the
+    /// scalar, other-extension or x87 tables) knows, likely a typo.
+    pub fn check_ignores(&self, table: &IsaTable) -> Result<()> {
+        let unknown: Vec<&str> = self
+            .ignore
+            .iter()
+            .filter(|pattern| {
+                !isa::known_mnemonics(table)
+                    .any(|mnemonic| glob_matches(pattern.as_bytes(), mnemonic.as_bytes()))
+            })
+            .map(String::as_str)
+            .collect();
+        if !unknown.is_empty() {
+            anyhow::bail!(
+                "--ignore-mnemonic: no known mnemonic matches '{}' (--allow-unknown-ignores to keep it anyway)",
+                unknown.join("', '")
+            );
+        }
+        Ok(())
+    }
+
+    /// Moves each reclassified mnemonic out of whatever categories list it
+    /// and into its new one, created if no category of that name (in any
+    /// case) exists; ignored mnemonics are removed altogether.
+    pub fn apply(&self, table: &mut IsaTable) {
+        let overridden = |mnemonic: &str| {
+            self.reclassify.contains_key(mnemonic) || ignored(&self.ignore, mnemonic)
+        };
+        for mnemonics in table.values_mut() {
+            mnemonics.retain(|&m| !overridden(m));
+        }
+        for (mnemonic, category) in &self.reclassify {
+            let existing = table
+                .keys()
+                .find(|key| key.eq_ignore_ascii_case(category))
+                .copied();
+            let category = existing.unwrap_or_else(|| leak(category.clone()));
+            table
+                .entry(category)
+                .or_default()
+                .insert(leak(mnemonic.clone()));
+        }
+    }
+}
+
+/// `table` as a replace-mode TOML file, categories and mnemonics sorted, so
+/// the output can be fed back to `--isa-table`.
+pub fn dump(table: &IsaTable) -> Result<String> {
+    let mut categories: IndexMap<String, Vec<String>> = table
+        .iter()
+        .map(|(isa, mnemonics)| {
+            let mut mnemonics: Vec<String> = mnemonics.iter().map(|m| m.to_string()).collect();
+            mnemonics.sort_unstable();
+            (isa.to_string(), mnemonics)
+        })
+        .collect();
+    categories.sort_keys();
+    let file = TableFile {
+        mode: Mode::Replace,
+        runtime_routines: Vec::new(),
+        ignore_mnemonics: Vec::new(),
+        categories,
+    };
+    Ok(toml::to_string(&file)?)
+}
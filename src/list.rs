@@ -0,0 +1,99 @@
+//! `list-isas` and `list-mnemonics`: what the effective ISA table contains.
+//! Everything is sorted so the output can be diffed or used as a golden file.
+
+use crate::isa::{self, IsaTable};
+use crate::policy;
+use anyhow::Result;
+use serde::Serialize;
+use std::fmt::Write;
+
+/// Output format for the listing subcommands.
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ListFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Serialize)]
+struct IsaEntry {
+    isa: &'static str,
+    mnemonics: usize,
+    /// psABI level name, e.g. `x86-64-v3`; `None` if no level guarantees it
+    level: Option<String>,
+}
+
+#[derive(Serialize)]
+struct MnemonicEntry {
+    mnemonic: &'static str,
+    isa: &'static str,
+}
+
+fn sorted_keys(table: &IsaTable) -> Vec<&'static str> {
+    let mut keys: Vec<&'static str> = table.keys().copied().collect();
+    keys.sort_unstable();
+    keys
+}
+
+pub fn isas(table: &IsaTable, format: ListFormat) -> Result<String> {
+    let entries: Vec<IsaEntry> = sorted_keys(table)
+        .into_iter()
+        .map(|isa| IsaEntry {
+            isa,
+            mnemonics: table[isa].len(),
+            level: isa::isa_level(isa).map(isa::level_name),
+        })
+        .collect();
+    if format == ListFormat::Json {
+        return Ok(serde_json::to_string_pretty(&entries)? + "\n");
+    }
+
+    let isa_w = entries
+        .iter()
+        .map(|e| e.isa.len())
+        .max()
+        .unwrap_or(0)
+        .max(3);
+    let mut out = String::new();
+    let _ = writeln!(out, "{:<isa_w$}  MNEMONICS  LEVEL", "ISA");
+    for entry in &entries {
+        let _ = writeln!(
+            out,
+            "{:<isa_w$}  {:>9}  {}",
+            entry.isa,
+            entry.mnemonics,
+            entry.level.as_deref().unwrap_or("-")
+        );
+    }
+    Ok(out)
+}
+
+/// Every mnemonic in `table`, or only those of `only` (matched
+/// case-insensitively against the table's ISA keys).
+pub fn mnemonics(table: &IsaTable, only: Option<&str>, format: ListFormat) -> Result<String> {
+    let keys = match only {
+        Some(name) => policy::resolve_isas(&[name.to_string()], table.keys().copied())?
+            .into_iter()
+            .filter_map(|isa| table.get_key_value(isa.as_str()).map(|(&key, _)| key))
+            .collect(),
+        None => sorted_keys(table),
+    };
+    let mut entries: Vec<MnemonicEntry> = keys
+        .into_iter()
+        .flat_map(|isa| {
+            table[isa]
+                .iter()
+                .map(move |&mnemonic| MnemonicEntry { mnemonic, isa })
+        })
+        .collect();
+    entries.sort_unstable_by(|a, b| a.mnemonic.cmp(b.mnemonic).then(a.isa.cmp(b.isa)));
+    if format == ListFormat::Json {
+        return Ok(serde_json::to_string_pretty(&entries)? + "\n");
+    }
+
+    let mut out = String::new();
+    for entry in &entries {
+        let _ = writeln!(out, "{}\t{}", entry.mnemonic, entry.isa);
+    }
+    Ok(out)
+}
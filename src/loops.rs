@@ -0,0 +1,125 @@
+//! `--loop-heuristic`: SIMD inside loop bodies vs straight-line code, so
+//! vectorized loops stand apart from prologue spills and epilogue restores.
+//!
+//! A heuristic, not control-flow analysis: every conditional branch (`jcc`,
+//! `loop*`) to a lower address in the same symbol is taken as a loop's back
+//! edge, and everything from its target up to the branch as the loop body.
+//! Loops closed by an unconditional `jmp`, or spread over `.cold` splits,
+//! are missed; code jumped over inside a body still counts as in the loop.
+
+use crate::classify::FunctionCounts;
+use indexmap::IndexMap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// SIMD instructions inside and outside loop bodies.
+#[derive(Serialize, Deserialize, JsonSchema, Default, Clone, Copy)]
+pub struct LoopSplit {
+    pub in_loop: usize,
+    pub straight_line: usize,
+}
+
+impl LoopSplit {
+    fn add(&mut self, in_loop: bool) {
+        if in_loop {
+            self.in_loop += 1;
+        } else {
+            self.straight_line += 1;
+        }
+    }
+
+    pub fn merge(&mut self, other: &LoopSplit) {
+        self.in_loop += other.in_loop;
+        self.straight_line += other.straight_line;
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Default)]
+pub struct LoopSummary {
+    /// Backward conditional branches found, i.e. loops (nested ones included)
+    pub loops: usize,
+    pub in_loop: usize,
+    pub straight_line: usize,
+    /// `in_loop` as a percentage of all SIMD instructions, to two decimals
+    pub in_loop_percent: f64,
+    pub by_isa: IndexMap<String, LoopSplit>,
+}
+
+/// One SIMD instruction of the current symbol: address, ISA key, and its
+/// indexes into classify's `functions` and `parent_functions`.
+type Pending = (u64, &'static str, usize, usize);
+
+/// Collects one symbol at a time; [`Tracker::flush`] settles it once the
+/// symbol's last instruction has been seen.
+#[derive(Default)]
+pub struct Tracker {
+    /// Address of the current symbol, if inside one
+    start: Option<u64>,
+    /// `(target, branch)` address pairs
+    back_edges: Vec<(u64, u64)>,
+    simd: Vec<Pending>,
+    summary: LoopSummary,
+}
+
+impl Tracker {
+    /// Start a new symbol at `start` (`None` outside any symbol). Call
+    /// [`Tracker::flush`] first.
+    pub fn enter(&mut self, start: Option<u64>) {
+        self.start = start;
+    }
+
+    /// A conditional branch at `address` to `target`.
+    pub fn branch(&mut self, address: u64, target: u64) {
+        if self.start.is_some_and(|start| target >= start) && target <= address {
+            self.back_edges.push((target, address));
+        }
+    }
+
+    pub fn simd(&mut self, address: u64, isa: &'static str, function: usize, parent: usize) {
+        self.simd.push((address, isa, function, parent));
+    }
+
+    /// Split the current symbol's SIMD instructions into loop bodies and
+    /// straight-line code, counting them into `functions` and `parents` too.
+    pub fn flush(
+        &mut self,
+        functions: &mut IndexMap<String, FunctionCounts>,
+        parents: &mut IndexMap<String, FunctionCounts>,
+    ) {
+        self.summary.loops += self.back_edges.len();
+        // Merge into disjoint, sorted bodies
+        self.back_edges.sort_unstable();
+        let mut bodies: Vec<(u64, u64)> = Vec::new();
+        for &(start, end) in &self.back_edges {
+            match bodies.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => bodies.push((start, end)),
+            }
+        }
+        for (address, isa, function, parent) in self.simd.drain(..) {
+            let before = bodies.partition_point(|&(start, _)| start <= address);
+            let in_loop = before > 0 && address <= bodies[before - 1].1;
+            if in_loop {
+                self.summary.in_loop += 1;
+            } else {
+                self.summary.straight_line += 1;
+            }
+            self.summary
+                .by_isa
+                .entry(isa.to_string())
+                .or_default()
+                .add(in_loop);
+            functions[function].loops.add(in_loop);
+            parents[parent].loops.add(in_loop);
+        }
+        self.back_edges.clear();
+    }
+
+    /// Totals over every flushed symbol.
+    pub fn finish(mut self) -> LoopSummary {
+        let total = self.summary.in_loop + self.summary.straight_line;
+        self.summary.in_loop_percent = crate::report::percent(self.summary.in_loop, total);
+        self.summary.by_isa.sort_keys();
+        self.summary
+    }
+}
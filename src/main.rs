@@ -1,148 +1,526 @@
+mod binary_info;
+mod callgraph;
+mod classify;
+mod clones;
+mod compat;
+mod deps;
+mod dispatch;
+mod encoding;
+mod explain;
+mod gnu_property;
+mod guards;
+mod heaviness;
+mod host;
+mod isa;
+mod isa_table;
+mod list;
+mod loops;
+mod output;
+mod policy;
+mod profile;
+mod report;
+mod runtime;
+mod source;
+mod text_data;
+mod transitions;
+mod walk;
+
 use anyhow::{Context, Result};
-use clap::{Parser, ValueEnum};
+use binary_info::BinaryInfo;
+use clap::Parser;
+use classify::Classification;
 use indexmap::IndexMap;
-use lazy_static::lazy_static;
-use regex::Regex;
-use serde::Serialize;
-use std::collections::{HashMap, HashSet};
-use std::path::PathBuf;
-use std::process::Command;
+use output::{OutputFormat, RenderOptions, Sink, Template};
+use report::{Disassembler, Meta, Report};
+use std::io::{self, IsTerminal, Write};
+use std::num::NonZeroUsize;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
+use std::time::{Duration, Instant, SystemTime};
 
 #[derive(Parser)]
 #[command(
     name = "simdscan",
     about = "Classify SIMD instructions by ISA extension",
-    long_about = "Analyze x86-64 binaries to detect and classify SIMD instructions by their ISA extension (SSE, AVX, etc.)"
+    long_about = "Analyze x86-64 binaries to detect and classify SIMD instructions by their ISA extension (SSE, AVX, etc.)",
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
 )]
 struct Args {
-    /// Path to the binary file (ELF, Mach-O, or PE)
-    binary: PathBuf,
+    #[command(subcommand)]
+    command: Option<Subcommand>,
+
+    /// Paths to the binary files (ELF, Mach-O, or PE), or with --recursive
+    /// directories to search. With more than one, each is scanned in turn:
+    /// JSON, YAML and TOML give `reports` and an `aggregate` of their counts,
+    /// a failed scan becomes an error entry, and the rest still run
+    #[arg(required_unless_present = "dump_table", value_name = "BINARY")]
+    binaries: Vec<PathBuf>,
+
+    /// Scan every ELF, Mach-O and PE file under the directories given,
+    /// recognised by its first bytes; always gives the multi-binary output,
+    /// with the files passed over counted in `aggregate.skipped`
+    #[arg(short, long, conflicts_with_all = ["profile", "perf_data", "load_address", "debug_file"])]
+    recursive: bool,
+
+    /// With --recursive, only take files matching one of these globs
+    /// (repeatable; `*` and `?`). A glob with a `/` is matched against the
+    /// path below the directory given, one without against the file name
+    #[arg(long, value_name = "GLOB", requires = "recursive")]
+    include: Vec<String>,
 
-    /// Output format
-    #[arg(short, long, value_enum, default_value = "json")]
-    format: OutputFormat,
+    /// With --recursive, leave out files and directories matching one of
+    /// these globs (repeatable, matched like --include)
+    #[arg(long, value_name = "GLOB", requires = "recursive")]
+    exclude: Vec<String>,
+
+    /// With --recursive, look at most N levels down; 1 takes only each
+    /// directory's own entries
+    #[arg(long, value_name = "N", requires = "recursive")]
+    max_depth: Option<NonZeroUsize>,
+
+    /// Output format [default: json, or inferred from the --output extension]
+    #[arg(short, long, value_enum)]
+    format: Option<OutputFormat>,
 
     /// Include per-ISA instruction breakdown
     #[arg(long)]
     show_insts: bool,
+
+    /// How many mnemonics per ISA the details list, or `all`
+    #[arg(long, value_name = "N", default_value = "10", value_parser = parse_top)]
+    top: usize,
+
+    /// Add up to N addresses of each listed mnemonic to the details
+    /// [default: 5]; with --per-function also as `symbol+0xoffset`
+    #[arg(long, value_name = "N", num_args = 0..=1, require_equals = true, default_missing_value = "5")]
+    show_addresses: Option<usize>,
+
+    /// List every mnemonic in the details, same as `--top all`
+    #[arg(long, conflicts_with = "top")]
+    all_insts: bool,
+
+    /// List the N functions with the most SIMD instructions [default: 10]
+    #[arg(long, value_name = "N", num_args = 0..=1, require_equals = true, default_missing_value = "10")]
+    show_functions: Option<usize>,
+
+    /// Add a `functions` list with each function's address and per-ISA counts;
+    /// `=all` includes functions without SIMD
+    #[arg(long, value_enum, value_name = "WHICH", num_args = 0..=1, require_equals = true, default_missing_value = "simd")]
+    per_function: Option<PerFunction>,
+
+    /// Order of `isa_summary`, `isa_breakdown` and `isa_details` in every
+    /// format: by key, by count, or by ISA generation
+    #[arg(long, value_enum, value_name = "ORDER", default_value_t = report::IsaSort::Name)]
+    sort_by: report::IsaSort,
+
+    /// Order of the --show-functions and --per-function lists
+    #[arg(long, value_enum, value_name = "ORDER", default_value_t = report::FunctionSort::SimdCount)]
+    sort_functions: report::FunctionSort,
+
+    /// Leave functions with fewer than N instructions out of the
+    /// --show-functions and --per-function lists
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    min_function_insts: usize,
+
+    /// Only list functions whose newest ISA (`requires`) is one of these keys
+    /// in --show-functions and --per-function (repeatable, comma-separated);
+    /// `avx512` covers every AVX-512 subset
+    #[arg(long, value_name = "ISA", value_delimiter = ',')]
+    functions_requiring: Vec<String>,
+
+    /// Split SIMD counts into loop bodies and straight-line code, found by
+    /// a backward-branch heuristic
+    #[arg(long)]
+    loop_heuristic: bool,
+
+    /// Weight the counts by this `address,count` sample profile (hex
+    /// addresses), adding `weighted_counts`
+    #[arg(long, value_name = "FILE", conflicts_with = "perf_data")]
+    profile: Option<PathBuf>,
+
+    /// Weight the counts by the samples in this perf.data (via `perf script`)
+    #[arg(long, value_name = "FILE")]
+    perf_data: Option<PathBuf>,
+
+    /// Where the binary was loaded in the profiled process (hex), subtracted
+    /// from profile addresses; --perf-data reads it from the mmap events
+    #[arg(long, value_name = "ADDR", value_parser = parse_address)]
+    load_address: Option<u64>,
+
+    /// Add `source_files`: SIMD counts per source file, from the debug line
+    /// info (via addr2line)
+    #[arg(long)]
+    by_source: bool,
+
+    /// Read line info for --by-source from this split debug file instead of
+    /// the binary or its .gnu_debuglink target
+    #[arg(long, value_name = "PATH", requires = "by_source")]
+    debug_file: Option<PathBuf>,
+
+    /// Leave ISAs with fewer than N instructions out of the summary and
+    /// details; they are totalled under `suppressed`
+    #[arg(long, value_name = "N")]
+    min_count: Option<usize>,
+
+    /// Leave ISAs under this percentage of all instructions out of the
+    /// summary and details, like --min-count
+    #[arg(long, value_name = "PERCENT")]
+    min_percent: Option<f64>,
+
+    /// Only report these ISA keys (repeatable, comma-separated,
+    /// case-insensitive); `avx512` covers every AVX-512 subset. Policy checks,
+    /// `has_simd` and the required level still see every ISA
+    #[arg(long, value_name = "ISA", value_delimiter = ',')]
+    only_isa: Vec<String>,
+
+    /// Leave these ISA keys out of the report, like --only-isa
+    #[arg(long, value_name = "ISA", value_delimiter = ',')]
+    exclude_isa: Vec<String>,
+
+    /// Count only the ISAs --only-isa / --exclude-isa keep in
+    /// `total_simd_insts` and `simd_percent`, overall and per function
+    #[arg(long)]
+    recompute_totals: bool,
+
+    /// Leave ISA-specific variants of multiversioned functions (FMV clones,
+    /// glibc IFUNC implementations) out of `required_level` and `min_cpu`
+    #[arg(long)]
+    assume_dispatch: bool,
+
+    /// Split SIMD counts into `guarded_isas` and `unconditional_isas` by
+    /// whether the functions holding them are only reached after a `cpuid`
+    /// / `xgetbv` check or feature-cache read (a heuristic over direct calls)
+    #[arg(long)]
+    dispatch_analysis: bool,
+
+    /// Demangler for function names
+    #[arg(long, value_enum, value_name = "STYLE", default_value_t = classify::DemangleStyle::Auto)]
+    demangle: classify::DemangleStyle,
+
+    /// Show raw symbol names instead of demangling them
+    #[arg(long, conflicts_with_all = ["demangle", "strip_hash"])]
+    no_demangle: bool,
+
+    /// Drop the hash from demangled Rust names so reports diff across builds
+    #[arg(long)]
+    strip_hash: bool,
+
+    /// How finely to split ISA keys; `coarse` restores the single SSE4 bucket
+    #[arg(long, value_enum, default_value_t = isa::Granularity::Fine)]
+    isa_granularity: isa::Granularity,
+
+    /// Also report BMI1 and BMI2 (scalar extensions that gate x86-64-v3) and
+    /// the SSE2 GPR store movnti
+    #[arg(long)]
+    include_scalar_ext: bool,
+
+    /// Also report x87 floating-point instructions (fld, faddp, fsin, ...)
+    #[arg(long)]
+    include_x87: bool,
+
+    /// Flag functions that run legacy SSE (or return) after 256/512-bit VEX
+    /// code without vzeroupper, or x87 after MMX without emms
+    #[arg(long)]
+    check_transitions: bool,
+
+    /// Split AVX-512 instructions into heavy (512-bit FP math, FMA, integer
+    /// multiply) and light ones, for frequency-license planning
+    #[arg(long)]
+    avx512_heaviness: bool,
+
+    /// Also report these non-vector extension groups (rdrand, rdseed, tsx,
+    /// cet, waitpkg, cldemote, clwb, clflushopt), or `all`
+    #[arg(long, value_name = "GROUP", value_delimiter = ',')]
+    extensions: Vec<String>,
+
+    /// Count crc32, popcnt, and lzcnt as SSE4.2 SIMD instructions again
+    /// instead of scalar extensions
+    #[arg(long)]
+    legacy_sse4_grouping: bool,
+
+    /// Extra or replacement ISA categories from a TOML/JSON file
+    /// [default: $XDG_CONFIG_HOME/simdscan/isa-table.toml if present]
+    #[arg(long, value_name = "FILE")]
+    isa_table: Option<PathBuf>,
+
+    /// Count this mnemonic under another ISA category for this run, creating
+    /// the category if needed (repeatable)
+    #[arg(long, value_name = "MNEMONIC=CATEGORY")]
+    reclassify: Vec<String>,
+
+    /// Leave instructions with this mnemonic out of every count (repeatable,
+    /// comma-separated); `*` and `?` globs such as `pmov*` are allowed
+    #[arg(
+        long,
+        visible_alias = "ignore-mnemonics",
+        value_name = "MNEMONIC",
+        value_delimiter = ','
+    )]
+    ignore_mnemonic: Vec<String>,
+
+    /// Keep --ignore-mnemonic patterns that match no known mnemonic instead
+    /// of failing
+    #[arg(long)]
+    allow_unknown_ignores: bool,
+
+    /// Print the effective ISA table as TOML and exit
+    #[arg(long)]
+    dump_table: bool,
+
+    /// Only classify instructions in symbols whose mangled or demangled name
+    /// matches this regex (repeatable)
+    #[arg(long, value_name = "REGEX")]
+    function: Vec<String>,
+
+    /// Only classify functions reachable from this symbol (mangled or
+    /// demangled name) through direct calls; indirect calls and calls into
+    /// dispatchers are listed under `reachability`, not followed
+    #[arg(long, value_name = "SYMBOL")]
+    entry: Option<String>,
+
+    /// Skip symbols whose mangled or demangled name matches this regex
+    /// (repeatable), e.g. a statically linked `__memmove_avx_unaligned_erms`
+    #[arg(long, value_name = "REGEX")]
+    exclude_function: Vec<String>,
+
+    /// Count `.plt*` sections, dynamic-linker/C runtime stubs
+    /// (`_dl_runtime_resolve_xsavec`, `__x86.get_pc_thunk.bx`, `_start`, ...)
+    /// and suspected data in text sections, which are skipped by default
+    #[arg(long)]
+    no_default_excludes: bool,
+
+    /// Count glibc, libmvec and compiler runtime SIMD routines
+    /// (`__memmove_avx_unaligned_erms`, `__strlen_evex`, ...) with the rest
+    /// of the binary instead of under `runtime_library`
+    #[arg(long)]
+    no_split_runtime: bool,
+
+    /// Only classify instructions at or after this address (hex, `0x` optional);
+    /// without --end, up to the end of the function containing it
+    #[arg(long, value_name = "ADDR", value_parser = parse_address)]
+    start: Option<u64>,
+
+    /// Only classify instructions below this address (hex, `0x` optional)
+    #[arg(long, value_name = "ADDR", value_parser = parse_address)]
+    end: Option<u64>,
+
+    /// Only classify instructions from these sections; `.text*` matches any
+    /// `.text` prefix [default: all executable sections]
+    #[arg(long, value_name = "SECTION", value_delimiter = ',')]
+    sections: Vec<String>,
+
+    /// Skip instructions from these sections, e.g. `.plt,.plt.got,.init,.fini`
+    #[arg(long, value_name = "SECTION", value_delimiter = ',')]
+    exclude_sections: Vec<String>,
+
+    /// Shortcut for `--sections '.text*'`
+    #[arg(long, conflicts_with = "sections")]
+    text_only: bool,
+
+    /// Handlebars template file for `--format template`, or builtin:oneline / builtin:markdown
+    #[arg(long, value_name = "FILE")]
+    template: Option<String>,
+
+    /// Write the report to a file instead of stdout (replaced atomically)
+    #[arg(short, long, value_name = "PATH")]
+    output: Option<PathBuf>,
+
+    /// Create missing parent directories of the --output path
+    #[arg(long, requires = "output")]
+    mkdirs: bool,
+
+    /// Don't print the summary line when writing to --output
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Omit the `meta` block (timestamp, versions, arguments) for byte-identical reruns
+    #[arg(long)]
+    no_meta: bool,
+
+    /// Append vector-looking mnemonics missing from the ISA tables to this file
+    #[arg(long, value_name = "FILE")]
+    collect_unknown: Option<PathBuf>,
+
+    /// Fail if any instruction line has no recognizable mnemonic
+    #[arg(long)]
+    strict: bool,
+
+    /// Add a `stats` block with timing, line throughput, and peak memory
+    #[arg(long)]
+    stats: bool,
+
+    /// Exit with status 2 if any instruction from this ISA is found (repeatable).
+    /// Also takes an operation category, `non-temporal`, `avx512-512bit`, or an
+    /// --extensions group
+    #[arg(long, value_name = "ISA", value_delimiter = ',')]
+    fail_if_found: Vec<String>,
+
+    /// Exit with status 2 if no instruction from this ISA is found (repeatable).
+    /// Also takes an operation category, `non-temporal`, `avx512-512bit`, or an
+    /// --extensions group
+    #[arg(long, value_name = "ISA", value_delimiter = ',')]
+    fail_if_missing: Vec<String>,
+
+    /// Add `compatibility`: the instructions that would fault on this CPU (a
+    /// microarchitecture like `sandybridge` or `znver2`, or a psABI level
+    /// like `x86-64-v2`), and exit with status 3 if there are any
+    #[arg(long, value_name = "CPU", conflicts_with = "target_features")]
+    target_cpu: Option<String>,
+
+    /// Like --target-cpu, for a CPU with these CPUID features on top of the
+    /// x86-64 baseline (comma-separated, e.g. `sse4.2,avx`)
+    #[arg(long, value_name = "FEATURE", value_delimiter = ',')]
+    target_features: Vec<String>,
+
+    /// Check the binary against the CPU simdscan runs on, like --target-cpu:
+    /// print whether it can run here to stderr and exit with status 3 if
+    /// not. Instructions only in guarded dispatch variants don't count
+    #[arg(long, conflicts_with_all = ["target_cpu", "target_features"])]
+    check_cpu: bool,
+
+    /// Example addresses per faulting ISA in `compatibility`
+    #[arg(long, value_name = "N", default_value_t = 5)]
+    target_addresses: usize,
+}
+
+/// Which functions `--per-function` lists.
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq, Eq)]
+enum PerFunction {
+    /// Functions containing SIMD instructions
+    Simd,
+    /// Every function
+    All,
 }
 
-#[derive(ValueEnum, Clone)]
-enum OutputFormat {
-    Json,
-    Yaml,
+#[derive(clap::Subcommand)]
+enum Subcommand {
+    /// Print the JSON Schema of the report
+    #[command(
+        long_about = "Print a JSON Schema (draft 2020-12) describing the report emitted by \
+the JSON, NDJSON, YAML, TOML, and CBOR formats: `oneOf` the report for one binary, the \
+report for several (`reports` plus an `aggregate`), and NDJSON's `{\"binary\", \"error\"}` \
+line for a binary that failed and closing `{\"skipped\"}` line with `--recursive`.
+
+Every report carries a `schema_version` integer. It is bumped whenever a field is \
+added, removed, renamed, or changes meaning. Optional fields (such as `isa_details`) \
+are omitted rather than set to null. Consumers should check `schema_version` and \
+ignore fields they do not recognize."
+    )]
+    Schema,
+
+    /// Show which ISA a mnemonic belongs to and what CPU it needs
+    Explain {
+        mnemonic: String,
+
+        #[command(flatten)]
+        table: TableArgs,
+    },
+
+    /// List the ISA categories with their mnemonic counts and psABI levels
+    ListIsas(TableArgs),
+
+    /// List the mnemonics the ISA table knows, with their ISA
+    ListMnemonics {
+        /// Only list this ISA's mnemonics
+        isa: Option<String>,
+
+        #[command(flatten)]
+        table: TableArgs,
+    },
 }
 
-#[derive(Serialize)]
-struct Report {
-    binary: String,
-    has_simd: bool,
-    isa_summary: IndexMap<String, usize>,
-    total_simd_insts: usize,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    isa_details: Option<IndexMap<String, IsaDetail>>,
+/// Options shared by the subcommands that read the ISA table.
+#[derive(clap::Args)]
+struct TableArgs {
+    /// Extra or replacement ISA categories, as for the main command
+    #[arg(long, value_name = "FILE")]
+    isa_table: Option<PathBuf>,
+
+    #[arg(short, long, value_enum, default_value_t = list::ListFormat::Text)]
+    format: list::ListFormat,
 }
 
-#[derive(Serialize)]
-struct IsaDetail {
-    unique_mnemonics: usize,
-    occurrences: IndexMap<String, usize>,
+impl TableArgs {
+    fn load(&self) -> Result<isa::IsaTable> {
+        let path = self.isa_table.clone().or_else(isa_table::default_path);
+        isa_table::load(path.as_deref())
+    }
 }
 
-lazy_static! {
-    static ref ISA_TABLE: HashMap<&'static str, HashSet<&'static str>> = {
-        let mut table = HashMap::new();
-
-        // SSE
-        table.insert("SSE", HashSet::from([
-            "addps", "addss", "andnps", "andps", "cmpps", "cmpss", "comiss",
-            "cvtpi2ps", "cvtps2pi", "cvtsi2ss", "cvtss2si", "cvttps2pi", "cvttss2si",
-            "divps", "divss", "ldmxcsr", "maxps", "maxss", "minps", "minss",
-            "movaps", "movhlps", "movhps", "movlhps", "movlps", "movmskps",
-            "movntps", "movss", "movups", "mulps", "mulss", "orps", "rcpps",
-            "rcpss", "rsqrtps", "rsqrtss", "shufps", "sqrtps", "sqrtss",
-            "stmxcsr", "subps", "subss", "ucomiss", "unpckhps", "unpcklps",
-            "xorps", "pavgb", "pavgw", "pextrw", "pinsrw", "pmaxsw", "pmaxub",
-            "pminsw", "pminub", "pmovmskb", "psadbw", "pshufw"
-        ]));
-
-        // SSE2
-        table.insert("SSE2", HashSet::from([
-            "addpd", "addsd", "andnpd", "andpd", "cmppd", "comisd", "cvtdq2pd",
-            "cvtdq2ps", "cvtpd2dq", "cvtpd2pi", "cvtpd2ps", "cvtpi2pd",
-            "cvtps2dq", "cvtps2pd", "cvtsd2si", "cvtsd2ss", "cvtsi2sd",
-            "cvtss2sd", "cvttpd2dq", "cvttpd2pi", "cvttps2dq", "cvttsd2si",
-            "divpd", "divsd", "maxpd", "maxsd", "minpd", "minsd", "movapd",
-            "movhpd", "movlpd", "movmskpd", "movupd", "mulpd", "mulsd", "orpd",
-            "shufpd", "sqrtpd", "sqrtsd", "subpd", "subsd", "ucomisd",
-            "unpckhpd", "unpcklpd", "xorpd", "movdq2q", "movdqa", "movdqu",
-            "movq2dq", "paddq", "pmuludq", "pshufhw", "pshuflw", "pshufd",
-            "pslldq", "psrldq", "punpckhqdq", "punpcklqdq"
-        ]));
-
-        // SSE3
-        table.insert("SSE3", HashSet::from([
-            "addsubpd", "addsubps", "haddpd", "haddps", "hsubpd", "hsubps",
-            "movddup", "movshdup", "movsldup", "lddqu", "fisttp"
-        ]));
-
-        // SSSE3
-        table.insert("SSSE3", HashSet::from([
-            "psignw", "psignd", "psignb", "pshufb", "pmulhrsw", "pmaddubsw",
-            "phsubw", "phsubsw", "phsubd", "phaddw", "phaddsw", "phaddd",
-            "palignr", "pabsw", "pabsd", "pabsb"
-        ]));
-
-        // SSE4
-        table.insert("SSE4", HashSet::from([
-            "mpsadbw", "phminposuw", "pmulld", "pmuldq", "dpps", "dppd",
-            "blendps", "blendpd", "blendvps", "blendvpd", "pblendvb", "pblendw",
-            "pblenddw", "pminsb", "pmaxsb", "pminuw", "pmaxuw", "pminud",
-            "pmaxud", "pminsd", "pmaxsd", "roundps", "roundss", "roundpd",
-            "roundsd", "insertps", "pinsrb", "pinsrd", "pinsrq", "extractps",
-            "pextrb", "pextrd", "pextrw", "pextrq", "pmovsxbw", "pmovzxbw",
-            "pmovsxbd", "pmovzxbd", "pmovsxbq", "pmovzxbq", "pmovsxwd",
-            "pmovzxwd", "pmovsxwq", "pmovzxwq", "pmovsxdq", "pmovzxdq",
-            "ptest", "pcmpeqq", "pcmpgtq", "packusdw", "pcmpestri", "pcmpestrm",
-            "pcmpistri", "pcmpistrm", "crc32", "popcnt", "movntdqa", "extrq",
-            "insertq", "movntsd", "movntss", "lzcnt"
-        ]));
-
-        // AVX
-        table.insert("AVX", HashSet::from([
-            "vaddps", "vaddpd", "vaddss", "vaddsd", "vsubps", "vsubpd", "vsubss",
-            "vsubsd", "vmulps", "vmulpd", "vmulss", "vmulsd", "vdivps", "vdivpd",
-            "vdivss", "vdivsd", "vmaxps", "vmaxpd", "vmaxss", "vmaxsd", "vminps",
-            "vminpd", "vminss", "vminsd", "vxorps", "vxorpd", "vandps", "vandpd",
-            "vmovaps", "vmovups", "vmovapd", "vmovupd", "vmovdqa", "vmovdqu",
-            "vmovntps", "vmovntpd", "vbroadcastss", "vbroadcastsd", "vinsertf128",
-            "vextractf128", "vblendps", "vblendpd", "vblendvps", "vblendvpd",
-            "vpermilps", "vpermilpd", "vperm2f128", "vshufps", "vshufpd",
-            "vzeroupper", "vpaddd", "vpsubd", "vpmulld", "vpmuludq", "vpackssdw",
-            "vpackusdw", "vpcmpeqd", "vpcmpgtd", "vpminud", "vpmaxud", "vpminsd",
-            "vpmaxsd", "vgatherdps", "vgatherdpd", "vpgatherdd", "vpgatherdq",
-            "vpmaskmovd", "vpmaskmovq", "vmaskmovps", "vmaskmovpd", "vfmadd213pd",
-            "vfmadd231pd", "vfmadd132pd", "vfmsub213pd", "vfmsub231pd", "vfmsub132pd",
-            "vfnmadd213pd", "vfnmadd231pd", "vfnmadd132pd"
-        ]));
-
-        // AVX-512
-        table.insert("AVX-512", HashSet::from([
-            "kaddd", "kandd", "korw", "kxorq", "vcompresspd", "vexpandps",
-            "vpermb", "vpmovm2d", "vpconflictd", "vpternlogd", "vpshldv",
-            "vpopcntd", "vscalefpd", "vrndscaleps"
-        ]));
-
-        table
-    };
+/// How many functions to list where `--show-functions` sets the number but
+/// wasn't given.
+const DEFAULT_TOP_FUNCTIONS: usize = 10;
+
+/// Exit status when a `--fail-if-*` policy check fails.
+const EXIT_POLICY_VIOLATION: u8 = 2;
+
+/// Exit status when `--target-cpu` / `--target-features` finds instructions
+/// the target lacks, and no policy check failed.
+const EXIT_INCOMPATIBLE: u8 = 3;
+
+impl Args {
+    /// Mnemonics per ISA to keep in `isa_details`.
+    fn top_insts(&self) -> usize {
+        if self.all_insts {
+            usize::MAX
+        } else {
+            self.top
+        }
+    }
+
+    /// `--demangle`, `--no-demangle` and `--strip-hash` together.
+    fn demangle(&self) -> classify::Demangle {
+        classify::Demangle {
+            style: (!self.no_demangle).then_some(self.demangle),
+            strip_hash: self.strip_hash,
+        }
+    }
+
+    /// `--sections`, or `.text*` for `--text-only`.
+    fn section_filter(&self) -> Vec<String> {
+        if self.text_only {
+            vec![".text*".to_string()]
+        } else {
+            self.sections.clone()
+        }
+    }
+
+    fn format(&self) -> OutputFormat {
+        self.format
+            .or_else(|| {
+                self.output
+                    .as_deref()
+                    .and_then(OutputFormat::from_extension)
+            })
+            .unwrap_or(OutputFormat::Json)
+    }
+}
+
+/// `--top`: a count, or `all` for no limit.
+fn parse_top(text: &str) -> Result<usize, String> {
+    if text.eq_ignore_ascii_case("all") {
+        return Ok(usize::MAX);
+    }
+    text.parse()
+        .map_err(|_| format!("'{text}' is neither a number nor `all`"))
+}
 
-    static ref OBJLINE_RE: Regex = Regex::new(r"^\s*[0-9a-f]+:\s+\w").unwrap();
-    static ref MNE_RE: Regex = Regex::new(r"\s([a-z][a-z0-9]+\b)").unwrap();
+/// A hex address, with or without `0x`. clap names the flag in the error.
+fn parse_address(text: &str) -> Result<u64, String> {
+    let digits = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .unwrap_or(text);
+    u64::from_str_radix(digits, 16).map_err(|_| format!("'{text}' is not a hex address"))
 }
 
-fn disassemble(path: &PathBuf) -> Result<Vec<String>> {
+fn disassemble(path: &Path) -> Result<Vec<String>> {
     let output = Command::new("objdump")
-        .args(["-d", "--no-show-raw-insn"])
+        // Raw bytes give us the VEX/EVEX prefix; the wide field keeps every
+        // instruction's bytes on one line
+        .args(["-d", "--insn-width=15"])
         .arg(path)
         .output()
         .context("Failed to execute objdump")?;
@@ -157,102 +535,991 @@ fn disassemble(path: &PathBuf) -> Result<Vec<String>> {
     Ok(stdout.lines().map(|s| s.to_string()).collect())
 }
 
-fn classify(
-    lines: &[String],
-) -> (
-    IndexMap<String, usize>,
-    HashMap<String, HashMap<String, usize>>,
-) {
-    let mut isa_counts = IndexMap::new();
-    let mut inst_detail: HashMap<String, HashMap<String, usize>> = HashMap::new();
-
-    for line in lines {
-        if !OBJLINE_RE.is_match(line) {
-            continue;
-        }
+fn disassembler_version() -> Option<String> {
+    let output = Command::new("objdump").arg("--version").output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout.lines().next().map(|line| line.trim().to_string())
+}
 
-        if let Some(captures) = MNE_RE.captures(line) {
-            let mnemonic = captures.get(1).unwrap().as_str().to_lowercase();
+fn build_meta(overrides: &isa_table::Overrides) -> Meta {
+    Meta {
+        simdscan_version: env!("CARGO_PKG_VERSION").to_string(),
+        timestamp: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+        disassembler: Disassembler {
+            name: "objdump".to_string(),
+            version: disassembler_version(),
+        },
+        args: std::env::args().collect(),
+        reclassified: overrides.reclassify.clone(),
+        ignored_mnemonics: overrides.ignore.clone(),
+    }
+}
 
-            // Check each ISA table
-            for (isa, mset) in ISA_TABLE.iter() {
-                if mset.contains(mnemonic.as_str()) {
-                    *isa_counts.entry(isa.to_string()).or_insert(0) += 1;
+/// Our own peak RSS, from `VmHWM` in /proc/self/status. Linux only.
+fn peak_rss_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
 
-                    let isa_detail = inst_detail
-                        .entry(isa.to_string())
-                        .or_insert_with(HashMap::new);
-                    *isa_detail.entry(mnemonic).or_insert(0) += 1;
+fn millis(elapsed: Duration) -> f64 {
+    (elapsed.as_secs_f64() * 1_000_000.0).round() / 1000.0
+}
 
-                    // Stop at first match
-                    break;
-                }
-            }
-        }
+/// Append `mnemonic<TAB>count<TAB>binary` lines for each unclassified mnemonic,
+/// creating the file if needed.
+fn append_unknown(path: &Path, report: &Report) -> Result<()> {
+    if report.unclassified.is_empty() {
+        return Ok(());
     }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    let mut lines = String::new();
+    for (mnemonic, count) in &report.unclassified {
+        lines.push_str(&format!("{mnemonic}\t{count}\t{}\n", report.binary));
+    }
+    // One write per report keeps lines from concurrent runs from interleaving
+    file.write_all(lines.as_bytes())?;
+    Ok(())
+}
 
-    // Sort isa_counts by key
-    isa_counts.sort_keys();
+/// Policy ISA and category names resolved to their canonical spelling.
+struct Policy {
+    fail_if_found: Vec<String>,
+    fail_if_missing: Vec<String>,
+    /// `isa::OTHER_TABLE` groups to classify: those from `--extensions`, plus
+    /// any a policy flag names, so `--fail-if-found tsx` works on its own
+    extensions: Vec<&'static str>,
+    /// Lowercased `--ignore-mnemonic` globs, the table file's first
+    ignore: Vec<String>,
+    /// Compiled `--function` patterns
+    functions: Vec<regex::Regex>,
+    /// Compiled `--exclude-function` patterns
+    exclude_functions: Vec<regex::Regex>,
+    /// Runtime routine patterns, none with `--no-split-runtime`
+    runtime_routines: Option<regex::RegexSet>,
+    /// `--only-isa` / `--exclude-isa`, resolved to report keys
+    isa_filter: report::IsaFilter,
+    /// `--functions-requiring`, resolved to report keys
+    functions_requiring: Vec<String>,
+    /// `--target-cpu` / `--target-features`
+    target: Option<compat::Target>,
+}
 
-    (isa_counts, inst_detail)
+impl Policy {
+    fn from_args(
+        args: &Args,
+        table: &isa::IsaTable,
+        overrides: &isa_table::Overrides,
+        runtime_routines: regex::RegexSet,
+    ) -> Result<Self> {
+        let keys = isa::report_keys(table, args.isa_granularity);
+        let isa_filter = report::IsaFilter {
+            only: policy::resolve_isas(&args.only_isa, keys.iter().copied())
+                .context("Invalid --only-isa")?,
+            exclude: policy::resolve_isas(&args.exclude_isa, keys.iter().copied())
+                .context("Invalid --exclude-isa")?,
+            recomputed_totals: args.recompute_totals,
+        };
+        let functions_requiring =
+            policy::resolve_isas(&args.functions_requiring, keys.iter().copied())
+                .context("Invalid --functions-requiring")?;
+        let known = keys.into_iter().chain(isa::policy_categories());
+        let fail_if_found = policy::resolve_isas(&args.fail_if_found, known.clone())
+            .context("Invalid --fail-if-found")?;
+        let fail_if_missing = policy::resolve_isas(&args.fail_if_missing, known)
+            .context("Invalid --fail-if-missing")?;
+        let requested = if args
+            .extensions
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case("all"))
+        {
+            isa::other_keys().map(str::to_string).collect()
+        } else {
+            policy::resolve_isas(&args.extensions, isa::other_keys())
+                .context("Invalid --extensions")?
+        };
+        let extensions = isa::other_keys()
+            .filter(|key| {
+                requested
+                    .iter()
+                    .chain(&fail_if_found)
+                    .chain(&fail_if_missing)
+                    .any(|name| name == key)
+            })
+            .collect();
+        Ok(Policy {
+            fail_if_found,
+            fail_if_missing,
+            extensions,
+            ignore: overrides.ignore.clone(),
+            functions: args
+                .function
+                .iter()
+                .map(|pattern| regex::Regex::new(pattern))
+                .collect::<Result<_, _>>()
+                .context("Invalid --function")?,
+            exclude_functions: args
+                .exclude_function
+                .iter()
+                .map(|pattern| regex::Regex::new(pattern))
+                .collect::<Result<_, _>>()
+                .context("Invalid --exclude-function")?,
+            runtime_routines: (!args.no_split_runtime).then_some(runtime_routines),
+            isa_filter,
+            functions_requiring,
+            target: if args.check_cpu {
+                Some(compat::Target::host()?)
+            } else if let Some(cpu) = &args.target_cpu {
+                Some(compat::Target::cpu(cpu)?)
+            } else if !args.target_features.is_empty() {
+                Some(compat::Target::features(&args.target_features)?)
+            } else {
+                None
+            },
+        })
+    }
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+fn scan(
+    binary: &Path,
+    args: &Args,
+    index: &isa::IsaIndex,
+    policy: &Policy,
+    meta: Option<&Meta>,
+) -> Result<Report> {
+    if !binary.exists() {
+        anyhow::bail!("Binary file '{}' not found", binary.display());
+    }
+
+    let bytes =
+        std::fs::read(binary).with_context(|| format!("Failed to read '{}'", binary.display()))?;
+    let binary_info = BinaryInfo::parse(&bytes);
+    drop(bytes);
+    if binary_info.is_x86 == Some(false) {
+        anyhow::bail!(
+            "'{}' is a {} binary for {}, not x86/x86-64",
+            binary.display(),
+            binary_info.format,
+            binary_info
+                .arch
+                .as_deref()
+                .unwrap_or("an unknown architecture")
+        );
+    }
+
+    let profile = if let Some(path) = &args.profile {
+        Some(profile::read_csv(path, args.load_address)?)
+    } else if let Some(path) = &args.perf_data {
+        let relocatable = matches!(
+            binary_info.kind.as_deref(),
+            Some("pie-executable" | "shared-library")
+        );
+        Some(profile::read_perf(
+            path,
+            binary,
+            relocatable,
+            args.load_address,
+        )?)
+    } else {
+        None
+    };
+
+    let started = Instant::now();
+    let lines = disassemble(binary).context("Failed to disassemble binary")?;
+    let disassembled = Instant::now();
 
-    if !args.binary.exists() {
-        anyhow::bail!("Binary file '{}' not found", args.binary.display());
+    let graph = (args.entry.is_some() || args.dispatch_analysis)
+        .then(|| callgraph::CallGraph::build(&lines));
+    let reachability = match (&args.entry, &graph) {
+        (Some(entry), Some(graph)) => {
+            let roots = graph.roots(entry, args.demangle());
+            if roots.is_empty() {
+                anyhow::bail!(
+                    "--entry '{entry}' matched no symbol in '{}'",
+                    binary.display()
+                );
+            }
+            Some(graph.reach(entry, &roots, &binary_info.ifuncs, args.demangle()))
+        }
+        _ => None,
+    };
+    let mut classification = classify::classify(
+        &lines,
+        &classify::Options {
+            index,
+            sections: &args.section_filter(),
+            exclude_sections: &args.exclude_sections,
+            scalar_ext: args.include_scalar_ext,
+            legacy_sse4: args.legacy_sse4_grouping,
+            x87: args.include_x87,
+            extensions: &policy.extensions,
+            transitions: args.check_transitions,
+            avx512_heaviness: args.avx512_heaviness,
+            ignore: &policy.ignore,
+            functions: &policy.functions,
+            reachable: reachability.as_ref().map(|(reachable, _)| reachable),
+            range: (args.start.is_some() || args.end.is_some()).then(|| classify::AddressRange {
+                start: args.start.unwrap_or(0),
+                end: args.end,
+            }),
+            default_excludes: !args.no_default_excludes,
+            exclude_functions: &policy.exclude_functions,
+            runtime_routines: policy.runtime_routines.as_ref(),
+            by_source: args.by_source,
+            demangle: args.demangle(),
+            address_samples: args.show_addresses.unwrap_or(0),
+            symbol_offsets: args.per_function.is_some(),
+            ifuncs: &binary_info.ifuncs,
+            weights: profile.as_ref().map(|profile| &profile.samples),
+            loops: args.loop_heuristic,
+            target: policy.target.as_ref(),
+            target_samples: args.target_addresses,
+        },
+    );
+    let classify_time = disassembled.elapsed();
+    if !args.function.is_empty() && classification.matched_functions.is_empty() {
+        let message = format!("--function matched no symbols in '{}'", binary.display());
+        if args.strict {
+            anyhow::bail!("{message} (--strict)");
+        }
+        eprintln!("warning: {message}");
+    }
+    if args.strict && classification.diagnostics.unmatched_mnemonics > 0 {
+        anyhow::bail!(
+            "{} instruction lines in '{}' had no recognizable mnemonic (--strict)",
+            classification.diagnostics.unmatched_mnemonics,
+            binary.display()
+        );
     }
 
-    let lines = disassemble(&args.binary).context("Failed to disassemble binary")?;
+    let stats = args.stats.then(|| {
+        let secs = classify_time.as_secs_f64();
+        report::Stats {
+            disassemble_ms: millis(disassembled - started),
+            classify_ms: millis(classify_time),
+            lines: lines.len(),
+            lines_per_sec: if secs > 0.0 {
+                (lines.len() as f64 / secs).round()
+            } else {
+                0.0
+            },
+            peak_rss_bytes: peak_rss_bytes(),
+        }
+    });
 
-    let (isa_counts, inst_detail) = classify(&lines);
+    let (mut required_level, unleveled_mnemonics) = isa::required_level(
+        classification
+            .inst_detail
+            .iter()
+            .chain(&classification.scalar_detail)
+            .flat_map(|(isa, counts)| counts.keys().map(move |m| (isa.as_str(), m.as_str()))),
+    );
+    // Mnemonics with some occurrence outside guarded dispatch variants
+    let guarded = &classification.guarded_detail;
+    let (baseline_level, _) = isa::required_level(
+        classification
+            .inst_detail
+            .iter()
+            .chain(&classification.scalar_detail)
+            .flat_map(|(isa, counts)| {
+                counts
+                    .iter()
+                    .filter(move |&(m, count)| {
+                        guarded.get(isa).and_then(|g| g.get(m)) != Some(count)
+                    })
+                    .map(move |(m, _)| (isa.as_str(), m.as_str()))
+            }),
+    );
+    let gnu_property = binary_info.gnu_property.as_ref().map(|declared| {
+        let widths = &classification.register_widths;
+        let mask_usage = &classification.mask_usage;
+        let observed = gnu_property::Observed {
+            level: required_level,
+            x87: classification.x87_insts > 0,
+            mmx: classification.mmx_insts > 0,
+            xmm: widths.xmm > 0,
+            ymm: widths.ymm > 0,
+            zmm: widths.zmm > 0,
+            mask: mask_usage.masked > 0 || !mask_usage.mask_ops.is_empty(),
+        };
+        gnu_property::compare(declared, &observed)
+    });
+    if args.assume_dispatch {
+        required_level = baseline_level;
+    }
+    classification.regroup(args.isa_granularity);
+    let Classification {
+        isa_counts,
+        mut isa_bytes,
+        inst_detail,
+        inst_addresses,
+        total_insts,
+        register_widths,
+        functions,
+        parent_functions,
+        mangled_names,
+        matched_functions,
+        range_end,
+        range_symbols,
+        exclusions,
+        runtime,
+        simd_addresses,
+        sections,
+        scanned_sections,
+        diagnostics,
+        unclassified,
+        memory_moves,
+        scalar_fp,
+        packed_fp,
+        packed_int,
+        cpu_features,
+        dispatch,
+        guarded_detail: _,
+        baseline_features,
+        scalar_detail,
+        other_detail,
+        mask_usage,
+        evex_operands,
+        categories,
+        gather_scatter,
+        non_temporal,
+        x87,
+        x87_insts,
+        mmx_insts: _,
+        prefetch,
+        mxcsr_access,
+        state_resets,
+        transition_warnings,
+        weighted,
+        mut loops,
+        faults,
+        avx512_heavy,
+        avx512_light,
+        mut vector_widths,
+        avx512_widths,
+        avx512_zmm,
+    } = classification;
 
     let total_simd_insts = isa_counts.values().sum();
+    let x87_fp = args.include_x87.then(|| x87.mnemonics.values().sum());
     let has_simd = total_simd_insts > 0;
 
-    let isa_details = if args.show_insts {
-        let mut details = IndexMap::new();
-        for (isa, detail_map) in inst_detail {
-            let mut occurrences = IndexMap::new();
+    let mmx_emms = inst_detail
+        .get("MMX")
+        .map(|mnemonics| mnemonics.get("emms").copied().unwrap_or(0));
+    let mut details = report::build_details(
+        inst_detail,
+        inst_addresses,
+        args.top_insts(),
+        args.show_addresses.unwrap_or(0),
+    );
+    let extensions_enabled = !policy.extensions.is_empty();
+    let mut policy_categories = categories.clone();
+    policy_categories.extend(other_detail.clone());
+    policy_categories.insert(
+        isa::NON_TEMPORAL.to_string(),
+        non_temporal.mnemonics.clone(),
+    );
+    policy_categories.insert(isa::AVX512_512BIT.to_string(), avx512_zmm);
+    let isa_filter = &policy.isa_filter;
+    let functions_requiring = &policy.functions_requiring;
+    let target = policy.target.as_ref();
+    let policy = policy::evaluate(
+        &isa_counts,
+        &details,
+        &policy_categories,
+        &policy.fail_if_found,
+        &policy.fail_if_missing,
+    );
 
-            // Sort by count (descending) and take top 10
-            let mut sorted_pairs: Vec<_> = detail_map.into_iter().collect();
-            sorted_pairs.sort_by(|a, b| b.1.cmp(&a.1));
+    // After the policy checks, which must still see every ISA
+    let mut isa_summary = isa_counts.clone();
+    isa_filter.retain(&mut isa_summary);
+    let total_simd_insts: usize = if isa_filter.recomputed_totals {
+        isa_filter.retain(&mut isa_bytes);
+        isa_summary.values().sum()
+    } else {
+        total_simd_insts
+    };
+    let simd_bytes: usize = isa_bytes.values().sum();
+    let guards = graph.filter(|_| args.dispatch_analysis).map(|graph| {
+        let (mut guarded, mut unconditional, section) = guards::analyze(
+            &graph,
+            &binary_info.ifuncs,
+            args.demangle(),
+            &parent_functions,
+        );
+        isa_filter.retain(&mut guarded);
+        isa_filter.retain(&mut unconditional);
+        (guarded, unconditional, section)
+    });
+    let (guarded_isas, unconditional_isas, dispatch_guards) = match guards {
+        Some((guarded, unconditional, section)) => {
+            (Some(guarded), Some(unconditional), Some(section))
+        }
+        None => (None, None, None),
+    };
+    let code_bytes = binary_info
+        .code_sections
+        .iter()
+        .filter(|(name, _)| scanned_sections.contains(name))
+        .map(|(_, size)| size)
+        .sum::<u64>();
+    let code_bytes = (code_bytes > 0).then_some(code_bytes);
+    isa_filter.retain(&mut vector_widths);
+    isa_filter.retain(&mut loops.by_isa);
+    let suppressed = report::suppress(
+        &mut isa_summary,
+        total_insts,
+        args.min_count.unwrap_or(0),
+        args.min_percent.unwrap_or(0.0),
+    );
+    args.sort_by.sort(&mut isa_summary);
+    details.retain(|isa, _| isa_summary.contains_key(isa));
+    details.sort_by_cached_key(|isa, _| isa_summary.get_index_of(isa));
+    let isa_breakdown = report::build_breakdown(&isa_summary, total_simd_insts, total_insts);
 
-            for (mnemonic, count) in sorted_pairs.into_iter().take(10) {
-                occurrences.insert(mnemonic, count);
-            }
+    let isa_details =
+        if args.show_insts || args.show_addresses.is_some() || args.format().needs_details() {
+            Some(details)
+        } else {
+            None
+        };
 
-            details.insert(
-                isa,
-                IsaDetail {
-                    unique_mnemonics: occurrences.len(),
-                    occurrences,
-                },
-            );
-        }
-        Some(details)
-    } else {
-        None
+    let listing = report::FunctionListing {
+        sort: args.sort_functions,
+        filter: isa_filter,
+        min_insts: args.min_function_insts,
+        requiring: functions_requiring,
+        samples: profile.is_some(),
+        loops: args.loop_heuristic,
     };
+    let function_requirements = args
+        .per_function
+        .map(|_| report::build_requirements(&parent_functions));
+
+    let source_files = args
+        .by_source
+        .then(|| source::attribute(binary, args.debug_file.as_deref(), &simd_addresses))
+        .transpose()?;
 
+    let dependencies = deps::list(&binary_info.libraries);
     let report = Report {
-        binary: args.binary.to_string_lossy().to_string(),
+        schema_version: report::SCHEMA_VERSION,
+        meta: meta.cloned(),
+        binary: binary.to_string_lossy().to_string(),
+        binary_info,
         has_simd,
-        isa_summary: isa_counts,
+        has_packed_simd: packed_fp + packed_int > 0,
+        scalar_extensions: (args.include_scalar_ext || !scalar_detail.is_empty()).then(|| {
+            let mut counts: IndexMap<String, usize> = scalar_detail
+                .iter()
+                .map(|(ext, mnemonics)| (ext.clone(), mnemonics.values().sum()))
+                .collect();
+            counts.sort_keys();
+            counts
+        }),
+        other_extensions: extensions_enabled.then(|| {
+            let mut counts: IndexMap<String, usize> = other_detail
+                .iter()
+                .map(|(ext, mnemonics)| (ext.clone(), mnemonics.values().sum()))
+                .collect();
+            counts.sort_keys();
+            counts
+        }),
+        legacy_isas: isa_counts
+            .keys()
+            .filter(|isa| isa::is_legacy(isa))
+            .cloned()
+            .collect(),
+        mmx_emms,
+        avx512_any: Some(
+            isa_counts
+                .iter()
+                .filter(|(isa, _)| isa::is_avx512(isa) || *isa == "AVX-512")
+                .map(|(_, count)| count)
+                .sum(),
+        )
+        .filter(|total| *total > 0),
+        avx512_heaviness: args.avx512_heaviness.then(|| {
+            report::build_heaviness(
+                avx512_heavy.mnemonics,
+                avx512_heavy.functions,
+                avx512_light,
+                args.show_functions.unwrap_or(DEFAULT_TOP_FUNCTIONS),
+            )
+        }),
+        isa_summary,
         total_simd_insts,
+        total_insts,
+        simd_percent: report::percent(total_simd_insts, total_insts),
+        simd_bytes,
+        code_bytes,
+        simd_bytes_percent: code_bytes
+            .map(|code| report::percent(simd_bytes, usize::try_from(code).unwrap_or(usize::MAX))),
+        simd_per_kb: code_bytes.map(|code| report::per_kb(total_simd_insts, code)),
+        isa_breakdown,
+        suppressed,
+        isa_filter: (!isa_filter.is_empty()).then(|| isa_filter.clone()),
+        loops: args.loop_heuristic.then_some(loops),
+        weighted_counts: profile.as_ref().map(|profile| {
+            let mut weighted = report::build_weighted(profile, weighted);
+            isa_filter.retain(&mut weighted.isa_summary);
+            args.sort_by.sort(&mut weighted.isa_summary);
+            weighted.isa_breakdown = report::build_breakdown(
+                &weighted.isa_summary,
+                weighted.simd_samples,
+                weighted.matched_samples,
+            );
+            weighted
+        }),
+        dependencies,
+        feature_clones: clones::find(&functions, &mangled_names, args.isa_granularity),
+        dispatch: (!dispatch.is_empty()).then(|| {
+            report::build_dispatch(dispatch, &isa_counts, baseline_level, args.assume_dispatch)
+        }),
+        guarded_isas,
+        unconditional_isas,
+        dispatch_guards,
+        required_level,
+        min_cpu: report::MinCpu::from_features(if args.assume_dispatch {
+            baseline_features
+        } else {
+            cpu_features
+        }),
+        gnu_property,
+        compatibility: target.map(|target| {
+            let assume_dispatch = args.assume_dispatch || args.check_cpu;
+            compat::build(target, faults, args.target_addresses, assume_dispatch)
+        }),
+        unleveled_mnemonics,
+        max_vector_width: register_widths.max_bits(),
+        max_vector_width_used: vector_widths
+            .values()
+            .fold(report::RegisterWidths::default(), |mut all, widths| {
+                all.merge(widths);
+                all
+            })
+            .max_bits(),
+        avx512_max_width: avx512_widths.max_bits(),
+        vector_widths,
+        register_widths,
+        mask_usage: (!mask_usage.is_empty()).then_some(mask_usage),
+        evex_operands: (!evex_operands.is_empty()).then_some(evex_operands),
+        categories: report::by_count(
+            categories
+                .into_iter()
+                .map(|(category, mnemonics)| (category, mnemonics.values().sum()))
+                .collect(),
+        ),
+        uses_gather_scatter: !gather_scatter.mnemonics.is_empty(),
+        gather_scatter: report::build_attributed(
+            gather_scatter.mnemonics,
+            gather_scatter.functions,
+            args.show_functions.is_some(),
+        ),
+        uses_non_temporal: !non_temporal.mnemonics.is_empty(),
+        non_temporal: report::build_attributed(
+            non_temporal.mnemonics,
+            non_temporal.functions,
+            args.show_functions.is_some(),
+        ),
+        prefetch: report::build_attributed(
+            prefetch.mnemonics,
+            prefetch.functions,
+            args.show_functions.is_some(),
+        ),
+        modifies_mxcsr: mxcsr_access
+            .mnemonics
+            .keys()
+            .any(|mnemonic| isa::writes_mxcsr(mnemonic)),
+        mxcsr_access: report::build_attributed(
+            mxcsr_access.mnemonics,
+            mxcsr_access.functions,
+            args.show_functions.is_some(),
+        ),
+        state_resets: report::build_attributed(
+            state_resets.mnemonics,
+            state_resets.functions,
+            args.show_functions.is_some(),
+        ),
+        transition_warnings: args.check_transitions.then_some(transition_warnings),
+        x87: report::build_x87(
+            x87.mnemonics,
+            x87.functions,
+            args.show_functions.is_some(),
+            index,
+        ),
+        sections,
+        excluded: exclusions,
+        runtime_library: report::build_runtime(runtime, &mangled_names),
+        source_files,
+        scanned_sections: (!args.section_filter().is_empty() || !args.exclude_sections.is_empty())
+            .then_some(scanned_sections),
+        diagnostics,
+        unclassified: report::by_count(unclassified),
+        memory_ops: report::build_memory_ops(memory_moves, args.show_insts),
+        scalar_fp,
+        packed_fp,
+        packed_int,
+        x87_fp,
+        vectorization_ratio: report::ratio(
+            packed_fp + packed_int,
+            scalar_fp + packed_fp + packed_int,
+        ),
+        float_profile: report::FloatProfile::from_counts(
+            x87_insts,
+            scalar_fp,
+            packed_fp + packed_int,
+        ),
         isa_details,
+        top_functions: args
+            .show_functions
+            .map(|count| report::build_top_functions(functions, count, &mangled_names, listing)),
+        functions: args.per_function.map(|which| {
+            report::build_functions(
+                parent_functions,
+                which == PerFunction::All,
+                &mangled_names,
+                listing,
+            )
+        }),
+        function_requirements,
+        matched_functions: (!args.function.is_empty()).then_some(matched_functions),
+        address_range: (args.start.is_some() || args.end.is_some()).then(|| report::AddressRange {
+            start: format!("{:x}", args.start.unwrap_or(0)),
+            end: range_end.map(|end| format!("{end:x}")),
+            symbols: range_symbols,
+        }),
+        reachability: reachability.map(|(_, section)| section),
+        policy,
+        stats,
     };
 
-    match args.format {
-        OutputFormat::Json => {
-            println!("{}", serde_json::to_string_pretty(&report)?);
+    if let Some(path) = &args.collect_unknown {
+        append_unknown(path, &report)
+            .with_context(|| format!("Failed to append to '{}'", path.display()))?;
+    }
+    Ok(report)
+}
+
+/// The `--check-cpu` verdict on `report`, for stderr.
+fn cpu_verdict(report: &Report) -> String {
+    let Some(compatibility) = &report.compatibility else {
+        return format!("{}: not checked", report.binary);
+    };
+    if !compatibility.compatible {
+        format!(
+            "{}: cannot run on this CPU: {} instructions in {} functions need {}",
+            report.binary,
+            compatibility.faulting_insts,
+            compatibility.functions.len(),
+            compatibility.missing_features.join(", ")
+        )
+    } else if compatibility.faulting_insts > 0 {
+        format!(
+            "{}: can run on this CPU; the {} instructions needing {} are all in guarded dispatch variants",
+            report.binary,
+            compatibility.faulting_insts,
+            compatibility.missing_features.join(", ")
+        )
+    } else {
+        format!("{}: can run on this CPU", report.binary)
+    }
+}
+
+/// What the exit status reflects over every binary of a run: a failed scan
+/// wins over a policy violation, which wins over an incompatibility.
+#[derive(Default)]
+struct ExitStatus {
+    failed: bool,
+    violated: bool,
+    incompatible: bool,
+}
+
+impl ExitStatus {
+    fn record(&mut self, report: &Report) {
+        self.violated |= report.violates_policy();
+        self.incompatible |= report.incompatible();
+    }
+
+    fn code(&self) -> ExitCode {
+        if self.failed {
+            ExitCode::FAILURE
+        } else if self.violated {
+            ExitCode::from(EXIT_POLICY_VIOLATION)
+        } else if self.incompatible {
+            ExitCode::from(EXIT_INCOMPATIBLE)
+        } else {
+            ExitCode::SUCCESS
         }
-        OutputFormat::Yaml => {
-            println!("{}", serde_yaml::to_string(&report)?);
+    }
+}
+
+/// `scan`, with an error or a panic turned into the message to report for
+/// `binary`, so one bad input doesn't end a run over several.
+fn scan_isolated(
+    binary: &Path,
+    args: &Args,
+    index: &isa::IsaIndex,
+    policy: &Policy,
+    meta: Option<&Meta>,
+) -> std::result::Result<Report, String> {
+    match panic::catch_unwind(AssertUnwindSafe(|| scan(binary, args, index, policy, meta))) {
+        Ok(Ok(report)) => Ok(report),
+        Ok(Err(err)) => Err(format!("{err:#}")),
+        Err(_) => Err("scan panicked".to_string()),
+    }
+}
+
+/// Emit one compact JSON line per binary as soon as it is scanned. A failing or
+/// panicking scan produces an `{"binary", "error"}` line instead of ending the stream.
+/// What `--recursive` passed over follows as a last `{"skipped"}` line.
+fn run_ndjson(
+    binaries: &[PathBuf],
+    skipped: Option<report::Skipped>,
+    args: &Args,
+    index: &isa::IsaIndex,
+    policy: &Policy,
+    meta: Option<&Meta>,
+    out: &mut Sink,
+) -> Result<ExitCode> {
+    let mut status = ExitStatus::default();
+    for binary in binaries {
+        let line = match scan_isolated(binary, args, index, policy, meta) {
+            Ok(report) => {
+                status.record(&report);
+                if args.check_cpu {
+                    eprintln!("{}", cpu_verdict(&report));
+                }
+                output::ndjson::report_line(&report)?
+            }
+            Err(error) => {
+                status.failed = true;
+                output::ndjson::error_line(&binary.to_string_lossy(), &error)?
+            }
+        };
+        writeln!(out, "{line}")?;
+        out.flush()?;
+    }
+    if let Some(skipped) = skipped {
+        writeln!(out, "{}", output::ndjson::skipped_line(skipped)?)?;
+    }
+    Ok(status.code())
+}
+
+/// Scan several binaries and render them as one `MultiReport`. A failed scan
+/// becomes that binary's entry, and is repeated on stderr for the formats
+/// without room for it. `skipped` is what `--recursive` passed over.
+fn run_many(
+    binaries: &[PathBuf],
+    skipped: Option<report::Skipped>,
+    args: &Args,
+    index: &isa::IsaIndex,
+    policy: &Policy,
+    meta: Option<&Meta>,
+    render_opts: &RenderOptions,
+) -> Result<(Vec<u8>, ExitCode, String)> {
+    let mut status = ExitStatus::default();
+    let mut reports = Vec::new();
+    for binary in binaries {
+        match scan_isolated(binary, args, index, policy, meta) {
+            Ok(report) => {
+                status.record(&report);
+                if args.check_cpu {
+                    eprintln!("{}", cpu_verdict(&report));
+                }
+                reports.push(report::ReportEntry::Report(Box::new(report)));
+            }
+            Err(error) => {
+                status.failed = true;
+                eprintln!("error: {}: {error}", binary.display());
+                reports.push(report::ReportEntry::Error(report::ScanError {
+                    binary: binary.to_string_lossy().to_string(),
+                    error,
+                }));
+            }
         }
     }
+    let mut aggregate = report::build_aggregate(&reports);
+    aggregate.skipped = skipped;
+    let skipped = aggregate.skipped.as_ref().map_or(String::new(), |skipped| {
+        format!(", {} skipped", skipped.total())
+    });
+    let summary = format!(
+        "{} binaries ({} failed{skipped}): {} SIMD instructions across {} extensions",
+        aggregate.binaries,
+        aggregate.failed,
+        aggregate.total_simd_insts,
+        aggregate.isa_summary.len()
+    );
+    let multi = report::MultiReport {
+        schema_version: report::SCHEMA_VERSION,
+        reports,
+        aggregate,
+    };
+    let rendered = output::render_many(args.format(), multi, render_opts)?;
+    Ok((rendered, status.code(), summary))
+}
 
-    Ok(())
+fn main() -> Result<ExitCode> {
+    let args = Args::parse();
+
+    match &args.command {
+        Some(Subcommand::Schema) => {
+            let mut schema = schemars::schema_for!(report::Document);
+            // No document matches more than one of the three
+            if let Some(object) = schema.as_object_mut() {
+                if let Some(variants) = object.remove("anyOf") {
+                    object.insert("oneOf".to_string(), variants);
+                }
+            }
+            println!("{}", serde_json::to_string_pretty(&schema)?);
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Subcommand::Explain { mnemonic, table }) => {
+            print!(
+                "{}",
+                explain::render(&table.load()?, mnemonic, table.format)?
+            );
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Subcommand::ListIsas(table_args)) => {
+            print!("{}", list::isas(&table_args.load()?, table_args.format)?);
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Subcommand::ListMnemonics { isa, table }) => {
+            let listing = list::mnemonics(&table.load()?, isa.as_deref(), table.format)?;
+            print!("{listing}");
+            return Ok(ExitCode::SUCCESS);
+        }
+        None => {}
+    }
+    if let (Some(start), Some(end)) = (args.start, args.end) {
+        if start >= end {
+            anyhow::bail!("--start {start:#x} must be below --end {end:#x}");
+        }
+    }
+    let table_path = args.isa_table.clone().or_else(isa_table::default_path);
+    let mut table = isa_table::load(table_path.as_deref())?;
+    let settings = isa_table::settings(table_path.as_deref())?;
+    let ignore: Vec<String> = settings
+        .ignore_mnemonics
+        .into_iter()
+        .chain(args.ignore_mnemonic.iter().cloned())
+        .collect();
+    let overrides = isa_table::Overrides::parse(&args.reclassify, &ignore)?;
+    if !args.allow_unknown_ignores {
+        overrides.check_ignores(&table)?;
+    }
+    overrides.apply(&mut table);
+    if args.dump_table {
+        print!("{}", isa_table::dump(&table)?);
+        return Ok(ExitCode::SUCCESS);
+    }
+    let format = args.format();
+    let policy = Policy::from_args(&args, &table, &overrides, settings.runtime_routines)?;
+    let index = isa::index(&table);
+    let meta = (!args.no_meta).then(|| build_meta(&overrides));
+    if format.is_binary() && args.output.is_none() && io::stdout().is_terminal() {
+        anyhow::bail!("Refusing to write binary output to a terminal; use --output or a pipe");
+    }
+    if format == OutputFormat::Template && args.template.is_none() {
+        anyhow::bail!("--format template requires --template");
+    }
+    if args.binaries.len() > 1 {
+        let single = [
+            ("--profile", args.profile.is_some()),
+            ("--perf-data", args.perf_data.is_some()),
+            ("--load-address", args.load_address.is_some()),
+            ("--debug-file", args.debug_file.is_some()),
+        ];
+        if let Some((flag, _)) = single.iter().find(|(_, given)| *given) {
+            anyhow::bail!("{flag} applies to one binary; got {}", args.binaries.len());
+        }
+    }
+    let (binaries, skipped) = if args.recursive {
+        let filter = walk::Filter {
+            include: &args.include,
+            exclude: &args.exclude,
+            max_depth: args.max_depth.map(NonZeroUsize::get),
+        };
+        let (binaries, skipped) = walk::expand(&args.binaries, &filter);
+        (binaries, Some(skipped))
+    } else {
+        (args.binaries.clone(), None)
+    };
+    let mut out = Sink::open(args.output.as_deref(), args.mkdirs)?;
+
+    if format == OutputFormat::Ndjson {
+        let code = run_ndjson(
+            &binaries,
+            skipped,
+            &args,
+            &index,
+            &policy,
+            meta.as_ref(),
+            &mut out,
+        )?;
+        out.commit()?;
+        if let Some(path) = &args.output {
+            if !args.quiet {
+                println!("Wrote ndjson report to {}", path.display());
+            }
+        }
+        return Ok(code);
+    }
+
+    let render_opts = RenderOptions {
+        color: args.output.is_none() && io::stdout().is_terminal(),
+        template: args.template.as_deref().map(Template::load).transpose()?,
+    };
+    let (false, [binary]) = (args.recursive, binaries.as_slice()) else {
+        let (rendered, code, summary) = run_many(
+            &binaries,
+            skipped,
+            &args,
+            &index,
+            &policy,
+            meta.as_ref(),
+            &render_opts,
+        )?;
+        out.write_all(&rendered)?;
+        out.commit()?;
+        if let Some(path) = &args.output {
+            if !args.quiet {
+                println!("{summary}, report written to {}", path.display());
+            }
+        }
+        return Ok(code);
+    };
+    let report = scan(binary, &args, &index, &policy, meta.as_ref())?;
+
+    let rendered = output::render(format, &report, &render_opts)?;
+    out.write_all(&rendered)?;
+    out.commit()?;
+
+    if let Some(path) = &args.output {
+        if !args.quiet {
+            println!(
+                "{}: {} SIMD instructions across {} extensions, report written to {}",
+                report.binary,
+                report.total_simd_insts,
+                report.isa_summary.len(),
+                path.display()
+            );
+        }
+    }
+
+    if args.check_cpu {
+        eprintln!("{}", cpu_verdict(&report));
+    }
+
+    let mut status = ExitStatus::default();
+    status.record(&report);
+    Ok(status.code())
 }
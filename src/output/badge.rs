@@ -0,0 +1,92 @@
+//! shields.io endpoint JSON (<https://shields.io/badges/endpoint-badge>).
+
+use crate::isa;
+use crate::report::{Aggregate, Report};
+use indexmap::IndexMap;
+use serde::Serialize;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Badge {
+    schema_version: u8,
+    label: &'static str,
+    message: String,
+    color: &'static str,
+}
+
+/// Extensions every x86-64 CPU has; a binary topping out here needs nothing special.
+const BASELINE: &[&str] = &["MMX", "SSE", "SSE2"];
+
+fn color(highest: Option<&str>) -> &'static str {
+    match highest {
+        None => "lightgrey",
+        Some(isa) if BASELINE.contains(&isa) => "green",
+        Some(isa) if isa == "AVX-512" || isa::is_avx512(isa) || isa::is_amx(isa) => "red",
+        Some("AVX" | "FMA" | "AVX2" | "AVX-VNNI" | "FMA4" | "XOP") => "orange",
+        Some(_) => "yellowgreen",
+    }
+}
+
+pub fn render(report: &Report) -> serde_json::Result<String> {
+    badge(&report.isa_summary, report.required_level)
+}
+
+/// One badge for a multi-binary run: the highest ISA any binary uses.
+pub fn render_aggregate(aggregate: &Aggregate) -> serde_json::Result<String> {
+    badge(&aggregate.isa_summary, aggregate.required_level)
+}
+
+fn badge(isa_summary: &IndexMap<String, usize>, required_level: u8) -> serde_json::Result<String> {
+    let highest = isa::highest(isa_summary.keys().map(String::as_str));
+    let message = match highest {
+        None => "none".to_string(),
+        Some(isa) if BASELINE.contains(&isa) => format!("{isa} only"),
+        Some(isa) => format!("{isa} ({})", isa::level_name(required_level)),
+    };
+
+    serde_json::to_string(&Badge {
+        schema_version: 1,
+        label: "SIMD",
+        message,
+        color: color(highest),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::tests::fixture;
+
+    fn badge_for(isas: &[&str], scalar: &[&str], required_level: u8) -> serde_json::Value {
+        let mut report = fixture();
+        report.isa_summary = isas.iter().map(|isa| (isa.to_string(), 1)).collect();
+        report.scalar_extensions = Some(scalar.iter().map(|ext| (ext.to_string(), 1)).collect());
+        report.required_level = required_level;
+        serde_json::from_str(&render(&report).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn scalar_extensions_only_is_none() {
+        let badge = badge_for(&[], &["POPCNT", "LZCNT"], 2);
+        assert_eq!(badge["message"], "none");
+        assert_eq!(badge["color"], "lightgrey");
+        assert_eq!(badge["schemaVersion"], 1);
+    }
+
+    #[test]
+    fn baseline_is_only() {
+        let badge = badge_for(&["SSE", "SSE2"], &["POPCNT"], 2);
+        assert_eq!(badge["message"], "SSE2 only");
+        assert_eq!(badge["color"], "green");
+    }
+
+    #[test]
+    fn highest_isa_names_the_level() {
+        let badge = badge_for(&["SSE2", "AVX", "AVX2"], &[], 3);
+        assert_eq!(badge["message"], "AVX2 (x86-64-v3)");
+        assert_eq!(badge["color"], "orange");
+        let badge = badge_for(&["AVX2", "AVX512-F"], &[], 4);
+        assert_eq!(badge["message"], "AVX512-F (x86-64-v4)");
+        assert_eq!(badge["color"], "red");
+    }
+}
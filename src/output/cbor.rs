@@ -0,0 +1,27 @@
+use crate::report::Report;
+use anyhow::Result;
+
+/// Encode reports as one definite-length CBOR array, so a single-binary scan
+/// and a batch decode the same way (`Vec<Report>`).
+pub fn render(reports: &[Report]) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    ciborium::into_writer(reports, &mut out)?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::tests::fixture;
+
+    #[test]
+    fn round_trips_to_report() {
+        let reports = vec![fixture(), fixture()];
+        let bytes = render(&reports).unwrap();
+        let decoded: Vec<Report> = ciborium::from_reader(bytes.as_slice()).unwrap();
+        assert_eq!(
+            serde_json::to_value(&decoded).unwrap(),
+            serde_json::to_value(&reports).unwrap()
+        );
+    }
+}
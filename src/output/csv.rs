@@ -0,0 +1,79 @@
+use crate::isa;
+use crate::report::{Report, ScanError};
+
+/// Quote a field if it contains a delimiter, quote, or line break (RFC 4180).
+fn field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub const ISAS_HEADER: &str = "binary,isa,count,unique_mnemonics,required_level,error";
+pub const INSTS_HEADER: &str = "binary,isa,mnemonic,count,required_level,error";
+
+pub fn render_isas(report: &Report) -> String {
+    let binary = field(&report.binary);
+    let level = isa::level_name(report.required_level);
+    let mut out = vec![ISAS_HEADER.to_string()];
+
+    for (isa, count) in &report.isa_summary {
+        let unique = report
+            .isa_details
+            .as_ref()
+            .and_then(|details| details.get(isa))
+            .map_or(0, |detail| detail.unique_mnemonics);
+        out.push(format!(
+            "{},{},{},{},{},",
+            binary,
+            field(isa),
+            count,
+            unique,
+            level
+        ));
+    }
+
+    // Keep one row per binary so joins downstream don't drop SIMD-free files
+    if report.isa_summary.is_empty() {
+        out.push(format!("{},,0,0,{},", binary, level));
+    }
+
+    out.join("\n")
+}
+
+pub fn render_insts(report: &Report) -> String {
+    let binary = field(&report.binary);
+    let level = isa::level_name(report.required_level);
+    let mut out = vec![INSTS_HEADER.to_string()];
+
+    if let Some(details) = &report.isa_details {
+        for isa in report.isa_summary.keys() {
+            let Some(detail) = details.get(isa) else {
+                continue;
+            };
+            for (mnemonic, count) in &detail.occurrences {
+                out.push(format!(
+                    "{},{},{},{},{},",
+                    binary,
+                    field(isa),
+                    field(mnemonic),
+                    count,
+                    level
+                ));
+            }
+        }
+    }
+
+    if out.len() == 1 {
+        out.push(format!("{},,,0,{},", binary, level));
+    }
+
+    out.join("\n")
+}
+
+/// The row of a binary that failed to scan, for either format: only
+/// `binary` and `error` are filled in.
+pub fn render_error(error: &ScanError) -> String {
+    format!("{},,,,,{}", field(&error.binary), field(&error.error))
+}
@@ -0,0 +1,104 @@
+//! GitHub Actions workflow commands (`::error`, `::warning`, `::notice`).
+
+use crate::isa;
+use crate::policy::PolicyRule;
+use crate::report::{Report, ScanError};
+
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+pub fn render(report: &Report) -> String {
+    let file = escape_property(&report.binary);
+    let mut out = Vec::new();
+
+    for check in report.policy.iter().filter(|check| !check.passed) {
+        let (command, message) = match check.rule {
+            PolicyRule::FailIfFound => {
+                let top: Vec<String> = check
+                    .top_mnemonics
+                    .iter()
+                    .map(|(m, n)| format!("{m} ({n})"))
+                    .collect();
+                (
+                    "error",
+                    format!(
+                        "{}: found {} {} instructions (top: {})",
+                        report.binary,
+                        check.count,
+                        check.isa,
+                        top.join(", ")
+                    ),
+                )
+            }
+            PolicyRule::FailIfMissing => (
+                "warning",
+                format!("{}: no {} instructions found", report.binary, check.isa),
+            ),
+        };
+        let title = escape_property(&format!("simdscan {} {}", check.rule.flag(), check.isa));
+        out.push(format!(
+            "::{command} file={file},title={title}::{}",
+            escape_data(&message)
+        ));
+    }
+
+    let violating = |isa: &str| {
+        report
+            .policy
+            .iter()
+            .any(|check| !check.passed && check.isa == isa)
+    };
+    let ok: Vec<String> = report
+        .isa_summary
+        .iter()
+        .filter(|(isa, _)| !violating(isa))
+        .map(|(isa, count)| format!("{isa}: {count}"))
+        .collect();
+    let summary = if report.isa_summary.is_empty() {
+        "no SIMD instructions".to_string()
+    } else if ok.is_empty() {
+        "no other SIMD instructions".to_string()
+    } else {
+        ok.join(", ")
+    };
+    out.push(format!(
+        "::notice file={file},title=simdscan::{}",
+        escape_data(&format!(
+            "{}: {} (requires {})",
+            report.binary,
+            summary,
+            isa::level_name(report.required_level)
+        ))
+    ));
+
+    out.join("\n")
+}
+
+/// The annotation for a binary that failed to scan.
+pub fn render_error(error: &ScanError) -> String {
+    format!(
+        "::error file={},title=simdscan::{}",
+        escape_property(&error.binary),
+        escape_data(&format!("{}: scan failed: {}", error.binary, error.error))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::tests::fixture;
+
+    #[test]
+    fn notice_without_simd() {
+        let mut report = fixture();
+        report.isa_summary.clear();
+        assert!(render(&report).contains("testdata/dispatch: no SIMD instructions ("));
+    }
+}
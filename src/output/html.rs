@@ -0,0 +1,161 @@
+//! Single-file HTML report. Everything is inlined so the page works from `file://`.
+
+use crate::isa;
+use crate::report::{Report, ScanError};
+use std::fmt::Write;
+
+const STYLE: &str = r#"
+body { font-family: system-ui, sans-serif; margin: 2rem; color: #222; }
+section { margin-bottom: 3rem; }
+h2 code { font-size: 0.9em; }
+dl { display: grid; grid-template-columns: max-content auto; gap: 0.25rem 1rem; }
+dt { font-weight: 600; }
+table { border-collapse: collapse; margin-top: 1rem; }
+th, td { padding: 0.25rem 0.75rem; border-bottom: 1px solid #ddd; text-align: left; }
+th { cursor: pointer; user-select: none; }
+td.num { text-align: right; font-variant-numeric: tabular-nums; }
+svg text { font-size: 12px; }
+"#;
+
+const SCRIPT: &str = r#"
+document.querySelectorAll("table.sortable").forEach(function (table) {
+  table.querySelectorAll("th").forEach(function (th, col) {
+    th.addEventListener("click", function () {
+      var body = table.tBodies[0];
+      var rows = Array.prototype.slice.call(body.rows);
+      var asc = th.dataset.order !== "asc";
+      th.dataset.order = asc ? "asc" : "desc";
+      rows.sort(function (a, b) {
+        var x = a.cells[col].dataset.value || a.cells[col].textContent;
+        var y = b.cells[col].dataset.value || b.cells[col].textContent;
+        var nx = Number(x), ny = Number(y);
+        var cmp = isNaN(nx) || isNaN(ny) ? x.localeCompare(y) : nx - ny;
+        return asc ? cmp : -cmp;
+      });
+      rows.forEach(function (row) { body.appendChild(row); });
+    });
+  });
+});
+"#;
+
+const BAR_HEIGHT: usize = 22;
+const LABEL_WIDTH: usize = 90;
+const CHART_WIDTH: usize = 480;
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+fn bar_chart(out: &mut String, report: &Report) {
+    let max = report.isa_summary.values().copied().max().unwrap_or(0);
+    if max == 0 {
+        return;
+    }
+    let height = report.isa_summary.len() * BAR_HEIGHT;
+    let _ = writeln!(
+        out,
+        r#"<svg width="{}" height="{}" role="img" aria-label="Instructions per ISA">"#,
+        LABEL_WIDTH + CHART_WIDTH + 60,
+        height
+    );
+    for (row, (isa, count)) in report.isa_summary.iter().enumerate() {
+        let y = row * BAR_HEIGHT;
+        let width = (count * CHART_WIDTH).div_ceil(max);
+        let _ = writeln!(
+            out,
+            r##"<text x="0" y="{}">{}</text><rect x="{}" y="{}" width="{}" height="{}" fill="#4a78c2"/><text x="{}" y="{}">{}</text>"##,
+            y + 15,
+            escape(isa),
+            LABEL_WIDTH,
+            y + 3,
+            width,
+            BAR_HEIGHT - 6,
+            LABEL_WIDTH + width + 6,
+            y + 15,
+            count
+        );
+    }
+    out.push_str("</svg>\n");
+}
+
+fn mnemonic_table(out: &mut String, report: &Report) {
+    let Some(details) = &report.isa_details else {
+        return;
+    };
+    out.push_str(
+        "<table class=\"sortable\">\n<thead><tr><th>ISA</th><th>Mnemonic</th><th>Count</th></tr></thead>\n<tbody>\n",
+    );
+    for isa in report.isa_summary.keys() {
+        let Some(detail) = details.get(isa) else {
+            continue;
+        };
+        for (mnemonic, count) in &detail.occurrences {
+            let _ = writeln!(
+                out,
+                r#"<tr><td>{}</td><td><code>{}</code></td><td class="num" data-value="{}">{}</td></tr>"#,
+                escape(isa),
+                escape(mnemonic),
+                count,
+                count
+            );
+        }
+    }
+    out.push_str("</tbody>\n</table>\n");
+}
+
+pub fn render(reports: &[Report], errors: &[ScanError]) -> String {
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str("<title>simdscan report</title>\n");
+    let _ = writeln!(out, "<style>{STYLE}</style>\n</head>\n<body>");
+    out.push_str("<h1>simdscan report</h1>\n");
+    match reports.first().and_then(|report| report.meta.as_ref()) {
+        Some(meta) => {
+            let _ = writeln!(out, "<p>Generated by {}</p>", escape(&meta.summary()));
+        }
+        None => {
+            let _ = writeln!(
+                out,
+                "<p>Generated by simdscan {}</p>",
+                env!("CARGO_PKG_VERSION")
+            );
+        }
+    }
+
+    for report in reports {
+        out.push_str("<section>\n");
+        let _ = writeln!(out, "<h2><code>{}</code></h2>", escape(&report.binary));
+        let _ = writeln!(
+            out,
+            "<dl><dt>Total SIMD instructions</dt><dd>{}</dd><dt>Has SIMD</dt><dd>{}</dd><dt>Requires</dt><dd>{}</dd></dl>",
+            report.total_simd_insts,
+            report.has_simd,
+            isa::level_name(report.required_level)
+        );
+        bar_chart(&mut out, report);
+        mnemonic_table(&mut out, report);
+        out.push_str("</section>\n");
+    }
+    for error in errors {
+        let _ = writeln!(
+            out,
+            "<section>\n<h2><code>{}</code></h2>\n<p>Scan failed: {}</p>\n</section>",
+            escape(&error.binary),
+            escape(&error.error)
+        );
+    }
+
+    let _ = writeln!(out, "<script>{SCRIPT}</script>\n</body>\n</html>");
+    out
+}
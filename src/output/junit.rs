@@ -0,0 +1,136 @@
+//! JUnit XML: one testcase per policy check, or one summary testcase per binary
+//! when no policy flags were given. A binary that failed to scan is a `scan`
+//! testcase with an `<error>`.
+
+use crate::isa;
+use crate::policy::{PolicyCheck, PolicyRule};
+use crate::report::{Report, ScanError};
+use std::fmt::Write;
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+fn failure_message(check: &PolicyCheck) -> String {
+    match check.rule {
+        PolicyRule::FailIfFound => {
+            let top: Vec<String> = check
+                .top_mnemonics
+                .iter()
+                .map(|(m, n)| format!("{m} ({n})"))
+                .collect();
+            format!(
+                "found {} {} instructions; top mnemonics: {}",
+                check.count,
+                check.isa,
+                top.join(", ")
+            )
+        }
+        PolicyRule::FailIfMissing => format!("no {} instructions found", check.isa),
+    }
+}
+
+fn summary(report: &Report) -> String {
+    let level = isa::level_name(report.required_level);
+    if report.isa_summary.is_empty() {
+        return format!("no SIMD instructions; requires {level}");
+    }
+    let counts = report
+        .isa_summary
+        .iter()
+        .map(|(isa, count)| format!("{isa}: {count}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("{counts}; requires {level}")
+}
+
+pub fn render(reports: &[Report], errors: &[ScanError]) -> String {
+    let mut cases = String::new();
+    let mut tests = 0;
+    let mut failures = 0;
+
+    for report in reports {
+        let classname = escape(&report.binary);
+
+        if report.policy.is_empty() {
+            tests += 1;
+            let _ = writeln!(
+                cases,
+                "  <testcase classname=\"{}\" name=\"scan\">\n    <system-out>{}</system-out>\n  </testcase>",
+                classname,
+                escape(&summary(report))
+            );
+            continue;
+        }
+
+        for check in &report.policy {
+            tests += 1;
+            let name = escape(&format!("{} {}", check.rule.flag(), check.isa));
+            if check.passed {
+                let _ = writeln!(
+                    cases,
+                    "  <testcase classname=\"{classname}\" name=\"{name}\"/>"
+                );
+            } else {
+                failures += 1;
+                let message = escape(&failure_message(check));
+                let _ = writeln!(
+                    cases,
+                    "  <testcase classname=\"{classname}\" name=\"{name}\">\n    <failure message=\"{message}\">{message}</failure>\n  </testcase>"
+                );
+            }
+        }
+    }
+
+    for error in errors {
+        tests += 1;
+        let message = escape(&error.error);
+        let _ = writeln!(
+            cases,
+            "  <testcase classname=\"{}\" name=\"scan\">\n    <error message=\"{message}\">{message}</error>\n  </testcase>",
+            escape(&error.binary)
+        );
+    }
+
+    let mut attrs = format!(
+        "name=\"simdscan\" tests=\"{tests}\" failures=\"{failures}\" errors=\"{}\"",
+        errors.len()
+    );
+    let mut properties = String::new();
+    if let Some(meta) = reports.first().and_then(|report| report.meta.as_ref()) {
+        let _ = write!(attrs, " timestamp=\"{}\"", escape(&meta.timestamp));
+        properties.push_str("  <properties>\n");
+        let entries = [
+            ("simdscan.version", meta.simdscan_version.clone()),
+            (
+                "simdscan.disassembler",
+                meta.disassembler.describe().to_string(),
+            ),
+            ("simdscan.args", meta.args.join(" ")),
+        ];
+        for (name, value) in entries {
+            let _ = writeln!(
+                properties,
+                "    <property name=\"{}\" value=\"{}\"/>",
+                name,
+                escape(&value)
+            );
+        }
+        properties.push_str("  </properties>\n");
+    }
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuite {attrs}>\n{properties}{cases}</testsuite>"
+    )
+}
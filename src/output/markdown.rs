@@ -0,0 +1,220 @@
+use crate::isa;
+use crate::report::{Aggregate, Report, ScanError};
+
+/// Format an integer with comma thousands separators.
+fn thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut rest = digits.as_str();
+    let mut groups = Vec::new();
+    while rest.len() > 3 {
+        let (head, tail) = rest.split_at(rest.len() - 3);
+        groups.push(tail);
+        rest = head;
+    }
+    groups.push(rest);
+    groups.reverse();
+    groups.join(",")
+}
+
+/// Wrap text in a code span, using a longer backtick fence if the text contains backticks.
+fn code_span(text: &str) -> String {
+    let longest_run = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest_run + 1);
+    if longest_run > 0 {
+        format!("{fence} {text} {fence}")
+    } else {
+        format!("{fence}{text}{fence}")
+    }
+}
+
+pub fn render(report: &Report) -> String {
+    let mut out = Vec::new();
+    out.push(format!("### SIMD usage in {}", code_span(&report.binary)));
+    out.push(String::new());
+    out.push(format!(
+        "**SIMD density: {:.2}%** ({} of {} instructions)",
+        report.simd_percent,
+        thousands(report.total_simd_insts),
+        thousands(report.total_insts)
+    ));
+    out.push(String::new());
+    if let (Some(code), Some(share), Some(per_kb)) = (
+        report.code_bytes,
+        report.simd_bytes_percent,
+        report.simd_per_kb,
+    ) {
+        out.push(format!(
+            "SIMD is {share:.2}% of {} code bytes, {per_kb:.2} SIMD instructions per KiB",
+            thousands(usize::try_from(code).unwrap_or(usize::MAX))
+        ));
+        out.push(String::new());
+    }
+    out.push(format!(
+        "Requires {}",
+        code_span(&isa::level_name(report.required_level))
+    ));
+    out.push(String::new());
+
+    if report.isa_summary.is_empty() && report.suppressed.is_none() {
+        out.push("No SIMD instructions found.".to_string());
+        push_meta(&mut out, report);
+        return out.join("\n");
+    }
+
+    out.push("| ISA | Instructions |".to_string());
+    out.push("| --- | ---: |".to_string());
+    for (isa, count) in &report.isa_summary {
+        let legacy = if isa::is_legacy(isa) { " (legacy)" } else { "" };
+        out.push(format!("| {}{} | {} |", isa, legacy, thousands(*count)));
+    }
+    if let Some(suppressed) = &report.suppressed {
+        let isas: Vec<&str> = suppressed.isas.keys().map(String::as_str).collect();
+        out.push(format!(
+            "| Suppressed ({}) | {} |",
+            isas.join(", "),
+            thousands(suppressed.total)
+        ));
+    }
+    out.push(format!(
+        "| **Total** | **{}** |",
+        thousands(report.total_simd_insts)
+    ));
+
+    if let Some(details) = &report.isa_details {
+        out.push(String::new());
+        out.push("<details>".to_string());
+        out.push("<summary>Per-mnemonic breakdown</summary>".to_string());
+        out.push(String::new());
+        out.push("| ISA | Mnemonic | Count |".to_string());
+        out.push("| --- | --- | ---: |".to_string());
+        // Walk the (sorted) summary keys so row order never depends on map iteration
+        for isa in report.isa_summary.keys() {
+            let Some(detail) = details.get(isa) else {
+                continue;
+            };
+            for (mnemonic, count) in &detail.occurrences {
+                out.push(format!(
+                    "| {} | {} | {} |",
+                    isa,
+                    code_span(mnemonic),
+                    thousands(*count)
+                ));
+            }
+        }
+        out.push(String::new());
+        out.push("</details>".to_string());
+    }
+
+    if let Some(functions) = report.top_functions.as_ref().filter(|f| !f.is_empty()) {
+        out.push(String::new());
+        out.push("| Function | SIMD instructions | SIMD density |".to_string());
+        out.push("| --- | ---: | ---: |".to_string());
+        for function in functions {
+            out.push(format!(
+                "| {} | {} | {:.2}% |",
+                code_span(&function.name),
+                thousands(function.total_simd_insts),
+                function.simd_percent
+            ));
+        }
+    }
+
+    if let Some(requirements) = report
+        .function_requirements
+        .as_ref()
+        .filter(|r| !r.is_empty())
+    {
+        let levels: Vec<String> = requirements
+            .iter()
+            .map(|(isa, n)| format!("{} {isa}", thousands(*n)))
+            .collect();
+        out.push(String::new());
+        out.push(format!("Functions by requirement: {}", levels.join(", ")));
+    }
+
+    if let Some(functions) = report.functions.as_ref().filter(|f| !f.is_empty()) {
+        out.push(String::new());
+        out.push("| Function | Address | SIMD instructions | SIMD density |".to_string());
+        out.push("| --- | --- | ---: | ---: |".to_string());
+        for function in functions {
+            out.push(format!(
+                "| {} | {} | {} | {:.2}% |",
+                code_span(&function.name),
+                function
+                    .address
+                    .as_deref()
+                    .map(code_span)
+                    .unwrap_or_default(),
+                thousands(function.total_simd_insts),
+                function.simd_percent
+            ));
+        }
+    }
+
+    push_meta(&mut out, report);
+    out.join("\n")
+}
+
+/// The section for a binary whose scan failed, shaped like a report's.
+pub fn render_error(error: &ScanError) -> String {
+    format!(
+        "### SIMD usage in {}\n\nScan failed: {}",
+        code_span(&error.binary),
+        error.error
+    )
+}
+
+/// The closing section of a multi-binary run.
+pub fn render_aggregate(aggregate: &Aggregate) -> String {
+    let mut out = Vec::new();
+    out.push(format!(
+        "### All {} binaries",
+        thousands(aggregate.binaries)
+    ));
+    out.push(String::new());
+    out.push(format!(
+        "**SIMD density: {:.2}%** ({} of {} instructions)",
+        aggregate.simd_percent,
+        thousands(aggregate.total_simd_insts),
+        thousands(aggregate.total_insts)
+    ));
+    out.push(String::new());
+    out.push(format!(
+        "{} with SIMD, {} failed; the most demanding requires {}",
+        thousands(aggregate.with_simd),
+        thousands(aggregate.failed),
+        code_span(&isa::level_name(aggregate.required_level))
+    ));
+    if let Some(skipped) = &aggregate.skipped {
+        out.push(String::new());
+        out.push(format!(
+            "Skipped {}: {} not binary, {} unreadable, {} excluded, {} symlink loops",
+            thousands(skipped.total()),
+            thousands(skipped.not_binary),
+            thousands(skipped.unreadable),
+            thousands(skipped.excluded),
+            thousands(skipped.symlink_loops)
+        ));
+    }
+    if !aggregate.isa_summary.is_empty() {
+        out.push(String::new());
+        out.push("| ISA | Instructions | Binaries |".to_string());
+        out.push("| --- | ---: | ---: |".to_string());
+        for (isa, count) in &aggregate.isa_summary {
+            out.push(format!(
+                "| {} | {} | {} |",
+                isa,
+                thousands(*count),
+                thousands(aggregate.binaries_per_isa.get(isa).copied().unwrap_or(0))
+            ));
+        }
+    }
+    out.join("\n")
+}
+
+fn push_meta(out: &mut Vec<String>, report: &Report) {
+    if let Some(meta) = &report.meta {
+        out.push(String::new());
+        out.push(format!("<sub>{}</sub>", meta.summary()));
+    }
+}
@@ -0,0 +1,357 @@
+mod badge;
+mod cbor;
+mod csv;
+mod gha;
+mod html;
+mod junit;
+mod markdown;
+pub mod ndjson;
+mod prom;
+mod sarif;
+mod sink;
+mod table;
+pub mod template;
+
+use crate::report::{MultiReport, Report, ReportEntry, ScanError};
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::path::Path;
+
+pub use sink::Sink;
+pub use template::Template;
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Pretty-printed JSON report
+    Json,
+    /// One compact JSON object per line, streamed per binary
+    Ndjson,
+    /// The JSON report as YAML
+    Yaml,
+    /// The JSON report as TOML, `isa_details` as tables keyed by ISA
+    Toml,
+    /// CBOR array of reports (binary; requires --output or a pipe)
+    Cbor,
+    /// One row per ISA: binary,isa,count,unique_mnemonics,required_level,error
+    Csv,
+    /// One row per mnemonic: binary,isa,mnemonic,count,required_level,error
+    CsvInsts,
+    /// Aligned columns for reading in a terminal
+    Table,
+    /// GitHub-flavored Markdown for PR descriptions and issues
+    Markdown,
+    /// SARIF 2.1.0 for code scanning dashboards
+    Sarif,
+    /// Self-contained HTML page with charts (best used with --output)
+    Html,
+    /// JUnit XML with one testcase per policy check
+    Junit,
+    /// GitHub Actions annotations for policy checks
+    Gha,
+    /// Prometheus exposition format for the node_exporter textfile collector
+    Prom,
+    /// shields.io endpoint JSON naming the highest ISA detected
+    Badge,
+    /// Render through a Handlebars template given with --template
+    Template,
+}
+
+/// Settings that affect how a report is rendered, independent of its contents.
+pub struct RenderOptions {
+    /// Emit ANSI styling (only sensible when writing to a terminal)
+    pub color: bool,
+    /// Template for `--format template`
+    pub template: Option<Template>,
+}
+
+impl OutputFormat {
+    /// Guess the format from an output file's extension.
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        Some(match ext.as_str() {
+            "json" => OutputFormat::Json,
+            "ndjson" | "jsonl" => OutputFormat::Ndjson,
+            "yaml" | "yml" => OutputFormat::Yaml,
+            "toml" => OutputFormat::Toml,
+            "cbor" => OutputFormat::Cbor,
+            "csv" => OutputFormat::Csv,
+            "md" | "markdown" => OutputFormat::Markdown,
+            "sarif" => OutputFormat::Sarif,
+            "html" | "htm" => OutputFormat::Html,
+            "xml" => OutputFormat::Junit,
+            "prom" => OutputFormat::Prom,
+            _ => return None,
+        })
+    }
+
+    /// Formats that produce bytes rather than text.
+    pub fn is_binary(self) -> bool {
+        self == OutputFormat::Cbor
+    }
+
+    /// Whether this format needs the per-ISA mnemonic breakdown regardless of `--show-insts`.
+    pub fn needs_details(self) -> bool {
+        matches!(
+            self,
+            OutputFormat::Csv | OutputFormat::CsvInsts | OutputFormat::Prom
+        )
+    }
+}
+
+/// Render a report in the requested format. Text formats always end with a newline.
+pub fn render(format: OutputFormat, report: &Report, opts: &RenderOptions) -> Result<Vec<u8>> {
+    let mut text = match format {
+        OutputFormat::Cbor => return cbor::render(std::slice::from_ref(report)),
+        OutputFormat::Json => serde_json::to_string_pretty(report)?,
+        OutputFormat::Yaml => serde_yaml::to_string(report)?,
+        OutputFormat::Toml => toml::to_string_pretty(report)?,
+        OutputFormat::Ndjson => ndjson::report_line(report)?,
+        OutputFormat::Csv => csv::render_isas(report),
+        OutputFormat::CsvInsts => csv::render_insts(report),
+        OutputFormat::Table => table::render(report, opts),
+        OutputFormat::Markdown => markdown::render(report),
+        OutputFormat::Sarif => sarif::render(std::slice::from_ref(report), &[])?,
+        OutputFormat::Html => html::render(std::slice::from_ref(report), &[]),
+        OutputFormat::Junit => junit::render(std::slice::from_ref(report), &[]),
+        OutputFormat::Gha => gha::render(report),
+        OutputFormat::Prom => prom::render(std::slice::from_ref(report), &[]),
+        OutputFormat::Badge => badge::render(report)?,
+        OutputFormat::Template => {
+            let template = opts
+                .template
+                .as_ref()
+                .context("--format template requires --template")?;
+            template::render(template, std::slice::from_ref(report), &[])?
+        }
+    };
+    if !text.ends_with('\n') {
+        text.push('\n');
+    }
+    Ok(text.into_bytes())
+}
+
+/// Render a multi-binary run. JSON, YAML and TOML get the whole
+/// `MultiReport`; the per-report text formats repeat per binary and add the
+/// `aggregate` where it has a place; the formats built for several reports
+/// get the scanned ones. Failed binaries appear in each format's own way (an
+/// `error` row, an `<error>` testcase, a SARIF tool notification, ...),
+/// except in CBOR, which is an array of reports only.
+pub fn render_many(
+    format: OutputFormat,
+    multi: MultiReport,
+    opts: &RenderOptions,
+) -> Result<Vec<u8>> {
+    let mut text = match format {
+        OutputFormat::Json => serde_json::to_string_pretty(&multi)?,
+        OutputFormat::Yaml => serde_yaml::to_string(&multi)?,
+        OutputFormat::Toml => toml::to_string_pretty(&multi)?,
+        OutputFormat::Badge => badge::render_aggregate(&multi.aggregate)?,
+        OutputFormat::Ndjson => {
+            let lines: Vec<String> = multi
+                .reports
+                .iter()
+                .map(|entry| match entry {
+                    ReportEntry::Report(report) => ndjson::report_line(report),
+                    ReportEntry::Error(error) => ndjson::error_line(&error.binary, &error.error),
+                })
+                .collect::<serde_json::Result<_>>()?;
+            lines.join("\n")
+        }
+        OutputFormat::Table | OutputFormat::Markdown => {
+            let mut sections: Vec<String> = multi
+                .reports
+                .iter()
+                .map(|entry| match (entry, format) {
+                    (ReportEntry::Report(report), OutputFormat::Table) => {
+                        table::render(report, opts)
+                    }
+                    (ReportEntry::Report(report), _) => markdown::render(report),
+                    (ReportEntry::Error(error), OutputFormat::Table) => {
+                        format!("{}\nerror: {}", error.binary, error.error)
+                    }
+                    (ReportEntry::Error(error), _) => markdown::render_error(error),
+                })
+                .collect();
+            sections.push(if format == OutputFormat::Table {
+                table::render_aggregate(&multi.aggregate, opts)
+            } else {
+                markdown::render_aggregate(&multi.aggregate)
+            });
+            sections.join("\n\n")
+        }
+        // One header row for all binaries
+        OutputFormat::Csv | OutputFormat::CsvInsts => {
+            let header = if format == OutputFormat::Csv {
+                csv::ISAS_HEADER
+            } else {
+                csv::INSTS_HEADER
+            };
+            let mut rows = vec![header.to_string()];
+            for entry in &multi.reports {
+                match entry {
+                    ReportEntry::Report(report) => {
+                        let rendered = if format == OutputFormat::Csv {
+                            csv::render_isas(report)
+                        } else {
+                            csv::render_insts(report)
+                        };
+                        rows.extend(rendered.lines().skip(1).map(str::to_string));
+                    }
+                    ReportEntry::Error(error) => rows.push(csv::render_error(error)),
+                }
+            }
+            rows.join("\n")
+        }
+        // Each binary's annotations in turn
+        OutputFormat::Gha => multi
+            .reports
+            .iter()
+            .map(|entry| match entry {
+                ReportEntry::Report(report) => gha::render(report),
+                ReportEntry::Error(error) => gha::render_error(error),
+            })
+            .filter(|lines| !lines.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => {
+            let mut reports: Vec<Report> = Vec::new();
+            let mut errors: Vec<ScanError> = Vec::new();
+            for entry in multi.reports {
+                match entry {
+                    ReportEntry::Report(report) => reports.push(*report),
+                    ReportEntry::Error(error) => errors.push(error),
+                }
+            }
+            match format {
+                OutputFormat::Cbor => return cbor::render(&reports),
+                OutputFormat::Sarif => sarif::render(&reports, &errors)?,
+                OutputFormat::Html => html::render(&reports, &errors),
+                OutputFormat::Junit => junit::render(&reports, &errors),
+                OutputFormat::Prom => prom::render(&reports, &errors),
+                _ => {
+                    let template = opts
+                        .template
+                        .as_ref()
+                        .context("--format template requires --template")?;
+                    template::render(template, &reports, &errors)?
+                }
+            }
+        }
+    };
+    if !text.ends_with('\n') {
+        text.push('\n');
+    }
+    Ok(text.into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `--show-insts --per-function --loop-heuristic` report of a small
+    /// CPUID-dispatching C program.
+    pub fn fixture() -> Report {
+        serde_json::from_str(include_str!("../../testdata/report.json")).unwrap()
+    }
+
+    fn opts() -> RenderOptions {
+        RenderOptions {
+            color: false,
+            template: None,
+        }
+    }
+
+    #[test]
+    fn toml_matches_json_field_by_field() {
+        let report = fixture();
+        let json = render(OutputFormat::Json, &report, &opts()).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        let toml = render(OutputFormat::Toml, &report, &opts()).unwrap();
+        let toml: serde_json::Value = toml::from_str(&String::from_utf8(toml).unwrap()).unwrap();
+        let (json, toml) = (json.as_object().unwrap(), toml.as_object().unwrap());
+        assert!(json.contains_key("isa_details"));
+        for (field, value) in json {
+            assert_eq!(toml.get(field), Some(value), "field `{field}`");
+        }
+        assert_eq!(json.len(), toml.len());
+    }
+
+    /// The fixture report and a binary that failed to scan.
+    fn with_failure() -> MultiReport {
+        let reports = vec![
+            ReportEntry::Report(Box::new(fixture())),
+            ReportEntry::Error(ScanError {
+                binary: "missing.so".to_string(),
+                error: "Failed to open 'missing.so'".to_string(),
+            }),
+        ];
+        MultiReport {
+            schema_version: crate::report::SCHEMA_VERSION,
+            aggregate: crate::report::build_aggregate(&reports),
+            reports,
+        }
+    }
+
+    fn render_text(format: OutputFormat, opts: &RenderOptions) -> String {
+        String::from_utf8(render_many(format, with_failure(), opts).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn failed_binaries_in_every_format() {
+        let csv = render_text(OutputFormat::Csv, &opts());
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some(csv::ISAS_HEADER));
+        assert!(lines.clone().all(|row| row.split(',').count() == 6));
+        assert_eq!(
+            lines.last(),
+            Some("missing.so,,,,,Failed to open 'missing.so'")
+        );
+        let csv = render_text(OutputFormat::CsvInsts, &opts());
+        assert!(csv.starts_with(csv::INSTS_HEADER));
+        assert!(csv.ends_with("missing.so,,,,,Failed to open 'missing.so'\n"));
+
+        let junit = render_text(OutputFormat::Junit, &opts());
+        assert!(junit.contains(" errors=\"1\""));
+        assert!(junit.contains(
+            "<testcase classname=\"missing.so\" name=\"scan\">\n    <error message=\"Failed to open &apos;missing.so&apos;\">"
+        ));
+
+        let sarif: serde_json::Value =
+            serde_json::from_str(&render_text(OutputFormat::Sarif, &opts())).unwrap();
+        let invocation = &sarif["runs"][0]["invocations"][0];
+        assert_eq!(invocation["executionSuccessful"], false);
+        let notification = &invocation["toolExecutionNotifications"][0];
+        assert_eq!(notification["level"], "error");
+        assert_eq!(
+            notification["locations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+            "missing.so"
+        );
+
+        let gha = render_text(OutputFormat::Gha, &opts());
+        assert!(gha.ends_with(
+            "::error file=missing.so,title=simdscan::missing.so: scan failed: Failed to open 'missing.so'\n"
+        ));
+        let html = render_text(OutputFormat::Html, &opts());
+        assert!(html.contains("<p>Scan failed: Failed to open &#39;missing.so&#39;</p>"));
+        let prom = render_text(OutputFormat::Prom, &opts());
+        assert!(prom.contains("simdscan_scan_success{binary=\"testdata/dispatch\"} 1\n"));
+        assert!(prom.contains("simdscan_scan_success{binary=\"missing.so\"} 0\n"));
+
+        let template = RenderOptions {
+            color: false,
+            template: Some(Template {
+                name: "errors".to_string(),
+                source: "{{#each errors}}{{binary}}: {{error}}{{/each}}".to_string(),
+            }),
+        };
+        assert_eq!(
+            render_text(OutputFormat::Template, &template),
+            "missing.so: Failed to open 'missing.so'\n"
+        );
+
+        // CBOR stays a `Vec<Report>`: the failure is left out
+        let cbor = render_many(OutputFormat::Cbor, with_failure(), &opts()).unwrap();
+        let decoded: Vec<Report> = ciborium::from_reader(cbor.as_slice()).unwrap();
+        assert_eq!(decoded.len(), 1);
+    }
+}
@@ -0,0 +1,16 @@
+use crate::report::{Report, ScanError, Skipped, SkippedLine};
+
+pub fn report_line(report: &Report) -> serde_json::Result<String> {
+    serde_json::to_string(report)
+}
+
+pub fn error_line(binary: &str, error: &str) -> serde_json::Result<String> {
+    serde_json::to_string(&ScanError {
+        binary: binary.to_string(),
+        error: error.to_string(),
+    })
+}
+
+pub fn skipped_line(skipped: Skipped) -> serde_json::Result<String> {
+    serde_json::to_string(&SkippedLine { skipped })
+}
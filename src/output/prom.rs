@@ -0,0 +1,126 @@
+//! Prometheus text exposition format, for the node_exporter textfile collector.
+
+use crate::report::{Report, ScanError};
+use std::fmt::Write;
+
+fn label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn header(out: &mut String, name: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} gauge");
+}
+
+pub fn render(reports: &[Report], errors: &[ScanError]) -> String {
+    let mut out = String::new();
+
+    if let Some(meta) = reports.first().and_then(|report| report.meta.as_ref()) {
+        header(
+            &mut out,
+            "simdscan_info",
+            "Version of simdscan and the disassembler that produced these metrics.",
+        );
+        let _ = writeln!(
+            out,
+            "simdscan_info{{version=\"{}\",disassembler=\"{}\"}} 1",
+            label(&meta.simdscan_version),
+            label(
+                meta.disassembler
+                    .version
+                    .as_deref()
+                    .unwrap_or(&meta.disassembler.name)
+            )
+        );
+    }
+
+    header(
+        &mut out,
+        "simdscan_scan_success",
+        "Whether the binary was scanned (1) or the scan failed (0).",
+    );
+    let scanned = reports.iter().map(|report| (&report.binary, 1));
+    let failed = errors.iter().map(|error| (&error.binary, 0));
+    for (binary, success) in scanned.chain(failed) {
+        let _ = writeln!(
+            out,
+            "simdscan_scan_success{{binary=\"{}\"}} {}",
+            label(binary),
+            success
+        );
+    }
+
+    header(
+        &mut out,
+        "simdscan_instructions_total",
+        "SIMD instructions found in the binary, by ISA extension.",
+    );
+    for report in reports {
+        let binary = label(&report.binary);
+        for (isa, count) in &report.isa_summary {
+            let _ = writeln!(
+                out,
+                "simdscan_instructions_total{{binary=\"{}\",isa=\"{}\"}} {}",
+                binary,
+                label(isa),
+                count
+            );
+        }
+    }
+
+    header(
+        &mut out,
+        "simdscan_has_simd",
+        "Whether the binary contains any SIMD instructions (1) or not (0).",
+    );
+    for report in reports {
+        let _ = writeln!(
+            out,
+            "simdscan_has_simd{{binary=\"{}\"}} {}",
+            label(&report.binary),
+            u8::from(report.has_simd)
+        );
+    }
+
+    header(
+        &mut out,
+        "simdscan_required_level",
+        "Minimum x86-64 psABI microarchitecture level (1-4) the binary needs.",
+    );
+    for report in reports {
+        let _ = writeln!(
+            out,
+            "simdscan_required_level{{binary=\"{}\"}} {}",
+            label(&report.binary),
+            report.required_level
+        );
+    }
+
+    header(
+        &mut out,
+        "simdscan_unique_mnemonics",
+        "Distinct SIMD mnemonics found in the binary, by ISA extension.",
+    );
+    for report in reports {
+        let binary = label(&report.binary);
+        let Some(details) = &report.isa_details else {
+            continue;
+        };
+        for isa in report.isa_summary.keys() {
+            if let Some(detail) = details.get(isa) {
+                let _ = writeln!(
+                    out,
+                    "simdscan_unique_mnemonics{{binary=\"{}\",isa=\"{}\"}} {}",
+                    binary,
+                    label(isa),
+                    detail.unique_mnemonics
+                );
+            }
+        }
+    }
+
+    out
+}
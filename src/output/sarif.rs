@@ -0,0 +1,213 @@
+//! SARIF 2.1.0 output. Each ISA extension is a rule; each (binary, ISA) pair is a result.
+//! A binary that failed to scan is an error in the invocation's
+//! `toolExecutionNotifications`.
+
+use crate::isa;
+use crate::report::{Report, ScanError};
+use serde::Serialize;
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+#[derive(Serialize)]
+struct Log {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    version: &'static str,
+    runs: Vec<Run>,
+}
+
+#[derive(Serialize)]
+struct Run {
+    tool: Tool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    invocations: Vec<Invocation>,
+    results: Vec<SarifResult>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Invocation {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    arguments: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_time_utc: Option<String>,
+    execution_successful: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tool_execution_notifications: Vec<Notification>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    properties: Option<InvocationProperties>,
+}
+
+#[derive(Serialize)]
+struct Notification {
+    level: &'static str,
+    message: Message,
+    locations: Vec<Location>,
+}
+
+#[derive(Serialize)]
+struct InvocationProperties {
+    disassembler: String,
+}
+
+#[derive(Serialize)]
+struct Tool {
+    driver: Driver,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Driver {
+    name: &'static str,
+    version: &'static str,
+    information_uri: &'static str,
+    rules: Vec<Rule>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Rule {
+    id: String,
+    name: String,
+    short_description: Message,
+}
+
+#[derive(Serialize)]
+struct Message {
+    text: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult {
+    rule_id: String,
+    rule_index: usize,
+    level: &'static str,
+    message: Message,
+    locations: Vec<Location>,
+    properties: ResultProperties,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ResultProperties {
+    required_level: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Location {
+    physical_location: PhysicalLocation,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhysicalLocation {
+    artifact_location: ArtifactLocation,
+}
+
+#[derive(Serialize)]
+struct ArtifactLocation {
+    uri: String,
+}
+
+fn location(binary: &str) -> Location {
+    Location {
+        physical_location: PhysicalLocation {
+            artifact_location: ArtifactLocation {
+                uri: binary.to_string(),
+            },
+        },
+    }
+}
+
+/// Rule id for an ISA key, e.g. `AVX-512` -> `simdscan/avx512`.
+fn rule_id(isa: &str) -> String {
+    let slug: String = isa
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    format!("simdscan/{slug}")
+}
+
+pub fn render(reports: &[Report], errors: &[ScanError]) -> serde_json::Result<String> {
+    let mut rules: Vec<Rule> = Vec::new();
+    let mut results = Vec::new();
+
+    for report in reports {
+        for (isa, count) in &report.isa_summary {
+            let id = rule_id(isa);
+            let rule_index = match rules.iter().position(|r| r.id == id) {
+                Some(index) => index,
+                None => {
+                    rules.push(Rule {
+                        id: id.clone(),
+                        name: isa.clone(),
+                        short_description: Message {
+                            text: format!("Binary contains {isa} instructions"),
+                        },
+                    });
+                    rules.len() - 1
+                }
+            };
+
+            results.push(SarifResult {
+                rule_id: id,
+                rule_index,
+                level: "note",
+                message: Message {
+                    text: format!("{count} {isa} instructions in {}", report.binary),
+                },
+                locations: vec![location(&report.binary)],
+                properties: ResultProperties {
+                    required_level: isa::level_name(report.required_level),
+                },
+            });
+        }
+    }
+
+    let notifications: Vec<Notification> = errors
+        .iter()
+        .map(|error| Notification {
+            level: "error",
+            message: Message {
+                text: format!("Failed to scan {}: {}", error.binary, error.error),
+            },
+            locations: vec![location(&error.binary)],
+        })
+        .collect();
+    let meta = reports.first().and_then(|report| report.meta.as_ref());
+    let invocations = if meta.is_some() || !notifications.is_empty() {
+        vec![Invocation {
+            arguments: meta.map(|meta| meta.args.clone()),
+            start_time_utc: meta.map(|meta| meta.timestamp.clone()),
+            execution_successful: notifications.is_empty(),
+            tool_execution_notifications: notifications,
+            properties: meta.map(|meta| InvocationProperties {
+                disassembler: meta.disassembler.describe().to_string(),
+            }),
+        }]
+    } else {
+        Vec::new()
+    };
+
+    let log = Log {
+        schema: SCHEMA,
+        version: "2.1.0",
+        runs: vec![Run {
+            tool: Tool {
+                driver: Driver {
+                    name: env!("CARGO_PKG_NAME"),
+                    version: env!("CARGO_PKG_VERSION"),
+                    information_uri: env!("CARGO_PKG_REPOSITORY"),
+                    rules,
+                },
+            },
+            invocations,
+            results,
+        }],
+    };
+
+    serde_json::to_string_pretty(&log)
+}
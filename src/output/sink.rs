@@ -0,0 +1,105 @@
+use anyhow::{Context, Result};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Where the rendered report goes. File output is written to a temporary
+/// sibling and renamed into place on [`Sink::commit`], so readers never see a
+/// half-written report and a failed run leaves any previous file untouched.
+pub enum Sink {
+    Stdout(io::StdoutLock<'static>),
+    File(AtomicFile),
+}
+
+pub struct AtomicFile {
+    writer: Option<BufWriter<File>>,
+    temp: PathBuf,
+    target: PathBuf,
+}
+
+impl Sink {
+    pub fn open(path: Option<&Path>, mkdirs: bool) -> Result<Self> {
+        match path {
+            Some(target) => Ok(Sink::File(AtomicFile::create(target, mkdirs)?)),
+            None => Ok(Sink::Stdout(io::stdout().lock())),
+        }
+    }
+
+    /// Flush and, for file output, move the temporary file over the target.
+    pub fn commit(self) -> Result<()> {
+        match self {
+            Sink::Stdout(mut out) => Ok(out.flush()?),
+            Sink::File(file) => file.commit(),
+        }
+    }
+}
+
+impl AtomicFile {
+    fn create(target: &Path, mkdirs: bool) -> Result<Self> {
+        let parent = match target.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        if mkdirs {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory '{}'", parent.display()))?;
+        }
+
+        let file_name = target
+            .file_name()
+            .with_context(|| format!("Output path '{}' has no file name", target.display()))?;
+        let temp = parent.join(format!(
+            ".{}.{}.tmp",
+            file_name.to_string_lossy(),
+            std::process::id()
+        ));
+        let file = File::create(&temp)
+            .with_context(|| format!("Failed to create '{}'", temp.display()))?;
+
+        Ok(AtomicFile {
+            writer: Some(BufWriter::new(file)),
+            temp,
+            target: target.to_path_buf(),
+        })
+    }
+
+    fn commit(mut self) -> Result<()> {
+        if let Some(writer) = self.writer.take() {
+            let file = writer
+                .into_inner()
+                .map_err(|err| err.into_error())
+                .with_context(|| format!("Failed to write '{}'", self.temp.display()))?;
+            file.sync_all()?;
+        }
+        fs::rename(&self.temp, &self.target).with_context(|| {
+            format!(
+                "Failed to move report into place at '{}'",
+                self.target.display()
+            )
+        })
+    }
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Sink::Stdout(out) => out.write(buf),
+            Sink::File(file) => file.writer.as_mut().expect("sink in use").write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Sink::Stdout(out) => out.flush(),
+            Sink::File(file) => file.writer.as_mut().expect("sink in use").flush(),
+        }
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        // After a successful commit the temp path no longer exists, so this is a no-op
+        drop(self.writer.take());
+        let _ = fs::remove_file(&self.temp);
+    }
+}
@@ -0,0 +1,626 @@
+use super::RenderOptions;
+use crate::isa;
+use crate::report::{Aggregate, ExclusionRule, MismatchKind, Report};
+use indexmap::IndexMap;
+
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+fn percent(count: usize, total: usize) -> String {
+    if total == 0 {
+        "0.0%".to_string()
+    } else {
+        format!("{:.1}%", count as f64 * 100.0 / total as f64)
+    }
+}
+
+/// `name count, name count, ...` in map order.
+fn counts(counts: &IndexMap<String, usize>) -> String {
+    let pairs: Vec<String> = counts
+        .iter()
+        .map(|(name, count)| format!("{name} {count}"))
+        .collect();
+    pairs.join(", ")
+}
+
+/// The closing row group of a multi-binary run.
+pub fn render_aggregate(aggregate: &Aggregate, opts: &RenderOptions) -> String {
+    let mut out = Vec::new();
+    out.push(format!(
+        "all {} binaries: {} with SIMD, {} failed",
+        aggregate.binaries, aggregate.with_simd, aggregate.failed
+    ));
+    if let Some(skipped) = &aggregate.skipped {
+        out.push(format!(
+            "skipped {}: {} not binary, {} unreadable, {} excluded, {} symlink loops",
+            skipped.total(),
+            skipped.not_binary,
+            skipped.unreadable,
+            skipped.excluded,
+            skipped.symlink_loops
+        ));
+    }
+    let density = format!(
+        "SIMD density: {:.2}% of {} instructions, most demanding requires {}",
+        aggregate.simd_percent,
+        aggregate.total_insts,
+        isa::level_name(aggregate.required_level)
+    );
+    if opts.color {
+        out.push(format!("{BOLD}{density}{RESET}"));
+    } else {
+        out.push(density);
+    }
+    let isa_w = aggregate
+        .isa_summary
+        .keys()
+        .map(String::len)
+        .chain(["ISA".len()])
+        .max()
+        .unwrap_or(0);
+    let count_w = aggregate
+        .isa_summary
+        .values()
+        .map(|count| count.to_string().len())
+        .chain(["COUNT".len()])
+        .max()
+        .unwrap_or(0);
+    if !aggregate.isa_summary.is_empty() {
+        out.push(format!(
+            "{:<isa_w$}  {:>count_w$}  BINARIES",
+            "ISA", "COUNT"
+        ));
+        for (isa, count) in &aggregate.isa_summary {
+            out.push(format!(
+                "{isa:<isa_w$}  {count:>count_w$}  {}",
+                aggregate.binaries_per_isa.get(isa).copied().unwrap_or(0)
+            ));
+        }
+    }
+    out.push(format!(
+        "total SIMD: {} across {} extensions",
+        aggregate.total_simd_insts,
+        aggregate.isa_summary.len()
+    ));
+    out.join("\n")
+}
+
+pub fn render(report: &Report, opts: &RenderOptions) -> String {
+    let total = report.total_simd_insts;
+    let rows: Vec<(&str, String, String)> = report
+        .isa_summary
+        .iter()
+        .map(|(isa, count)| (isa.as_str(), count.to_string(), percent(*count, total)))
+        .collect();
+
+    let headers = ("ISA", "COUNT", "SHARE");
+    let isa_w = rows
+        .iter()
+        .map(|r| r.0.len())
+        .chain([headers.0.len()])
+        .max()
+        .unwrap_or(0);
+    let count_w = rows
+        .iter()
+        .map(|r| r.1.len())
+        .chain([headers.1.len()])
+        .max()
+        .unwrap_or(0);
+    let share_w = rows
+        .iter()
+        .map(|r| r.2.len())
+        .chain([headers.2.len()])
+        .max()
+        .unwrap_or(0);
+
+    let mut out = Vec::new();
+    out.push(report.binary.clone());
+    let density = format!(
+        "SIMD density: {:.2}% of {} instructions, requires {} (Intel {} / AMD {})",
+        report.simd_percent,
+        report.total_insts,
+        isa::level_name(report.required_level),
+        report.min_cpu.intel,
+        report.min_cpu.amd
+    );
+    if opts.color {
+        out.push(format!("{BOLD}{density}{RESET}"));
+    } else {
+        out.push(density);
+    }
+    if let (Some(code), Some(share), Some(per_kb)) = (
+        report.code_bytes,
+        report.simd_bytes_percent,
+        report.simd_per_kb,
+    ) {
+        out.push(format!(
+            "code size: {code} bytes, {} of them SIMD ({share:.2}%), {per_kb:.2} SIMD instructions per KiB",
+            report.simd_bytes
+        ));
+    }
+    if let Some(reach) = &report.reachability {
+        out.push(format!(
+            "entry {}: {} reachable functions; not followed: {} indirect calls, {} dispatched calls, {} external callees",
+            reach.entry,
+            reach.reachable_functions,
+            reach.indirect_calls.len(),
+            reach.dispatched_calls.len(),
+            reach.external_calls.len()
+        ));
+    }
+
+    let header = format!(
+        "{:<isa_w$}  {:>count_w$}  {:>share_w$}",
+        headers.0, headers.1, headers.2
+    );
+    if opts.color {
+        out.push(format!("{BOLD}{header}{RESET}"));
+    } else {
+        out.push(header);
+    }
+
+    for (isa, count, share) in &rows {
+        let legacy = if isa::is_legacy(isa) {
+            "  (legacy)"
+        } else {
+            ""
+        };
+        out.push(format!(
+            "{isa:<isa_w$}  {count:>count_w$}  {share:>share_w$}{legacy}"
+        ));
+
+        let detail = report.isa_details.as_ref().and_then(|d| d.get(*isa));
+        if let Some(detail) = detail {
+            let mne_w = detail
+                .occurrences
+                .keys()
+                .map(|m| m.len())
+                .max()
+                .unwrap_or(0);
+            let num_w = detail
+                .occurrences
+                .values()
+                .map(|c| c.to_string().len())
+                .max()
+                .unwrap_or(0);
+            for (mnemonic, n) in &detail.occurrences {
+                let at = detail
+                    .addresses
+                    .get(mnemonic)
+                    .map_or(String::new(), |list| {
+                        let places: Vec<&str> = list
+                            .iter()
+                            .map(|a| a.location.as_deref().unwrap_or(&a.address))
+                            .collect();
+                        format!("  at {}", places.join(", "))
+                    });
+                out.push(format!("    {mnemonic:<mne_w$}  {n:>num_w$}{at}"));
+            }
+            out.push(format!("    by category: {}", counts(&detail.categories)));
+        }
+    }
+
+    out.push(format!(
+        "total SIMD: {} across {} extensions",
+        total,
+        report.isa_summary.len()
+    ));
+    if let Some(loops) = &report.loops {
+        out.push(format!(
+            "loops (heuristic): {} in-loop, {} straight-line SIMD ({:.2}% in {} loops)",
+            loops.in_loop, loops.straight_line, loops.in_loop_percent, loops.loops
+        ));
+    }
+    if let Some(weighted) = &report.weighted_counts {
+        out.push(format!(
+            "weighted by {}: {:.2}% SIMD, {:.2}% AVX-512 of {} matched samples ({} read){}{}",
+            weighted.profile,
+            weighted.simd_percent,
+            weighted.avx512_percent,
+            weighted.matched_samples,
+            weighted.total_samples,
+            if weighted.isa_summary.is_empty() {
+                ""
+            } else {
+                ": "
+            },
+            counts(&weighted.isa_summary)
+        ));
+    }
+    if let Some(dispatch) = &report.dispatch {
+        let guarded = if dispatch.guarded_isas.is_empty() {
+            "none".to_string()
+        } else {
+            dispatch.guarded_isas.join(", ")
+        };
+        out.push(format!(
+            "dispatch: {} multiversioned functions, guarded-only ISAs: {guarded}; {} without guarded variants{}",
+            dispatch.functions.len(),
+            isa::level_name(dispatch.baseline_level),
+            if dispatch.assumed { " (assumed)" } else { "" }
+        ));
+    }
+    if !report.dependencies.is_empty() {
+        let names: Vec<String> = report
+            .dependencies
+            .iter()
+            .map(|dep| match &dep.simd_provider {
+                Some(what) => format!("{} (SIMD provider: {what})", dep.name),
+                None => dep.name.clone(),
+            })
+            .collect();
+        out.push(format!("dependencies: {}", names.join(", ")));
+    }
+    if let (Some(guarded), Some(unconditional), Some(guards)) = (
+        &report.guarded_isas,
+        &report.unconditional_isas,
+        &report.dispatch_guards,
+    ) {
+        out.push(format!(
+            "dispatch analysis: guarded {}; unconditional {} ({} checkers, {} guarded functions)",
+            if guarded.is_empty() {
+                "none".to_string()
+            } else {
+                counts(guarded)
+            },
+            if unconditional.is_empty() {
+                "none".to_string()
+            } else {
+                counts(unconditional)
+            },
+            guards.checkers.len(),
+            guards.guarded_functions.len()
+        ));
+    }
+    if let Some(clones) = &report.feature_clones {
+        let flagged: Vec<String> = clones
+            .groups
+            .iter()
+            .flat_map(|group| &group.clones)
+            .filter(|clone| clone.flagged)
+            .map(|clone| format!("{} (no {})", clone.symbol, clone.claimed_isas.join("/")))
+            .collect();
+        out.push(format!(
+            "feature clones: {} functions, {} symbols; flagged: {}",
+            clones.groups.len(),
+            clones
+                .groups
+                .iter()
+                .map(|group| group.clones.len())
+                .sum::<usize>(),
+            if flagged.is_empty() {
+                "none".to_string()
+            } else {
+                flagged.join(", ")
+            }
+        ));
+    }
+    if let Some(property) = &report.gnu_property {
+        let line = if !property.present {
+            "no .note.gnu.property".to_string()
+        } else if property.mismatches.is_empty() {
+            "matches the instructions found".to_string()
+        } else {
+            let mismatches: Vec<String> = property
+                .mismatches
+                .iter()
+                .map(|m| {
+                    let direction = match m.kind {
+                        MismatchKind::DeclaredNotObserved => "declared, not found",
+                        MismatchKind::ObservedNotDeclared => "found, not declared",
+                    };
+                    format!("{} {} ({direction})", m.property, m.what)
+                })
+                .collect();
+            mismatches.join(", ")
+        };
+        out.push(format!("gnu property: {line}"));
+    }
+    if let Some(compatibility) = &report.compatibility {
+        let line = if compatibility.faulting_insts == 0 {
+            "compatible".to_string()
+        } else {
+            let isas: Vec<String> = compatibility
+                .isas
+                .iter()
+                .map(|(isa, faults)| match faults.addresses.first() {
+                    Some(first) => format!("{isa} {} from {}", faults.count, first.address),
+                    None => format!("{isa} {}", faults.count),
+                })
+                .collect();
+            let functions: Vec<String> = compatibility
+                .functions
+                .iter()
+                .take(5)
+                .map(|(name, count)| format!("{name} {count}"))
+                .collect();
+            format!(
+                "{}, {} faulting instructions needing {} ({}) in {}{}",
+                if compatibility.compatible {
+                    "compatible outside dispatch variants"
+                } else {
+                    "incompatible"
+                },
+                compatibility.faulting_insts,
+                compatibility.missing_features.join(", "),
+                isas.join(", "),
+                functions.join(", "),
+                if compatibility.functions.len() > 5 {
+                    ", ..."
+                } else {
+                    ""
+                }
+            )
+        };
+        out.push(format!("target {}: {line}", compatibility.target));
+    }
+    if let Some(filter) = &report.isa_filter {
+        let mut parts = Vec::new();
+        if !filter.only.is_empty() {
+            parts.push(format!("only {}", filter.only.join(", ")));
+        }
+        if !filter.exclude.is_empty() {
+            parts.push(format!("excluding {}", filter.exclude.join(", ")));
+        }
+        out.push(format!(
+            "ISA filter: {}{}",
+            parts.join("; "),
+            if filter.recomputed_totals {
+                " (totals recomputed)"
+            } else {
+                " (totals over every ISA)"
+            }
+        ));
+    }
+    if let Some(suppressed) = &report.suppressed {
+        out.push(format!(
+            "suppressed: {} instructions ({})",
+            suppressed.total,
+            counts(&suppressed.isas)
+        ));
+    }
+    if let Some(scalar) = report.scalar_extensions.as_ref().filter(|s| !s.is_empty()) {
+        let counts: Vec<String> = scalar
+            .iter()
+            .map(|(ext, count)| format!("{ext} {count}"))
+            .collect();
+        out.push(format!("scalar extensions: {}", counts.join(", ")));
+    }
+    if let Some(other) = report.other_extensions.as_ref().filter(|o| !o.is_empty()) {
+        out.push(format!("other extensions: {}", counts(other)));
+    }
+    if let Some(emms) = report.mmx_emms {
+        out.push(format!("MMX: {emms} emms"));
+    }
+    // Coarse reports already have the single AVX-512 row
+    if let Some(total) = report.avx512_any {
+        if !report.isa_summary.contains_key("AVX-512") {
+            out.push(format!("AVX-512 (any): {total}"));
+        }
+    }
+    if let Some(bits) = report.avx512_max_width {
+        let narrow = if bits < 512 {
+            " (AVX512-VL only, no zmm)"
+        } else {
+            ""
+        };
+        out.push(format!("AVX-512 width: up to {bits}-bit{narrow}"));
+    }
+    let x87 = report
+        .x87_fp
+        .map(|n| format!(", {n} x87"))
+        .unwrap_or_default();
+    if let Some(heaviness) = &report.avx512_heaviness {
+        out.push(format!(
+            "AVX-512 heaviness: {} heavy, {} light ({:.1}% heavy)",
+            heaviness.heavy, heaviness.light, heaviness.heavy_percent
+        ));
+        let num_w = heaviness
+            .top_functions
+            .values()
+            .map(|n| n.to_string().len())
+            .max()
+            .unwrap_or(0);
+        for (function, n) in &heaviness.top_functions {
+            out.push(format!("    {n:>num_w$}  {function}"));
+        }
+    }
+    out.push(format!(
+        "vectorization: {:.1}% packed ({} scalar FP, {} packed FP, {} integer vector{x87})",
+        report.vectorization_ratio * 100.0,
+        report.scalar_fp,
+        report.packed_fp,
+        report.packed_int
+    ));
+    out.push(format!("float profile: {}", report.float_profile.name()));
+    if !report.categories.is_empty() {
+        out.push(format!("categories: {}", counts(&report.categories)));
+    }
+    if let Some(mask) = &report.mask_usage {
+        let ops: usize = mask.mask_ops.values().sum();
+        out.push(format!(
+            "masking: {} masked ({} zeroing), {} mask-register ops",
+            mask.masked, mask.zero_masked, ops
+        ));
+    }
+    if let Some(evex) = &report.evex_operands {
+        out.push(format!(
+            "EVEX operands: {} broadcast, {} rounding, {} SAE",
+            evex.broadcast, evex.rounding, evex.sae
+        ));
+    }
+    if let Some(usage) = &report.gather_scatter {
+        let total: usize = usage.mnemonics.values().sum();
+        out.push(format!(
+            "gather/scatter: {total} ({})",
+            counts(&usage.mnemonics)
+        ));
+    }
+    if let Some(usage) = &report.non_temporal {
+        let total: usize = usage.mnemonics.values().sum();
+        out.push(format!(
+            "non-temporal stores: {total} ({})",
+            counts(&usage.mnemonics)
+        ));
+    }
+    if let Some(usage) = &report.prefetch {
+        let total: usize = usage.mnemonics.values().sum();
+        out.push(format!(
+            "prefetches: {total} ({})",
+            counts(&usage.mnemonics)
+        ));
+    }
+    if let Some(usage) = &report.mxcsr_access {
+        let total: usize = usage.mnemonics.values().sum();
+        out.push(format!(
+            "MXCSR access: {total} ({})",
+            counts(&usage.mnemonics)
+        ));
+    }
+    if let Some(usage) = &report.state_resets {
+        out.push(format!("state resets: {}", counts(&usage.mnemonics)));
+    }
+    if let Some(warnings) = report
+        .transition_warnings
+        .as_ref()
+        .filter(|w| !w.is_empty())
+    {
+        out.push(format!("transition warnings: {}", warnings.len()));
+        let addr_w = warnings.iter().map(|w| w.address.len()).max().unwrap_or(0);
+        for warning in warnings {
+            out.push(format!(
+                "    {:>addr_w$}  {:<13}  {} ({})",
+                warning.address,
+                warning.kind.name(),
+                warning.function,
+                warning.mnemonic
+            ));
+        }
+    }
+    if let Some(usage) = &report.x87 {
+        let total: usize = usage.mnemonics.values().sum();
+        let mut line = format!("x87: {total} ({})", counts(&usage.mnemonics));
+        for (mnemonic, isa) in &usage.also_simd {
+            line.push_str(&format!("; {mnemonic} also counted as {isa}"));
+        }
+        out.push(line);
+    }
+    let memory = &report.memory_ops;
+    if memory.aligned + memory.unaligned + memory.non_temporal > 0 {
+        out.push(format!(
+            "memory moves: {} aligned, {} unaligned, {} non-temporal ({:.1}% aligned)",
+            memory.aligned, memory.unaligned, memory.non_temporal, memory.aligned_percent
+        ));
+        if let Some(mnemonics) = &memory.mnemonics {
+            let mne_w = mnemonics.keys().map(|m| m.len()).max().unwrap_or(0);
+            for (mnemonic, n) in mnemonics {
+                out.push(format!("    {mnemonic:<mne_w$}  {n}"));
+            }
+        }
+    }
+    if let Some(functions) = report.top_functions.as_ref().filter(|f| !f.is_empty()) {
+        out.push("top functions:".to_string());
+        let num_w = functions
+            .iter()
+            .map(|f| f.total_simd_insts.to_string().len())
+            .max()
+            .unwrap_or(0);
+        for function in functions {
+            out.push(format!(
+                "    {:>num_w$}  {:>6.2}%  {}",
+                function.total_simd_insts, function.simd_percent, function.name
+            ));
+        }
+    }
+    if let Some(requirements) = report
+        .function_requirements
+        .as_ref()
+        .filter(|r| !r.is_empty())
+    {
+        out.push(format!(
+            "functions by requirement: {}",
+            counts(requirements)
+        ));
+    }
+    if let Some(functions) = report.functions.as_ref().filter(|f| !f.is_empty()) {
+        out.push("functions:".to_string());
+        let num_w = functions
+            .iter()
+            .map(|f| f.total_simd_insts.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let addr_w = functions
+            .iter()
+            .map(|f| f.address.as_deref().map_or(0, str::len))
+            .max()
+            .unwrap_or(0);
+        for function in functions {
+            out.push(format!(
+                "    {:>num_w$}  {:>6.2}%  {:>addr_w$}  {} ({})",
+                function.total_simd_insts,
+                function.simd_percent,
+                function.address.as_deref().unwrap_or(""),
+                function.name,
+                counts(&function.isa_summary)
+            ));
+        }
+    }
+    if let Some(sources) = &report.source_files {
+        out.push(format!(
+            "source files: {} with SIMD, {} instructions without line info",
+            sources.files.len(),
+            sources.unknown
+        ));
+        if let Some(note) = &sources.note {
+            out.push(format!("    {note}"));
+        }
+        let num_w = sources
+            .files
+            .iter()
+            .map(|f| f.total_simd_insts.to_string().len())
+            .max()
+            .unwrap_or(0);
+        for file in &sources.files {
+            out.push(format!(
+                "    {:>num_w$}  {} ({})",
+                file.total_simd_insts,
+                file.file,
+                counts(&file.isa_summary)
+            ));
+        }
+    }
+    if !report.excluded.is_empty() {
+        let total: usize = report.excluded.iter().map(|e| e.instructions).sum();
+        let names: Vec<String> = report
+            .excluded
+            .iter()
+            .map(|e| match e.rule {
+                ExclusionRule::SuspectedData => format!("{} {} as data", e.name, e.instructions),
+                _ => format!("{} {}", e.name, e.instructions),
+            })
+            .collect();
+        out.push(format!(
+            "excluded: {total} instructions ({})",
+            names.join(", ")
+        ));
+    }
+    if let Some(runtime) = &report.runtime_library {
+        out.push(format!(
+            "runtime library: {} SIMD of {} instructions in {} routines, not counted above{}{}",
+            runtime.total_simd_insts,
+            runtime.total_insts,
+            runtime.routines.len(),
+            if runtime.isa_summary.is_empty() {
+                ""
+            } else {
+                ": "
+            },
+            counts(&runtime.isa_summary)
+        ));
+    }
+    if let Some(meta) = &report.meta {
+        out.push(meta.summary());
+    }
+
+    out.join("\n")
+}
@@ -0,0 +1,71 @@
+//! User-supplied Handlebars templates. The template sees every report field at
+//! the top level (for the common single-binary case) plus a `reports` array,
+//! and an `errors` array of `{binary, error}` for the binaries that failed to
+//! scan.
+
+use crate::report::{Report, ScanError};
+use anyhow::{Context, Result};
+use handlebars::Handlebars;
+use std::path::Path;
+
+const BUILTIN_PREFIX: &str = "builtin:";
+
+const BUILTINS: &[(&str, &str)] = &[
+    ("oneline", include_str!("templates/oneline.hbs")),
+    ("markdown", include_str!("templates/markdown.hbs")),
+];
+
+/// A loaded template; `name` is used in error messages.
+pub struct Template {
+    pub name: String,
+    pub source: String,
+}
+
+impl Template {
+    /// Load `builtin:NAME` or a template file path.
+    pub fn load(spec: &str) -> Result<Self> {
+        if let Some(name) = spec.strip_prefix(BUILTIN_PREFIX) {
+            let (_, source) = BUILTINS
+                .iter()
+                .find(|(builtin, _)| *builtin == name)
+                .with_context(|| {
+                    let names: Vec<_> = BUILTINS.iter().map(|(n, _)| *n).collect();
+                    format!(
+                        "Unknown builtin template '{}' (available: {})",
+                        name,
+                        names.join(", ")
+                    )
+                })?;
+            return Ok(Template {
+                name: spec.to_string(),
+                source: source.to_string(),
+            });
+        }
+
+        let source = std::fs::read_to_string(Path::new(spec))
+            .with_context(|| format!("Failed to read template '{spec}'"))?;
+        Ok(Template {
+            name: spec.to_string(),
+            source,
+        })
+    }
+}
+
+pub fn render(template: &Template, reports: &[Report], errors: &[ScanError]) -> Result<String> {
+    let mut registry = Handlebars::new();
+    registry.register_escape_fn(handlebars::no_escape);
+    registry
+        .register_template_string(&template.name, &template.source)
+        .map_err(|err| anyhow::anyhow!("{err}"))?;
+
+    let mut data = match reports.first() {
+        Some(report) => serde_json::to_value(report)?,
+        None => serde_json::Value::Object(Default::default()),
+    };
+    data["reports"] = serde_json::to_value(reports)?;
+    data["errors"] = serde_json::to_value(errors)?;
+
+    registry
+        .render(&template.name, &data)
+        .map_err(|err| anyhow::anyhow!("{err}"))
+}
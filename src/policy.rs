@@ -0,0 +1,139 @@
+use crate::isa;
+use crate::report::IsaDetail;
+use indexmap::IndexMap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How many of the most frequent mnemonics to quote in a failed check.
+const TOP_OFFENDERS: usize = 3;
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PolicyRule {
+    FailIfFound,
+    FailIfMissing,
+}
+
+impl PolicyRule {
+    pub fn flag(self) -> &'static str {
+        match self {
+            PolicyRule::FailIfFound => "fail-if-found",
+            PolicyRule::FailIfMissing => "fail-if-missing",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct PolicyCheck {
+    pub rule: PolicyRule,
+    /// ISA key, or a category name such as `gather/scatter` or `non-temporal`
+    pub isa: String,
+    pub passed: bool,
+    pub count: usize,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub top_mnemonics: IndexMap<String, usize>,
+}
+
+/// `name` lowercased without `-`, `_` or spaces, so `avx512` finds `AVX-512`
+/// and `avx512bw` finds `AVX512-BW`.
+fn loose(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, '-' | '_' | ' '))
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Resolve user-supplied ISA names case-insensitively against the known ISA
+/// keys, ignoring `-`, `_` and spaces when no key matches as written.
+pub fn resolve_isas<'a>(
+    names: &[String],
+    known: impl Iterator<Item = &'a str> + Clone,
+) -> anyhow::Result<Vec<String>> {
+    names
+        .iter()
+        .map(|name| {
+            known
+                .clone()
+                .find(|isa| isa.eq_ignore_ascii_case(name))
+                .or_else(|| known.clone().find(|isa| loose(isa) == loose(name)))
+                .map(str::to_string)
+                .ok_or_else(|| {
+                    let mut valid: Vec<_> = known.clone().collect();
+                    valid.sort_unstable();
+                    anyhow::anyhow!(
+                        "Unknown ISA '{}' (expected one of: {})",
+                        name,
+                        valid.join(", ")
+                    )
+                })
+        })
+        .collect()
+}
+
+/// Evaluate `--fail-if-found` / `--fail-if-missing` rules against one binary's counts.
+/// Rules naming an ISA count `isa_summary`; rules naming one of
+/// [`isa::policy_categories`] count the mnemonics in `categories`.
+/// Checks come out in flag order, found rules before missing rules.
+pub fn evaluate(
+    isa_summary: &IndexMap<String, usize>,
+    details: &IndexMap<String, IsaDetail>,
+    categories: &HashMap<String, HashMap<String, usize>>,
+    fail_if_found: &[String],
+    fail_if_missing: &[String],
+) -> Vec<PolicyCheck> {
+    let found = fail_if_found
+        .iter()
+        .map(|isa| (PolicyRule::FailIfFound, isa));
+    let missing = fail_if_missing
+        .iter()
+        .map(|isa| (PolicyRule::FailIfMissing, isa));
+
+    let category_names = isa::policy_categories();
+    let none = HashMap::new();
+    found
+        .chain(missing)
+        .map(|(rule, isa)| {
+            let category = category_names
+                .contains(&isa.as_str())
+                .then(|| categories.get(isa).unwrap_or(&none));
+            let count = match category {
+                Some(mnemonics) => mnemonics.values().sum(),
+                None => isa_summary
+                    .iter()
+                    .filter(|(key, _)| isa::covers(isa, key))
+                    .map(|(_, count)| count)
+                    .sum(),
+            };
+            let passed = match rule {
+                PolicyRule::FailIfFound => count == 0,
+                PolicyRule::FailIfMissing => count > 0,
+            };
+            let top_mnemonics = if rule == PolicyRule::FailIfFound && !passed {
+                let mut occurrences: Vec<(&String, usize)> = match category {
+                    Some(mnemonics) => mnemonics.iter().map(|(m, n)| (m, *n)).collect(),
+                    None => details
+                        .iter()
+                        .filter(|(key, _)| isa::covers(isa, key))
+                        .flat_map(|(_, detail)| detail.occurrences.iter().map(|(m, n)| (m, *n)))
+                        .collect(),
+                };
+                occurrences.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+                occurrences
+                    .into_iter()
+                    .take(TOP_OFFENDERS)
+                    .map(|(m, n)| (m.clone(), n))
+                    .collect()
+            } else {
+                IndexMap::new()
+            };
+            PolicyCheck {
+                rule,
+                isa: isa.clone(),
+                passed,
+                count,
+                top_mnemonics,
+            }
+        })
+        .collect()
+}
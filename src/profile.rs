@@ -0,0 +1,139 @@
+//! `--profile` / `--perf-data`: sample counts per instruction address, to
+//! weight the static counts by what actually ran.
+//!
+//! `--profile` reads `address,count` lines, e.g. from
+//! `perf script -F ip | sort | uniq -c | awk '{print $2 "," $1}'`.
+//! `--perf-data` runs `perf script` itself and keeps the samples whose DSO has
+//! the binary's file name. Runtime addresses of a PIE or shared library are
+//! rebased by the load bias: `--load-address`, or with `--perf-data` the
+//! start of the binary's executable mapping minus its file offset (which is
+//! where GNU ld and lld put the text segment).
+
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+lazy_static! {
+    // `... PERF_RECORD_MMAP2 1234/1234: [0x55d1c2a0a000(0x1000) @ 0x1000 fd:01 5678 0]: r-xp /usr/bin/prog`
+    // (`PERF_RECORD_MMAP` has `@ 0x1000]: x /usr/bin/prog`)
+    static ref MMAP_RE: Regex = Regex::new(
+        r"PERF_RECORD_MMAP2? .*\[0x([0-9a-f]+)\(0x[0-9a-f]+\) @ (?:0x)?([0-9a-f]+)[^\]]*\]:\s+(\S+)\s+(.+)$"
+    )
+    .unwrap();
+    // `    55d1c2a0b136 (/usr/bin/prog)`
+    static ref SAMPLE_RE: Regex = Regex::new(r"^\s*([0-9a-f]+)\s+\((.+)\)$").unwrap();
+}
+
+/// Samples per instruction address, already rebased to the addresses objdump
+/// prints.
+pub struct Profile {
+    /// The `--profile` or `--perf-data` path
+    pub source: String,
+    pub samples: HashMap<u64, usize>,
+    /// Every sample read (for the binary, with `--perf-data`)
+    pub total: usize,
+    /// The load bias subtracted from each address, if any
+    pub load_address: Option<u64>,
+}
+
+impl Profile {
+    fn new(source: &Path, load_address: Option<u64>) -> Self {
+        Profile {
+            source: source.display().to_string(),
+            samples: HashMap::new(),
+            total: 0,
+            load_address,
+        }
+    }
+
+    fn add(&mut self, address: u64, count: usize) {
+        self.total += count;
+        // Below the bias means another mapping; it can't match anything
+        if let Some(address) = address.checked_sub(self.load_address.unwrap_or(0)) {
+            *self.samples.entry(address).or_insert(0) += count;
+        }
+    }
+}
+
+/// Read an `address,count` file. Addresses are hex (`0x` optional); blank
+/// lines, `#` comments and a header line are skipped.
+pub fn read_csv(path: &Path, load_address: Option<u64>) -> Result<Profile> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read profile '{}'", path.display()))?;
+    let mut profile = Profile::new(path, load_address);
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let parsed = line.split_once(',').and_then(|(address, count)| {
+            Some((
+                crate::parse_address(address.trim()).ok()?,
+                count.trim().parse().ok()?,
+            ))
+        });
+        match parsed {
+            Some((address, count)) => profile.add(address, count),
+            None if number == 0 => continue,
+            None => anyhow::bail!(
+                "{}:{}: expected `address,count`, got '{line}'",
+                path.display(),
+                number + 1
+            ),
+        }
+    }
+    Ok(profile)
+}
+
+/// Run `perf script` on `perf_data` and count the samples in `binary`. The
+/// load bias is read from the mmap events when `relocatable` and no
+/// `load_address` is given.
+pub fn read_perf(
+    perf_data: &Path,
+    binary: &Path,
+    relocatable: bool,
+    load_address: Option<u64>,
+) -> Result<Profile> {
+    let output = Command::new("perf")
+        .arg("script")
+        .arg("-i")
+        .arg(perf_data)
+        .args(["-F", "ip,dso", "--show-mmap-events"])
+        .output()
+        .context("Failed to execute perf")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "perf script failed on '{}': {}",
+            perf_data.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let name = binary.file_name();
+    let is_binary = |dso: &str| Path::new(dso).file_name() == name;
+
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut bias = load_address;
+    let mut addresses: Vec<u64> = Vec::new();
+    for line in text.lines() {
+        if let Some(captures) = MMAP_RE.captures(line) {
+            if relocatable && bias.is_none() && captures[3].contains('x') && is_binary(&captures[4])
+            {
+                let start = u64::from_str_radix(&captures[1], 16).unwrap_or(0);
+                let offset = u64::from_str_radix(&captures[2], 16).unwrap_or(0);
+                bias = Some(start.saturating_sub(offset));
+            }
+        } else if let Some(captures) = SAMPLE_RE.captures(line) {
+            if is_binary(&captures[2]) {
+                addresses.extend(u64::from_str_radix(&captures[1], 16).ok());
+            }
+        }
+    }
+    let mut profile = Profile::new(perf_data, bias);
+    for address in addresses {
+        profile.add(address, 1);
+    }
+    Ok(profile)
+}
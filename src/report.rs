@@ -0,0 +1,1643 @@
+use crate::binary_info::BinaryInfo;
+use crate::classify::{FunctionCounts, Weighted};
+use crate::isa::{self, MemoryAccess};
+use crate::loops::{LoopSplit, LoopSummary};
+use crate::policy::PolicyCheck;
+use crate::profile::Profile;
+use crate::source::SourceFiles;
+use crate::transitions::TransitionWarning;
+use indexmap::IndexMap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Version of the report layout. Bump whenever a field is added, removed,
+/// renamed, or changes meaning, so consumers can tell layouts apart.
+pub const SCHEMA_VERSION: u32 = 64;
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Report {
+    pub schema_version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+    pub binary: String,
+    pub binary_info: BinaryInfo,
+    pub has_simd: bool,
+    /// Whether any packed floating-point or integer-vector instruction was found;
+    /// scalar `ss`/`sd` arithmetic alone doesn't count
+    pub has_packed_simd: bool,
+    pub isa_summary: IndexMap<String, usize>,
+    /// Scalar GPR extension counts: ABM (popcnt/lzcnt) and CRC32 whenever
+    /// found, BMI1/BMI2/MOVNTI with `--include-scalar-ext`. Not SIMD, so never part of
+    /// `isa_summary` or `total_simd_insts`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scalar_extensions: Option<IndexMap<String, usize>>,
+    /// Counts of the non-vector extensions enabled with `--extensions` or a
+    /// policy flag (RDRAND, TSX, CET, ...), present when any group is
+    /// enabled. Never part of `isa_summary` or `has_simd`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub other_extensions: Option<IndexMap<String, usize>>,
+    /// Keys of `isa_summary` for discontinued extensions (FMA4, XOP, AVX512-PF)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub legacy_isas: Vec<String>,
+    /// `emms` count, present whenever MMX was found. MMX code that never
+    /// runs `emms` leaves the x87 stack unusable for later float code.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mmx_emms: Option<usize>,
+    /// "AVX-512 (any)": every AVX-512 subset in `isa_summary` added up, so
+    /// consumers of the old single `AVX-512` key have one number to check
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avx512_any: Option<usize>,
+    /// AVX-512 instructions by frequency license, present with
+    /// `--avx512-heaviness`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avx512_heaviness: Option<Avx512Heaviness>,
+    pub total_simd_insts: usize,
+    /// Every decoded instruction, SIMD or not
+    pub total_insts: usize,
+    /// `total_simd_insts` as a percentage of `total_insts`, to two decimals
+    pub simd_percent: f64,
+    /// Encoded size in bytes of the `total_simd_insts` instructions
+    pub simd_bytes: usize,
+    /// Size in bytes of the scanned executable sections per their headers,
+    /// padding and embedded data included, whatever `--function`, `--entry`
+    /// or `--start` narrowed the scan to. Absent when the file has no section
+    /// headers objdump's names match (archives, unknown formats)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code_bytes: Option<u64>,
+    /// `simd_bytes` as a percentage of `code_bytes`, to two decimals. Above
+    /// `simd_percent` when the SIMD is mostly VEX/EVEX, whose encodings are
+    /// longer than the average instruction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub simd_bytes_percent: Option<f64>,
+    /// SIMD instructions per KiB of `code_bytes`, to two decimals, for
+    /// comparing binaries of different sizes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub simd_per_kb: Option<f64>,
+    /// Per-ISA counts with their share of SIMD and of all instructions
+    pub isa_breakdown: IndexMap<String, IsaShare>,
+    /// ISAs left out of `isa_summary`, `isa_breakdown` and `isa_details` by
+    /// `--min-count` / `--min-percent`. `total_simd_insts`, `has_simd`, the
+    /// required level and policy checks still count them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suppressed: Option<Suppressed>,
+    /// The `--only-isa` / `--exclude-isa` filter, when given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub isa_filter: Option<IsaFilter>,
+    /// SIMD inside loop bodies vs straight-line code, with
+    /// `--loop-heuristic` (see `loops` for how loops are found)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loops: Option<LoopSummary>,
+    /// Static counts weighted by a sample profile, with `--profile` or
+    /// `--perf-data`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weighted_counts: Option<WeightedCounts>,
+    /// Multiversioned functions, when the binary has any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dispatch: Option<Dispatch>,
+    /// SIMD counts in functions only reached after a CPUID check, with
+    /// `--dispatch-analysis` (see `dispatch_guards`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guarded_isas: Option<IndexMap<String, usize>>,
+    /// SIMD counts everywhere else, with `--dispatch-analysis`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unconditional_isas: Option<IndexMap<String, usize>>,
+    /// The CPUID checks `guarded_isas` rests on, with `--dispatch-analysis`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dispatch_guards: Option<DispatchGuards>,
+    /// Shared libraries the binary links against, in link order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<Dependency>,
+    /// Rust functions built for specific CPU features, grouped by the
+    /// function they are clones of, when the binary has any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feature_clones: Option<FeatureClones>,
+    /// Minimum x86-64 psABI level (1-4) needed to run the binary, e.g. 3 for x86-64-v3
+    pub required_level: u8,
+    pub min_cpu: MinCpu,
+    /// The ELF `.note.gnu.property` x86 bits checked against the
+    /// instructions found, for ELF files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gnu_property: Option<GnuProperty>,
+    /// `--target-cpu` / `--target-features`: what would fault on the target
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compatibility: Option<Compatibility>,
+    /// Mnemonics found that no psABI level guarantees (e.g. AMD SSE4a)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unleveled_mnemonics: Vec<String>,
+    pub register_widths: RegisterWidths,
+    /// Opmask usage, present when the binary masks anything
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mask_usage: Option<MaskUsage>,
+    /// Broadcast, rounding and SAE decorations, present when any were seen
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub evex_operands: Option<EvexOperands>,
+    /// SIMD instructions by kind of operation (`arithmetic`, `shuffle`, `fma`,
+    /// `load/store`, ...), most frequent first
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub categories: IndexMap<String, usize>,
+    /// Whether any gather or scatter (including the AVX512-PF prefetch forms)
+    /// was found
+    pub uses_gather_scatter: bool,
+    /// Gathers and scatters, present when any were found. They are often
+    /// slower than the scalar loop they replace on pre-Skylake cores.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gather_scatter: Option<AttributedUsage>,
+    /// Whether any non-temporal store (`movntps`, `movnti`, `maskmovdqu`, ...)
+    /// was found
+    pub uses_non_temporal: bool,
+    /// Non-temporal stores, present when any were found. They bypass the
+    /// cache, evicting nothing but also leaving nothing behind for the next read.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub non_temporal: Option<AttributedUsage>,
+    /// Software prefetches (`prefetcht0`, `prefetchnta`, `prefetchw`, the
+    /// AVX512-PF `vgatherpf0dps`, ...), present when any were found. Only the
+    /// AVX512-PF forms count as SIMD, but hand-tuned kernels tend to have all
+    /// of them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefetch: Option<AttributedUsage>,
+    /// Whether the binary loads MXCSR (`ldmxcsr`, `vldmxcsr`). Setting
+    /// flush-to-zero or denormals-are-zero that way changes float results
+    /// for the whole thread, including code in other libraries.
+    pub modifies_mxcsr: bool,
+    /// MXCSR loads and stores, present when any were found. They stay
+    /// counted under SSE/AVX as well.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mxcsr_access: Option<AttributedUsage>,
+    /// `vzeroupper`, `vzeroall` and `emms`, present when any were found
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_resets: Option<AttributedUsage>,
+    /// Functions mixing register states without a reset in between, in
+    /// disassembly order; present with `--check-transitions`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transition_warnings: Option<Vec<TransitionWarning>>,
+    /// x87 instructions, present with `--include-x87` when any were found.
+    /// Not SIMD, so never part of `isa_summary` or `has_simd`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x87: Option<X87Usage>,
+    /// Instructions left out of every count (PLT, linker stubs and suspected
+    /// data by default, plus `--exclude-function`), in disassembly order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub excluded: Vec<Exclusion>,
+    /// glibc, libmvec and compiler runtime routines (`__memmove_avx_unaligned_erms`,
+    /// `__strlen_evex`, ...), present when any were found. Their instructions
+    /// are in no other count, the required level and `min_cpu` included,
+    /// unless `--no-split-runtime`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runtime_library: Option<RuntimeLibrary>,
+    /// Counts per disassembled section, e.g. `.text`, `.plt`, `.init`
+    pub sections: IndexMap<String, SectionCounts>,
+    /// Sections the `--sections`, `--exclude-sections` or `--text-only`
+    /// filters let through, including any without instructions; present
+    /// when one of them is given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scanned_sections: Option<Vec<String>>,
+    pub diagnostics: Diagnostics,
+    /// Vector-looking mnemonics missing from every ISA table, most frequent first
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub unclassified: IndexMap<String, usize>,
+    pub memory_ops: MemoryOps,
+    /// Scalar floating-point SIMD instructions (`addss`, `vmulsd`, ...)
+    pub scalar_fp: usize,
+    /// Packed floating-point SIMD instructions (`addps`, `vmulpd`, ...)
+    pub packed_fp: usize,
+    /// Integer vector instructions (`paddd`, `vpshufb`, ...)
+    pub packed_int: usize,
+    /// x87 instructions (`fld`, `faddp`, ...), present with `--include-x87`.
+    /// Not part of `vectorization_ratio`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x87_fp: Option<usize>,
+    /// Packed instructions as a fraction (0-1) of scalar plus packed
+    pub vectorization_ratio: f64,
+    /// How the binary does floating point, which explains a false `has_simd`
+    pub float_profile: FloatProfile,
+    /// Widest vector register seen, in bits (128, 256, or 512)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_vector_width: Option<u16>,
+    /// Widest vector register among the classified SIMD instructions, in
+    /// bits. Unlike `max_vector_width`, unknown mnemonics don't count
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_vector_width_used: Option<u16>,
+    /// Widest register any AVX-512 instruction used, in bits. 256 or less
+    /// means AVX512-VL only (e.g. `-mprefer-vector-width=256`), which avoids
+    /// the 512-bit frequency penalties
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avx512_max_width: Option<u16>,
+    /// Instructions per register width for each ISA, with AVX512-VL counted
+    /// under AVX512-F so its 128/256/512-bit use shows together
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub vector_widths: IndexMap<String, RegisterWidths>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub isa_details: Option<IndexMap<String, IsaDetail>>,
+    /// Functions with the most SIMD instructions, present with `--show-functions`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_functions: Option<Vec<FunctionUsage>>,
+    /// Every function with SIMD (or every function, with `--per-function=all`),
+    /// most SIMD first, present with `--per-function`. Unlike `top_functions`,
+    /// `.cold` and `.part.N` splits are counted under the function they came
+    /// from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub functions: Option<Vec<FunctionUsage>>,
+    /// How many functions require each ISA, i.e. use it as their newest
+    /// (`requires` in `functions`), oldest ISA first; present with
+    /// `--per-function`. Counts every function with SIMD, whatever the list
+    /// filters keep
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function_requirements: Option<IndexMap<String, usize>>,
+    /// SIMD counts per source file, present with `--by-source`. Inlined code
+    /// counts under the file it was inlined from, as `objdump -l` shows it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_files: Option<SourceFiles>,
+    /// Demangled names of the symbols `--function` matched, in disassembly
+    /// order; present with `--function`. Every count covers only these
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matched_functions: Option<Vec<String>>,
+    /// Addresses classified, present with `--start` or `--end`. Every count
+    /// covers only these
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address_range: Option<AddressRange>,
+    /// Functions reachable from `--entry`, present with it. Every count
+    /// covers only these
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reachability: Option<Reachability>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub policy: Vec<PolicyCheck>,
+    /// Timing and throughput, present with `--stats`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stats: Option<Stats>,
+}
+
+/// Why instructions were left out of every count.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExclusionRule {
+    /// A `.plt*` section (off with `--no-default-excludes`)
+    DefaultSection,
+    /// A dynamic-linker or C runtime stub such as `_dl_runtime_resolve_xsavec`
+    /// or `__x86.get_pc_thunk.bx` (off with `--no-default-excludes`)
+    DefaultStub,
+    /// A symbol matching `--exclude-function`
+    ExcludeFunction,
+    /// Bytes in an executable section that look like data, e.g. a jump
+    /// table, decoded as instructions (see `text_data`; off with
+    /// `--no-default-excludes`)
+    SuspectedData,
+}
+
+/// Instructions skipped under one rule in one section or symbol.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Exclusion {
+    pub rule: ExclusionRule,
+    /// Section name for `default-section`, demangled symbol otherwise
+    /// (`<unattributed>` for `suspected-data` outside any symbol)
+    pub name: String,
+    /// The `--exclude-function` regex that matched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    pub instructions: usize,
+}
+
+/// SIMD in the runtime library routines (see `runtime`).
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct RuntimeLibrary {
+    pub total_simd_insts: usize,
+    /// Every decoded instruction in the routines, SIMD or not
+    pub total_insts: usize,
+    pub isa_summary: IndexMap<String, usize>,
+    /// Routines with SIMD, most first
+    pub routines: Vec<FunctionUsage>,
+}
+
+/// Declared x86 properties (see `gnu_property`) next to the observed ones.
+/// The `isa_*` and `features_*` fields are present when the note has them.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct GnuProperty {
+    /// Whether the file has a GNU property note
+    pub present: bool,
+    /// psABI levels the toolchain says the code uses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub isa_used: Option<Vec<String>>,
+    /// psABI levels the code says it needs to run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub isa_needed: Option<Vec<String>>,
+    /// Register state used: `x87`, `mmx`, `xmm`, `ymm`, `zmm`, `mask`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub features_used: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub features_needed: Option<Vec<String>>,
+    /// psABI level of every instruction found, guarded dispatch variants and
+    /// `--assume-dispatch` notwithstanding
+    pub observed_level: u8,
+    /// Register state the instructions found use, named as in `features_used`
+    pub observed_features: Vec<String>,
+    pub mismatches: Vec<PropertyMismatch>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum MismatchKind {
+    /// The note claims more than the code has: a level above the one found,
+    /// or register state never touched
+    DeclaredNotObserved,
+    /// The code has more than the note claims, e.g. hand-written assembly
+    /// the assembler's tracking missed
+    ObservedNotDeclared,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct PropertyMismatch {
+    /// `isa_used`, `isa_needed`, `features_used` or `features_needed`
+    pub property: String,
+    pub kind: MismatchKind,
+    /// The level or feature in question
+    pub what: String,
+}
+
+/// Instructions found that the `--target-cpu` / `--target-features` /
+/// `--check-cpu` target lacks a CPUID feature for, i.e. that would raise #UD
+/// there.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Compatibility {
+    /// The microarchitecture or psABI level, `features` for
+    /// `--target-features` or `host` for `--check-cpu`
+    pub target: String,
+    /// CPUID features the target has
+    pub target_features: Vec<String>,
+    /// No faulting instruction outside guarded dispatch variants, and none
+    /// at all without `--assume-dispatch` or `--check-cpu`
+    pub compatible: bool,
+    /// CPUID features the faulting instructions need that the target lacks
+    pub missing_features: Vec<String>,
+    pub faulting_insts: usize,
+    /// Per ISA key, most faulting instructions first
+    pub isas: IndexMap<String, FaultingIsa>,
+    /// Faulting instructions per function, most first
+    pub functions: IndexMap<String, usize>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct FaultingIsa {
+    pub count: usize,
+    /// Of `count`, those inside guarded dispatch variants
+    pub in_dispatch_variants: usize,
+    pub missing_features: Vec<String>,
+    /// The first `--target-addresses` of them, each with `symbol+0xoffset`
+    pub addresses: Vec<InstAddress>,
+    /// Faulting instructions of this ISA per function, most first
+    pub functions: IndexMap<String, usize>,
+}
+
+/// `--dispatch-analysis` (see `guards`).
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct DispatchGuards {
+    /// Functions that check CPU features, in disassembly order
+    pub checkers: Vec<CpuChecker>,
+    /// Functions only called or referenced by checkers and other guarded
+    /// functions, guarded dispatch variants included, in disassembly order
+    pub guarded_functions: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct CpuChecker {
+    pub function: String,
+    pub cpuid: usize,
+    pub xgetbv: usize,
+    /// CPU feature caches it reads or initializers it calls (`__cpu_model`,
+    /// `__cpu_indicator_init`, ...)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub feature_reads: Vec<String>,
+    /// An IFUNC or multiversioning resolver
+    pub resolver: bool,
+}
+
+/// Multiversioned functions (see `dispatch`) and what their variants use.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Dispatch {
+    /// Functions with ISA-specific variants, in disassembly order
+    pub functions: Vec<DispatchGroup>,
+    /// ISAs found only inside guarded variants
+    pub guarded_isas: Vec<String>,
+    /// psABI level needed by the code outside guarded variants
+    pub baseline_level: u8,
+    /// Whether `required_level` and `min_cpu` leave guarded variants out
+    /// (`--assume-dispatch`)
+    pub assumed: bool,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct DispatchGroup {
+    /// Demangled name of the dispatched function
+    pub function: String,
+    pub variants: Vec<DispatchVariant>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct DispatchVariant {
+    /// Demangled symbol name
+    pub symbol: String,
+    /// What the variant was built for, e.g. `avx2`, `arch_skylake_avx512`,
+    /// `default`, or `resolver`
+    pub target: String,
+    /// Whether only CPUs that passed the resolver's check run it
+    pub guarded: bool,
+    pub total_simd_insts: usize,
+    pub isa_summary: IndexMap<String, usize>,
+}
+
+/// One shared library from `DT_NEEDED`, `LC_LOAD_DYLIB` or the PE imports.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Dependency {
+    /// As the file names it: a soname, install name or DLL name
+    pub name: String,
+    /// What kind of known SIMD-heavy library it is (`BLAS/LAPACK`,
+    /// `vector math`, `FFT`, ...), when it is one: the binary's vector
+    /// work may happen there
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub simd_provider: Option<String>,
+}
+
+/// Rust `#[target_feature]` and `multiversion` clones (see `clones`).
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct FeatureClones {
+    /// In disassembly order of their first clone
+    pub groups: Vec<CloneGroup>,
+    /// Clones with `flagged` set
+    pub flagged: usize,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct CloneGroup {
+    /// Demangled path of the function, without the feature part
+    pub function: String,
+    /// Its clones, then the function itself if it has a symbol
+    pub clones: Vec<FeatureClone>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct FeatureClone {
+    /// Demangled symbol name
+    pub symbol: String,
+    /// Feature names in the symbol, e.g. `avx2` and `fma`; empty for the
+    /// function itself
+    pub features: Vec<String>,
+    /// ISA keys those names claim (scalar features like `bmi2` claim none)
+    pub claimed_isas: Vec<String>,
+    pub total_simd_insts: usize,
+    pub isa_summary: IndexMap<String, usize>,
+    /// Claimed ISAs with no instruction in the clone
+    pub missing_isas: Vec<String>,
+    /// No instruction from a claimed ISA or one it implies: likely baseline
+    /// code the feature attribute never reached
+    pub flagged: bool,
+}
+
+/// The counts weighted by profile samples (`--profile` / `--perf-data`):
+/// each instruction counts once per sample at its address.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct WeightedCounts {
+    /// The profile file
+    pub profile: String,
+    /// Load bias subtracted from the profile's addresses, in hex
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub load_address: Option<String>,
+    /// Samples read (with `--perf-data`, those in this binary)
+    pub total_samples: usize,
+    /// Samples on a classified instruction; the rest fell outside the
+    /// disassembly, on skipped code, or on a wrong load address
+    pub matched_samples: usize,
+    pub simd_samples: usize,
+    /// `simd_samples` as a percentage of `matched_samples`
+    pub simd_percent: f64,
+    /// Samples on AVX-512 instructions as a percentage of `matched_samples`
+    pub avx512_percent: f64,
+    pub isa_summary: IndexMap<String, usize>,
+    /// Per-ISA samples with their share of SIMD and of all matched samples
+    pub isa_breakdown: IndexMap<String, IsaShare>,
+    /// SIMD samples by operation category, most first
+    pub categories: IndexMap<String, usize>,
+}
+
+/// `--only-isa` / `--exclude-isa`: the ISA keys the per-ISA parts of the
+/// report (`isa_summary`, `isa_breakdown`, `isa_details`, `vector_widths`,
+/// `loops.by_isa`, `weighted_counts` and the function listings) keep.
+/// `has_simd`, the required level, `min_cpu` and policy checks still see
+/// every ISA.
+#[derive(Serialize, Deserialize, JsonSchema, Default, Clone)]
+pub struct IsaFilter {
+    /// Keys to keep; every key when empty. `AVX-512` and `AMX` cover their
+    /// subsets, as in `--fail-if-found`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub only: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+    /// Whether `total_simd_insts` and `simd_percent`, overall and per
+    /// function, count only the kept ISAs (`--recompute-totals`)
+    pub recomputed_totals: bool,
+}
+
+impl IsaFilter {
+    pub fn is_empty(&self) -> bool {
+        self.only.is_empty() && self.exclude.is_empty()
+    }
+
+    pub fn keeps(&self, isa: &str) -> bool {
+        (self.only.is_empty() || self.only.iter().any(|key| isa::covers(key, isa)))
+            && !self.exclude.iter().any(|key| isa::covers(key, isa))
+    }
+
+    pub fn retain<V>(&self, counts: &mut IndexMap<String, V>) {
+        counts.retain(|isa, _| self.keeps(isa));
+    }
+}
+
+/// ISAs under the `--min-count` / `--min-percent` thresholds.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Suppressed {
+    pub total: usize,
+    pub isas: IndexMap<String, usize>,
+}
+
+/// The effective `--start`/`--end` range, addresses in hex.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct AddressRange {
+    pub start: String,
+    /// Exclusive: `--end`, or without it the next symbol after `start`.
+    /// Absent when the scan ran to the end of the disassembly
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<String>,
+    /// Demangled names of the symbols with instructions in the range
+    pub symbols: Vec<String>,
+}
+
+/// The `--entry` call graph walk (see `callgraph`).
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Reachability {
+    pub entry: String,
+    /// Functions reachable through direct calls and tail jumps, the entry
+    /// included
+    pub reachable_functions: usize,
+    /// Their demangled names, in disassembly order
+    pub functions: Vec<String>,
+    /// Calls through a register or memory operand in reachable functions,
+    /// whose targets were not followed; what they reach is missing from the
+    /// counts
+    pub indirect_calls: Vec<CallSite>,
+    /// Calls into IFUNC resolvers and guarded multiversioned variants, not
+    /// followed since CPUID picks what runs
+    pub dispatched_calls: Vec<CallSite>,
+    /// Callees outside the binary (PLT imports, `-fno-plt` GOT calls)
+    pub external_calls: Vec<String>,
+}
+
+/// A call the `--entry` walk didn't follow.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct CallSite {
+    /// Demangled caller
+    pub function: String,
+    /// Hex
+    pub address: String,
+    /// The operand as objdump printed it, or the dispatcher's name
+    pub target: String,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Stats {
+    /// Wall-clock time spent waiting on the disassembler child process
+    pub disassemble_ms: f64,
+    /// Wall-clock time spent parsing and classifying its output
+    pub classify_ms: f64,
+    /// Lines of disassembler output processed
+    pub lines: usize,
+    pub lines_per_sec: f64,
+    /// Peak resident set size of simdscan itself (not the disassembler), where
+    /// the platform exposes it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peak_rss_bytes: Option<u64>,
+}
+
+/// Provenance of a report: which tool, backend, and flags produced it.
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+pub struct Meta {
+    pub simdscan_version: String,
+    /// RFC 3339 UTC time the scan started
+    pub timestamp: String,
+    pub disassembler: Disassembler,
+    /// Command line as invoked, including the program name
+    pub args: Vec<String>,
+    /// `--reclassify` overrides: mnemonic -> the category it was counted under
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub reclassified: IndexMap<String, String>,
+    /// `--ignore-mnemonic` and the table file's `ignore_mnemonics`: mnemonic
+    /// globs left out of every count
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignored_mnemonics: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+pub struct Disassembler {
+    pub name: String,
+    /// First line of `--version` output, if it could be obtained
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+impl Meta {
+    /// One-line description for human-readable formats.
+    pub fn summary(&self) -> String {
+        format!(
+            "simdscan {}, {}, {}",
+            self.simdscan_version,
+            self.disassembler.describe(),
+            self.timestamp
+        )
+    }
+}
+
+impl Disassembler {
+    /// The version banner if known, else just the backend name.
+    pub fn describe(&self) -> &str {
+        self.version.as_deref().unwrap_or(&self.name)
+    }
+}
+
+impl Report {
+    /// Whether any `--fail-if-*` check failed for this binary.
+    pub fn violates_policy(&self) -> bool {
+        self.policy.iter().any(|check| !check.passed)
+    }
+
+    /// Whether `--target-cpu` / `--target-features` found instructions the
+    /// target lacks.
+    pub fn incompatible(&self) -> bool {
+        self.compatibility
+            .as_ref()
+            .is_some_and(|compatibility| !compatibility.compatible)
+    }
+}
+
+/// The output for several binaries (`simdscan a.so b.so`): each one's report
+/// or error in argument order, and their counts added up. A single binary
+/// still gets a plain `Report`.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct MultiReport {
+    pub schema_version: u32,
+    pub reports: Vec<ReportEntry>,
+    pub aggregate: Aggregate,
+}
+
+/// Any document `simdscan schema` describes: the report for one binary, the
+/// one for several, or an NDJSON line for a binary that failed or for what
+/// `--recursive` passed over.
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum Document {
+    Report(Box<Report>),
+    Multi(Box<MultiReport>),
+    Error(ScanError),
+    Skipped(SkippedLine),
+}
+
+/// One binary of a [`MultiReport`].
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum ReportEntry {
+    Report(Box<Report>),
+    Error(ScanError),
+}
+
+/// A binary that could not be scanned, as its NDJSON error line has it.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct ScanError {
+    pub binary: String,
+    pub error: String,
+}
+
+/// The last NDJSON line of a `--recursive` scan, which has no aggregate to
+/// hold [`Skipped`].
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct SkippedLine {
+    pub skipped: Skipped,
+}
+
+/// Counts summed over the binaries of a [`MultiReport`] that were scanned.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Aggregate {
+    /// Binaries given
+    pub binaries: usize,
+    /// Binaries whose scan failed, in no count below
+    pub failed: usize,
+    /// Binaries with `has_simd`
+    pub with_simd: usize,
+    pub isa_summary: IndexMap<String, usize>,
+    /// How many binaries use each ISA of `isa_summary`
+    pub binaries_per_isa: IndexMap<String, usize>,
+    pub total_simd_insts: usize,
+    pub total_insts: usize,
+    /// `total_simd_insts` as a percentage of `total_insts`, to two decimals
+    pub simd_percent: f64,
+    /// The highest `required_level` of any binary
+    pub required_level: u8,
+    /// Binaries failing a `--fail-if-*` check
+    pub policy_violations: usize,
+    /// Binaries `--target-cpu` / `--target-features` found incompatible
+    pub incompatible: usize,
+    /// With `--recursive`, the files under the directories that were not
+    /// scanned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<Skipped>,
+}
+
+/// Files `--recursive` passed over, by reason.
+#[derive(Serialize, Deserialize, JsonSchema, Default)]
+pub struct Skipped {
+    /// Not an ELF, Mach-O or PE file
+    pub not_binary: usize,
+    /// Failed to open or list, dangling symlinks included
+    pub unreadable: usize,
+    /// Dropped by `--include` / `--exclude`
+    pub excluded: usize,
+    /// Symlinks back into a directory being walked
+    pub symlink_loops: usize,
+}
+
+impl Skipped {
+    pub fn total(&self) -> usize {
+        self.not_binary + self.unreadable + self.excluded + self.symlink_loops
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct IsaShare {
+    pub count: usize,
+    pub pct_of_simd: f64,
+    pub pct_of_total: f64,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Default)]
+pub struct SectionCounts {
+    pub total_simd_insts: usize,
+    pub total_insts: usize,
+}
+
+/// Oldest microarchitectures able to run every classified instruction.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct MinCpu {
+    /// Intel microarchitecture, `none` if Intel never shipped a required
+    /// feature, or `unknown` if a feature isn't in simdscan's table
+    pub intel: String,
+    /// AMD microarchitecture, with the same `none` / `unknown` convention
+    pub amd: String,
+    /// CPUID features the decision was based on, sorted
+    pub features: Vec<String>,
+}
+
+impl MinCpu {
+    pub fn from_features(features: impl IntoIterator<Item = String>) -> Self {
+        let mut features: Vec<String> = features.into_iter().collect();
+        features.sort_unstable();
+        let names: Vec<&str> = features.iter().map(String::as_str).collect();
+        let (intel, amd) = isa::min_cpu(&names);
+        MinCpu {
+            intel,
+            amd,
+            features,
+        }
+    }
+}
+
+/// SIMD loads and stores by alignment class. Register-to-register moves are
+/// not counted.
+#[derive(Serialize, Deserialize, JsonSchema, Default)]
+pub struct MemoryOps {
+    pub aligned: usize,
+    pub unaligned: usize,
+    pub non_temporal: usize,
+    /// `aligned` as a percentage of aligned plus unaligned moves
+    pub aligned_percent: f64,
+    /// Per-mnemonic counts, present with `--show-insts`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mnemonics: Option<IndexMap<String, usize>>,
+}
+
+/// Lines the parser couldn't make sense of. Nonzero counts usually mean the
+/// disassembler's output format changed.
+#[derive(Serialize, Deserialize, JsonSchema, Default)]
+pub struct Diagnostics {
+    /// Non-blank lines inside a section that weren't instructions or symbol headers
+    pub skipped_lines: usize,
+    /// Bytes the disassembler printed as `(bad)`
+    pub bad_instructions: usize,
+    /// Instruction lines with no recognizable mnemonic
+    pub unmatched_mnemonics: usize,
+    /// Instructions dropped by `--ignore-mnemonic`
+    pub ignored_instructions: usize,
+}
+
+/// Number of instructions touching each vector register width. An instruction
+/// that mixes widths (`vcvtpd2ps %zmm1,%ymm0`) counts once, under the widest.
+#[derive(Serialize, Deserialize, JsonSchema, Default)]
+pub struct RegisterWidths {
+    pub xmm: usize,
+    pub ymm: usize,
+    pub zmm: usize,
+}
+
+/// AVX-512 predication: instructions under an opmask and the mask-register
+/// instructions themselves.
+#[derive(Serialize, Deserialize, JsonSchema, Default)]
+pub struct MaskUsage {
+    /// Instructions with an opmask decoration (`{%k1}`)
+    pub masked: usize,
+    /// Masked instructions that zero the inactive lanes (`{%k1}{z}`)
+    pub zero_masked: usize,
+    /// Mask-register instructions (`kmovw`, `kortestd`, ...) by mnemonic, most
+    /// frequent first
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub mask_ops: IndexMap<String, usize>,
+}
+
+impl MaskUsage {
+    pub fn is_empty(&self) -> bool {
+        self.masked == 0 && self.mask_ops.is_empty()
+    }
+}
+
+/// Mnemonic counts for an instruction group audited on its own.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct AttributedUsage {
+    /// Count per mnemonic, most frequent first
+    pub mnemonics: IndexMap<String, usize>,
+    /// Functions containing them with their counts, most first; present with
+    /// `--show-functions`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub functions: Option<IndexMap<String, usize>>,
+}
+
+/// AVX-512 instructions split into heavy (512-bit FP math, FMA, integer
+/// multiply), which drop Skylake-SP and Cascade Lake cores to their lowest
+/// AVX-512 frequency, and light (everything else, including any AVX-512
+/// instruction on xmm/ymm).
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Avx512Heaviness {
+    pub heavy: usize,
+    pub light: usize,
+    /// `heavy` as a percentage of all AVX-512 instructions
+    pub heavy_percent: f64,
+    /// Heavy instruction count per mnemonic, most frequent first
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub heavy_mnemonics: IndexMap<String, usize>,
+    /// Functions with the most heavy instructions, most first, ties broken by
+    /// name; as many as `--show-functions` asks for, else 10
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub top_functions: IndexMap<String, usize>,
+}
+
+/// The most capable kind of floating-point (or vector) code found.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FloatProfile {
+    /// No FP or vector instructions: integer-only, or soft-float
+    None,
+    /// x87 is the only FP unit used, as in `-mfpmath=387` or i386 builds
+    X87,
+    /// Scalar SSE/AVX arithmetic (`addss`, `vmulsd`) but nothing packed
+    SseScalar,
+    /// Packed floating-point or integer vector instructions
+    Simd,
+}
+
+impl FloatProfile {
+    pub fn from_counts(x87: usize, scalar_fp: usize, packed: usize) -> Self {
+        if packed > 0 {
+            FloatProfile::Simd
+        } else if scalar_fp > 0 {
+            FloatProfile::SseScalar
+        } else if x87 > 0 {
+            FloatProfile::X87
+        } else {
+            FloatProfile::None
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FloatProfile::None => "none",
+            FloatProfile::X87 => "x87",
+            FloatProfile::SseScalar => "sse-scalar",
+            FloatProfile::Simd => "simd",
+        }
+    }
+}
+
+/// x87 instruction counts, by mnemonic without its AT&T size suffix.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct X87Usage {
+    /// Count per mnemonic, most frequent first
+    pub mnemonics: IndexMap<String, usize>,
+    /// Functions containing them with their counts, most first; present with
+    /// `--show-functions`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub functions: Option<IndexMap<String, usize>>,
+    /// Mnemonics found that a SIMD ISA also claims, with its key (`fisttp` is
+    /// SSE3), sorted. They are counted here and under that ISA.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub also_simd: IndexMap<String, String>,
+}
+
+/// EVEX operand decorations other than masking. Embedded broadcasts in
+/// particular are rare in hand-written code and common in vectorized loops.
+#[derive(Serialize, Deserialize, JsonSchema, Default)]
+pub struct EvexOperands {
+    /// Memory operands broadcast from one element (`(%rax){1to16}`, Intel
+    /// `DWORD BCST [rax]`)
+    pub broadcast: usize,
+    /// Static rounding overrides (`{rn-sae}`, `{rz-sae}`, ...)
+    pub rounding: usize,
+    /// Exception suppression without a rounding override (`{sae}`)
+    pub sae: usize,
+}
+
+impl EvexOperands {
+    pub fn is_empty(&self) -> bool {
+        self.broadcast == 0 && self.rounding == 0 && self.sae == 0
+    }
+}
+
+impl RegisterWidths {
+    /// Counts one instruction whose widest register has prefix letter `widest`
+    /// (`b'x'`, `b'y'` or `b'z'`).
+    pub fn record(&mut self, widest: u8) {
+        match widest {
+            b'z' => self.zmm += 1,
+            b'y' => self.ymm += 1,
+            _ => self.xmm += 1,
+        }
+    }
+
+    pub fn merge(&mut self, other: &RegisterWidths) {
+        self.xmm += other.xmm;
+        self.ymm += other.ymm;
+        self.zmm += other.zmm;
+    }
+
+    /// Widest register width with a nonzero count, in bits.
+    pub fn max_bits(&self) -> Option<u16> {
+        if self.zmm > 0 {
+            Some(512)
+        } else if self.ymm > 0 {
+            Some(256)
+        } else if self.xmm > 0 {
+            Some(128)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct IsaDetail {
+    /// Distinct mnemonics found for the ISA, however many `occurrences` shows
+    pub unique_mnemonics: usize,
+    /// The most frequent mnemonics (`--top`, default 10), most frequent first
+    pub occurrences: IndexMap<String, usize>,
+    /// Every occurrence of the ISA by category, not just the listed
+    /// mnemonics', most frequent first
+    #[serde(default)]
+    pub categories: IndexMap<String, usize>,
+    /// With `--show-addresses`: the first addresses of each listed mnemonic,
+    /// in disassembly order
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub addresses: IndexMap<String, Vec<InstAddress>>,
+}
+
+/// One instruction's location, for `--show-addresses`.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct InstAddress {
+    /// In hex, without `0x` or leading zeros, as in objdump's listing
+    pub address: String,
+    /// `symbol+0xoffset` from the enclosing symbol, with `--per-function`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct FunctionUsage {
+    /// Demangled symbol name, or `<unattributed>` for code before the first symbol
+    pub name: String,
+    /// Raw symbol name, when demangling changed it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mangled: Option<String>,
+    /// Start address in hex, in `functions` only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    pub total_simd_insts: usize,
+    /// The newest ISA the function uses (see `isa::requirement`), whatever
+    /// `--only-isa` / `--exclude-isa` keep; absent without SIMD
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requires: Option<String>,
+    /// Every decoded instruction in the function, SIMD or not
+    pub total_insts: usize,
+    /// `total_simd_insts` as a percentage of `total_insts`, to two decimals
+    pub simd_percent: f64,
+    /// Profile samples on the function's instructions, with `--profile` or
+    /// `--perf-data`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub samples: Option<usize>,
+    /// SIMD in and out of loop bodies, with `--loop-heuristic`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loops: Option<LoopSplit>,
+    pub isa_summary: IndexMap<String, usize>,
+}
+
+/// How `top_functions` and `functions` are listed.
+#[derive(Clone, Copy)]
+pub struct FunctionListing<'a> {
+    pub sort: FunctionSort,
+    /// ISAs to keep in each function's `isa_summary`; functions left without
+    /// SIMD are dropped like those that never had any
+    pub filter: &'a IsaFilter,
+    /// `--min-function-insts`
+    pub min_insts: usize,
+    /// `--functions-requiring`: keep only functions whose `requires` one of
+    /// these keys covers
+    pub requiring: &'a [String],
+    /// Fill in `samples`
+    pub samples: bool,
+    /// Fill in `loops`
+    pub loops: bool,
+}
+
+/// `--sort-functions`: the order of `top_functions` and `functions`.
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FunctionSort {
+    /// Highest `simd_percent` first, then most SIMD
+    Density,
+    /// Most SIMD instructions first
+    #[default]
+    SimdCount,
+    Name,
+    /// Newest `requires` first, then most SIMD
+    Requires,
+}
+
+impl FunctionSort {
+    /// Stable, so ties keep their current order.
+    fn sort(self, usage: &mut [FunctionUsage]) {
+        match self {
+            FunctionSort::Density => usage.sort_by(|a, b| {
+                b.simd_percent
+                    .total_cmp(&a.simd_percent)
+                    .then_with(|| b.total_simd_insts.cmp(&a.total_simd_insts))
+            }),
+            FunctionSort::SimdCount => {
+                usage.sort_by_key(|function| std::cmp::Reverse(function.total_simd_insts))
+            }
+            FunctionSort::Name => usage.sort_by(|a, b| a.name.cmp(&b.name)),
+            FunctionSort::Requires => usage.sort_by(|a, b| {
+                let order = |f: &FunctionUsage| {
+                    f.requires
+                        .as_deref()
+                        .map(|isa| (isa::rank(isa).map_or(0, |rank| rank + 1), isa.to_string()))
+                };
+                order(b)
+                    .cmp(&order(a))
+                    .then_with(|| b.total_simd_insts.cmp(&a.total_simd_insts))
+            }),
+        }
+    }
+}
+
+/// `--sort-by`: the order of `isa_summary` and everything keyed like it.
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IsaSort {
+    /// Alphabetical by key
+    #[default]
+    Name,
+    /// Most instructions first, ties by name
+    Count,
+    /// Oldest extension first: MMX, SSE, ..., AVX-512, AMX (see
+    /// `isa::ISA_RANKING`)
+    Level,
+}
+
+impl IsaSort {
+    /// Order `isa_summary` (already sorted by name).
+    pub fn sort(self, isa_summary: &mut IndexMap<String, usize>) {
+        match self {
+            IsaSort::Name => {}
+            IsaSort::Count => isa_summary.sort_by(|_, a, _, b| b.cmp(a)),
+            IsaSort::Level => isa_summary
+                .sort_by(|a, _, b, _| isa::generation_order(a).cmp(&isa::generation_order(b))),
+        }
+    }
+}
+
+/// `part` as a percentage of `whole`, rounded to two decimals; 0 when `whole` is 0.
+pub fn percent(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        return 0.0;
+    }
+    (part as f64 * 10_000.0 / whole as f64).round() / 100.0
+}
+
+/// `count` per KiB of `bytes`, to two decimals.
+pub fn per_kb(count: usize, bytes: u64) -> f64 {
+    if bytes == 0 {
+        return 0.0;
+    }
+    (count as f64 * 1024.0 * 100.0 / bytes as f64).round() / 100.0
+}
+
+/// `part / whole` rounded to four decimals; 0 when `whole` is 0.
+pub fn ratio(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        return 0.0;
+    }
+    (part as f64 * 10_000.0 / whole as f64).round() / 10_000.0
+}
+
+/// Express each ISA count as a share of SIMD and of all instructions.
+pub fn build_breakdown(
+    isa_summary: &IndexMap<String, usize>,
+    total_simd_insts: usize,
+    total_insts: usize,
+) -> IndexMap<String, IsaShare> {
+    isa_summary
+        .iter()
+        .map(|(isa, &count)| {
+            (
+                isa.clone(),
+                IsaShare {
+                    count,
+                    pct_of_simd: percent(count, total_simd_insts),
+                    pct_of_total: percent(count, total_insts),
+                },
+            )
+        })
+        .collect()
+}
+
+/// The `weighted_counts` section for `profile`.
+pub fn build_weighted(profile: &Profile, weighted: Weighted) -> WeightedCounts {
+    let simd_samples = weighted.isa_counts.values().sum();
+    let avx512 = weighted
+        .isa_counts
+        .iter()
+        .filter(|(isa, _)| isa::is_avx512(isa) || *isa == "AVX-512")
+        .map(|(_, count)| count)
+        .sum();
+    WeightedCounts {
+        profile: profile.source.clone(),
+        load_address: profile.load_address.map(|address| format!("{address:x}")),
+        total_samples: profile.total,
+        matched_samples: weighted.insts,
+        simd_samples,
+        simd_percent: percent(simd_samples, weighted.insts),
+        avx512_percent: percent(avx512, weighted.insts),
+        isa_breakdown: build_breakdown(&weighted.isa_counts, simd_samples, weighted.insts),
+        isa_summary: weighted.isa_counts,
+        categories: by_count(weighted.categories),
+    }
+}
+
+/// The `runtime_library` section for the `runtime` routine counts; `None`
+/// if no routine was found.
+pub fn build_runtime(
+    routines: IndexMap<String, FunctionCounts>,
+    mangled_names: &HashMap<String, String>,
+) -> Option<RuntimeLibrary> {
+    if routines.is_empty() {
+        return None;
+    }
+    let total_insts = routines.values().map(|counts| counts.total_insts).sum();
+    let mut isa_summary: IndexMap<String, usize> = IndexMap::new();
+    for (isa, count) in routines.values().flat_map(|counts| &counts.isa_counts) {
+        *isa_summary.entry(isa.clone()).or_insert(0) += count;
+    }
+    isa_summary.sort_keys();
+    let listing = FunctionListing {
+        sort: FunctionSort::SimdCount,
+        filter: &IsaFilter::default(),
+        min_insts: 0,
+        requiring: &[],
+        samples: false,
+        loops: false,
+    };
+    Some(RuntimeLibrary {
+        total_simd_insts: isa_summary.values().sum(),
+        total_insts,
+        isa_summary,
+        routines: build_functions(routines, false, mangled_names, listing),
+    })
+}
+
+/// The `dispatch` section: `groups` as found, plus the ISAs of `isa_summary`
+/// that only guarded variants use.
+pub fn build_dispatch(
+    groups: IndexMap<String, Vec<DispatchVariant>>,
+    isa_summary: &IndexMap<String, usize>,
+    baseline_level: u8,
+    assumed: bool,
+) -> Dispatch {
+    let mut guarded: HashMap<&str, usize> = HashMap::new();
+    for variant in groups.values().flatten().filter(|v| v.guarded) {
+        for (isa, count) in &variant.isa_summary {
+            *guarded.entry(isa.as_str()).or_insert(0) += count;
+        }
+    }
+    let guarded_isas = isa_summary
+        .iter()
+        .filter(|&(isa, count)| guarded.get(isa.as_str()) == Some(count))
+        .map(|(isa, _)| isa.clone())
+        .collect();
+    Dispatch {
+        functions: groups
+            .into_iter()
+            .map(|(function, variants)| DispatchGroup { function, variants })
+            .collect(),
+        guarded_isas,
+        baseline_level,
+        assumed,
+    }
+}
+
+/// Drop the ISAs with fewer than `min_count` instructions or less than
+/// `min_percent` of `total_insts` from `isa_summary`; `None` if none were.
+pub fn suppress(
+    isa_summary: &mut IndexMap<String, usize>,
+    total_insts: usize,
+    min_count: usize,
+    min_percent: f64,
+) -> Option<Suppressed> {
+    let mut isas = IndexMap::new();
+    isa_summary.retain(|isa, &mut count| {
+        let share = count as f64 * 100.0 / total_insts.max(1) as f64;
+        let keep = count >= min_count && share >= min_percent;
+        if !keep {
+            isas.insert(isa.clone(), count);
+        }
+        keep
+    });
+    (!isas.is_empty()).then(|| Suppressed {
+        total: isas.values().sum(),
+        isas,
+    })
+}
+
+/// Build the per-ISA mnemonic breakdown, keeping the `limit` most frequent
+/// mnemonics and up to `address_limit` of their `addresses`. ISAs are sorted
+/// by name; mnemonics by count, then name.
+pub fn build_details(
+    inst_detail: HashMap<String, HashMap<String, usize>>,
+    mut inst_addresses: HashMap<String, HashMap<String, Vec<InstAddress>>>,
+    limit: usize,
+    address_limit: usize,
+) -> IndexMap<String, IsaDetail> {
+    let mut details = IndexMap::new();
+    for (isa, detail_map) in inst_detail {
+        let mut samples = inst_addresses.remove(&isa).unwrap_or_default();
+        let mut addresses = IndexMap::new();
+        let mut occurrences = IndexMap::new();
+        let mut categories = HashMap::new();
+        for (mnemonic, count) in &detail_map {
+            *categories
+                .entry(isa::category(mnemonic).to_string())
+                .or_insert(0) += count;
+        }
+
+        // Sort by count (descending), break ties by name, and take the top `limit`
+        let unique_mnemonics = detail_map.len();
+        let mut sorted_pairs: Vec<_> = detail_map.into_iter().collect();
+        sorted_pairs.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        for (mnemonic, count) in sorted_pairs.into_iter().take(limit) {
+            if let Some(mut list) = samples.remove(&mnemonic) {
+                list.truncate(address_limit);
+                addresses.insert(mnemonic.clone(), list);
+            }
+            occurrences.insert(mnemonic, count);
+        }
+
+        details.insert(
+            isa,
+            IsaDetail {
+                unique_mnemonics,
+                occurrences,
+                categories: by_count(categories),
+                addresses,
+            },
+        );
+    }
+    // Same key order as `isa_summary`, so serialized output never depends on hash order
+    details.sort_keys();
+    details
+}
+
+/// Order mnemonic counts most frequent first, ties broken by name.
+pub fn by_count(counts: HashMap<String, usize>) -> IndexMap<String, usize> {
+    let mut pairs: Vec<_> = counts.into_iter().collect();
+    pairs.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    pairs.into_iter().collect()
+}
+
+/// The report section for an audited instruction group; `None` when nothing
+/// in it was found.
+pub fn build_attributed(
+    mnemonics: HashMap<String, usize>,
+    functions: HashMap<String, usize>,
+    show_functions: bool,
+) -> Option<AttributedUsage> {
+    (!mnemonics.is_empty()).then(|| AttributedUsage {
+        mnemonics: by_count(mnemonics),
+        functions: show_functions.then(|| by_count(functions)),
+    })
+}
+
+/// The x87 report section, with the mnemonics `index` also classifies as
+/// SIMD noted; `None` when no x87 instruction was found.
+pub fn build_x87(
+    mnemonics: HashMap<String, usize>,
+    functions: HashMap<String, usize>,
+    show_functions: bool,
+    index: &isa::IsaIndex,
+) -> Option<X87Usage> {
+    let mut also_simd: IndexMap<String, String> = mnemonics
+        .keys()
+        .filter_map(|mnemonic| Some((mnemonic.clone(), isa::lookup(index, mnemonic)?.to_string())))
+        .collect();
+    also_simd.sort_keys();
+    let usage = build_attributed(mnemonics, functions, show_functions)?;
+    Some(X87Usage {
+        mnemonics: usage.mnemonics,
+        functions: usage.functions,
+        also_simd,
+    })
+}
+
+/// The `--avx512-heaviness` section, keeping the `limit` functions with the
+/// most heavy instructions.
+pub fn build_heaviness(
+    heavy_mnemonics: HashMap<String, usize>,
+    heavy_functions: HashMap<String, usize>,
+    light: usize,
+    limit: usize,
+) -> Avx512Heaviness {
+    let heavy = heavy_mnemonics.values().sum();
+    let mut top_functions = by_count(heavy_functions);
+    top_functions.truncate(limit);
+    Avx512Heaviness {
+        heavy,
+        light,
+        heavy_percent: percent(heavy, heavy + light),
+        heavy_mnemonics: by_count(heavy_mnemonics),
+        top_functions,
+    }
+}
+
+/// Keep the first `limit` functions with SIMD instructions in `listing`
+/// order, ties broken by name.
+pub fn build_top_functions(
+    functions: IndexMap<String, FunctionCounts>,
+    limit: usize,
+    mangled_names: &HashMap<String, String>,
+    listing: FunctionListing,
+) -> Vec<FunctionUsage> {
+    let mut usage = function_usage(functions, false, mangled_names, listing);
+    usage.sort_by(|a, b| a.name.cmp(&b.name));
+    listing.sort.sort(&mut usage);
+    usage.truncate(limit);
+    usage
+}
+
+/// The `aggregate` section of a multi-binary run.
+pub fn build_aggregate(entries: &[ReportEntry]) -> Aggregate {
+    let reports: Vec<&Report> = entries
+        .iter()
+        .filter_map(|entry| match entry {
+            ReportEntry::Report(report) => Some(report.as_ref()),
+            ReportEntry::Error(_) => None,
+        })
+        .collect();
+    let mut isa_summary: IndexMap<String, usize> = IndexMap::new();
+    let mut binaries_per_isa: IndexMap<String, usize> = IndexMap::new();
+    for report in &reports {
+        for (isa, count) in &report.isa_summary {
+            *isa_summary.entry(isa.clone()).or_insert(0) += count;
+            *binaries_per_isa.entry(isa.clone()).or_insert(0) += 1;
+        }
+    }
+    isa_summary.sort_keys();
+    binaries_per_isa.sort_keys();
+    let total_simd_insts = reports.iter().map(|r| r.total_simd_insts).sum();
+    let total_insts = reports.iter().map(|r| r.total_insts).sum();
+    Aggregate {
+        binaries: entries.len(),
+        failed: entries.len() - reports.len(),
+        with_simd: reports.iter().filter(|r| r.has_simd).count(),
+        isa_summary,
+        binaries_per_isa,
+        total_simd_insts,
+        total_insts,
+        simd_percent: percent(total_simd_insts, total_insts),
+        required_level: reports.iter().map(|r| r.required_level).max().unwrap_or(1),
+        policy_violations: reports.iter().filter(|r| r.violates_policy()).count(),
+        incompatible: reports.iter().filter(|r| r.incompatible()).count(),
+        skipped: None,
+    }
+}
+
+/// How many functions with SIMD require each ISA (see
+/// `isa::requirement`), oldest ISA first.
+pub fn build_requirements(functions: &IndexMap<String, FunctionCounts>) -> IndexMap<String, usize> {
+    let mut counts: IndexMap<String, usize> = IndexMap::new();
+    for function in functions.values() {
+        if let Some(isa) = isa::requirement(function.isa_counts.keys().map(String::as_str)) {
+            *counts.entry(isa.to_string()).or_insert(0) += 1;
+        }
+    }
+    counts.sort_by(|a, _, b, _| isa::generation_order(a).cmp(&isa::generation_order(b)));
+    counts
+}
+
+/// Every function for `--per-function` in `listing` order, ties in
+/// disassembly order; functions without SIMD only when `include_empty`.
+pub fn build_functions(
+    functions: IndexMap<String, FunctionCounts>,
+    include_empty: bool,
+    mangled_names: &HashMap<String, String>,
+    listing: FunctionListing,
+) -> Vec<FunctionUsage> {
+    let mut usage = function_usage(functions, include_empty, mangled_names, listing);
+    listing.sort.sort(&mut usage);
+    usage
+}
+
+fn function_usage(
+    functions: IndexMap<String, FunctionCounts>,
+    include_empty: bool,
+    mangled_names: &HashMap<String, String>,
+    listing: FunctionListing,
+) -> Vec<FunctionUsage> {
+    functions
+        .into_iter()
+        .filter(|(_, counts)| include_empty || !counts.isa_counts.is_empty())
+        .filter(|(_, counts)| counts.total_insts >= listing.min_insts)
+        .filter_map(|(name, mut counts)| {
+            let mut total_simd_insts: usize = counts.isa_counts.values().sum();
+            let requires =
+                isa::requirement(counts.isa_counts.keys().map(String::as_str)).map(str::to_string);
+            if !listing.requiring.is_empty()
+                && !requires.as_deref().is_some_and(|requires| {
+                    listing
+                        .requiring
+                        .iter()
+                        .any(|key| isa::covers(key, requires))
+                })
+            {
+                return None;
+            }
+            listing.filter.retain(&mut counts.isa_counts);
+            if counts.isa_counts.is_empty() && !include_empty {
+                return None;
+            }
+            if listing.filter.recomputed_totals {
+                total_simd_insts = counts.isa_counts.values().sum();
+            }
+            Some(FunctionUsage {
+                mangled: mangled_names.get(&name).cloned(),
+                name,
+                address: counts.address,
+                total_simd_insts,
+                requires,
+                total_insts: counts.total_insts,
+                simd_percent: percent(total_simd_insts, counts.total_insts),
+                samples: listing.samples.then_some(counts.samples),
+                loops: listing.loops.then_some(counts.loops),
+                isa_summary: counts.isa_counts,
+            })
+        })
+        .collect()
+}
+
+/// Bucket per-mnemonic memory move counts by alignment class.
+pub fn build_memory_ops(moves: HashMap<String, usize>, with_mnemonics: bool) -> MemoryOps {
+    let mut ops = MemoryOps::default();
+    for (mnemonic, &count) in &moves {
+        match isa::memory_access(mnemonic) {
+            Some(MemoryAccess::Aligned) => ops.aligned += count,
+            Some(MemoryAccess::Unaligned) => ops.unaligned += count,
+            Some(MemoryAccess::NonTemporal) => ops.non_temporal += count,
+            None => {}
+        }
+    }
+    ops.aligned_percent = percent(ops.aligned, ops.aligned + ops.unaligned);
+    ops.mnemonics = with_mnemonics.then(|| by_count(moves));
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classify::tests::run;
+
+    fn details_json(text: &str, limit: usize) -> String {
+        let classification = run(text);
+        let details = build_details(
+            classification.inst_detail,
+            classification.inst_addresses,
+            limit,
+            0,
+        );
+        serde_json::to_string(&details).unwrap()
+    }
+
+    #[test]
+    fn details_are_byte_identical_across_runs() {
+        let text = include_str!("../testdata/kernels.objdump");
+        let first = details_json(text, 10);
+        assert_eq!(first, details_json(text, 10));
+
+        let details: IndexMap<String, IsaDetail> = serde_json::from_str(&first).unwrap();
+        let keys: Vec<&String> = details.keys().collect();
+        let mut sorted = keys.clone();
+        sorted.sort();
+        assert_eq!(keys, sorted);
+        // Equal counts fall back to name order
+        let sse: Vec<&String> = details["SSE"].occurrences.keys().collect();
+        assert_eq!(sse, ["pextrw", "addps", "movaps", "mulps", "subps"]);
+    }
+
+    #[test]
+    fn unique_mnemonics_counted_before_the_cutoff() {
+        let text = include_str!("../testdata/sse15.objdump");
+        let classification = run(text);
+        let details = build_details(
+            classification.inst_detail,
+            classification.inst_addresses,
+            3,
+            0,
+        );
+        let sse = &details["SSE"];
+        assert_eq!(sse.unique_mnemonics, 15);
+        let top: Vec<_> = sse
+            .occurrences
+            .iter()
+            .map(|(m, &n)| (m.as_str(), n))
+            .collect();
+        assert_eq!(top, [("addps", 5), ("subps", 4), ("mulps", 3)]);
+
+        // `--top all`
+        let classification = run(text);
+        let details = build_details(
+            classification.inst_detail,
+            classification.inst_addresses,
+            usize::MAX,
+            0,
+        );
+        assert_eq!(details["SSE"].unique_mnemonics, 15);
+        assert_eq!(details["SSE"].occurrences.len(), 15);
+    }
+
+    #[test]
+    fn documents_read_back_as_their_own_variant() {
+        let text = include_str!("../testdata/report.json");
+        let document: Document = serde_json::from_str(text).unwrap();
+        assert!(matches!(document, Document::Report(_)));
+
+        let line = crate::output::ndjson::error_line("a.out", "not an ELF file").unwrap();
+        let document: Document = serde_json::from_str(&line).unwrap();
+        assert!(matches!(document, Document::Error(_)));
+
+        let line = crate::output::ndjson::skipped_line(Skipped::default()).unwrap();
+        let document: Document = serde_json::from_str(&line).unwrap();
+        assert!(matches!(document, Document::Skipped(_)));
+
+        let reports = vec![
+            ReportEntry::Report(serde_json::from_str(text).unwrap()),
+            ReportEntry::Error(ScanError {
+                binary: "a.out".to_string(),
+                error: "not an ELF file".to_string(),
+            }),
+        ];
+        let mut aggregate = build_aggregate(&reports);
+        aggregate.skipped = Some(Skipped {
+            not_binary: 2,
+            ..Skipped::default()
+        });
+        let multi = serde_json::to_string(&MultiReport {
+            schema_version: SCHEMA_VERSION,
+            reports,
+            aggregate,
+        })
+        .unwrap();
+        let document: Document = serde_json::from_str(&multi).unwrap();
+        let Document::Multi(read) = &document else {
+            panic!("not read back as a MultiReport");
+        };
+        assert!(matches!(
+            read.reports[..],
+            [ReportEntry::Report(_), ReportEntry::Error(_)]
+        ));
+        assert_eq!(serde_json::to_string(&document).unwrap(), multi);
+    }
+}
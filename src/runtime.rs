@@ -0,0 +1,41 @@
+//! Runtime library routines: the SIMD string, memory and math code glibc,
+//! libmvec and compiler runtimes link into a binary, counted apart from the
+//! binary's own code (see `runtime_library` in the report).
+//!
+//! A symbol is a runtime routine when its mangled or demangled name matches
+//! one of [`BUILTIN`] or a `runtime_routines` entry of the ISA table file.
+//! Most of them are IFUNC-selected variants, so their ISAs say nothing about
+//! what the binary needs to run.
+
+use anyhow::{Context, Result};
+use regex::{Regex, RegexSet};
+
+/// Built-in patterns, matched against raw symbol names.
+pub const BUILTIN: &[&str] = &[
+    // glibc's string and memory variants: `__memmove_avx_unaligned_erms`,
+    // `__strlen_evex`, `__memset_chk_avx512_no_vzeroupper`, `__wcsnlen_sse4_1`
+    r"^__(?:mem|str|stp|wcs|wmem|rawmem|raw)\w*_(?:sse2|ssse3|sse4_1|sse4_2|sse42|avx|avx2|avx512[a-z]*|evex\d*)(?:_\w+)?$",
+    // libm's FMA/AVX2 builds of its scalar kernels: `__sin_fma`,
+    // `__ieee754_exp_avx2`, `__atan_sse2`
+    r"^__(?:ieee754_)?(?:a?sin|a?cos|a?tan|atan2|sincos|exp(?:2|10)?|log(?:2|10)?|pow|mp\w+|dubsin|docos|dosincos|slow\w+|branred|halfulp)f?_(?:sse2|sse41|avx|avx2|fma|fma4)$",
+    // libmvec vector math: `_ZGVdN4v_sin`, `_ZGVeN16vv_powf`
+    r"^_ZGV[bcdeBCDE][NM]\d+\w*_\w+$",
+    // Intel compiler runtime and SVML: `__intel_avx_rep_memcpy`,
+    // `_intel_fast_memset`, `__svml_sin4_l9`
+    r"^_?_intel_\w+$",
+    r"^__svml_\w+$",
+];
+
+/// [`BUILTIN`] plus `extra`, compiled into one set so each symbol is matched
+/// in a single pass. An invalid `extra` pattern is an error naming `source`.
+pub fn patterns(extra: &[String], source: &str) -> Result<RegexSet> {
+    for pattern in extra {
+        Regex::new(pattern)
+            .with_context(|| format!("Invalid runtime routine pattern in {source}"))?;
+    }
+    let patterns = BUILTIN
+        .iter()
+        .copied()
+        .chain(extra.iter().map(String::as_str));
+    Ok(RegexSet::new(patterns)?)
+}
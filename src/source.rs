@@ -0,0 +1,123 @@
+//! `--by-source`: SIMD counts per source file, from the DWARF line table.
+//!
+//! Addresses go through `addr2line` in one batch. Like `objdump -l`, it
+//! reports the line-table entry for each address, so code inlined from a
+//! header is counted under that header, not under the function it was
+//! inlined into. Split debug info is found through `.gnu_debuglink` in the
+//! usual places (next to the binary, `.debug/`, `/usr/lib/debug`), or given
+//! directly with `--debug-file`.
+
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct SourceFiles {
+    /// File the line info was read from: the binary, or `--debug-file`
+    pub debug_info: String,
+    /// Files with SIMD instructions, most first
+    pub files: Vec<SourceFileUsage>,
+    /// SIMD instructions with no line info
+    pub unknown: usize,
+    /// Why `files` is empty when there was SIMD to attribute
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct SourceFileUsage {
+    /// Path as recorded in the debug info
+    pub file: String,
+    pub total_simd_insts: usize,
+    pub isa_summary: IndexMap<String, usize>,
+}
+
+/// Attribute each `(address, isa)` pair to a source file.
+pub fn attribute(
+    binary: &Path,
+    debug_file: Option<&Path>,
+    instructions: &[(u64, &str)],
+) -> Result<SourceFiles> {
+    let debug_info = debug_file.unwrap_or(binary);
+    if !debug_info.exists() {
+        anyhow::bail!("Debug file '{}' not found", debug_info.display());
+    }
+
+    let mut child = Command::new("addr2line")
+        .arg("-e")
+        .arg(debug_info)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("Failed to execute addr2line")?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let addresses: Vec<u64> = instructions.iter().map(|&(address, _)| address).collect();
+    // Written from another thread so a full stdout pipe can't deadlock us
+    let writer = std::thread::spawn(move || -> std::io::Result<()> {
+        for address in addresses {
+            writeln!(stdin, "{address:x}")?;
+        }
+        Ok(())
+    });
+
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let mut per_file: IndexMap<String, IndexMap<String, usize>> = IndexMap::new();
+    let mut unknown = 0;
+    let mut lines = BufReader::new(stdout).lines();
+    for &(_, isa) in instructions {
+        let line = lines
+            .next()
+            .transpose()
+            .context("Failed to read addr2line output")?
+            .unwrap_or_default();
+        // `file:line`, `file:line (discriminator N)`, or `??:0` / `??:?`
+        match line.rsplit_once(':').map(|(file, _)| file) {
+            Some(file) if file != "??" && !file.is_empty() => {
+                let counts = per_file.entry(file.to_string()).or_default();
+                *counts.entry(isa.to_string()).or_insert(0) += 1;
+            }
+            _ => unknown += 1,
+        }
+    }
+    writer
+        .join()
+        .expect("addr2line writer panicked")
+        .context("Failed to write to addr2line")?;
+    let status = child.wait().context("Failed to wait for addr2line")?;
+    if !status.success() {
+        anyhow::bail!("addr2line failed on '{}'", debug_info.display());
+    }
+
+    let mut files: Vec<SourceFileUsage> = per_file
+        .into_iter()
+        .map(|(file, mut isa_summary)| {
+            isa_summary.sort_keys();
+            SourceFileUsage {
+                file,
+                total_simd_insts: isa_summary.values().sum(),
+                isa_summary,
+            }
+        })
+        .collect();
+    files.sort_by(|a, b| {
+        b.total_simd_insts
+            .cmp(&a.total_simd_insts)
+            .then_with(|| a.file.cmp(&b.file))
+    });
+    let note = (files.is_empty() && unknown > 0).then(|| {
+        format!(
+            "'{}' has no line info; rebuild with -g, or pass the split debug file with --debug-file",
+            debug_info.display()
+        )
+    });
+    Ok(SourceFiles {
+        debug_info: debug_info.display().to_string(),
+        files,
+        unknown,
+        note,
+    })
+}
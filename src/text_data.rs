@@ -0,0 +1,573 @@
+//! Data embedded in executable sections: jump tables, constant pools and
+//! zero fill that objdump decodes as instructions anyway. Some of that
+//! garbage lands on SIMD mnemonics (a `0f 11` in a switch table reads as
+//! `movups`), so classification skips what this finds as `suspected-data`
+//! exclusions.
+//!
+//! A heuristic over each section in two passes. The first collects direct
+//! branch targets and *anchors*, addresses the code treats as data: the base
+//! of a `jmp *0x401168(,%rdi,8)` table, the target of a RIP-relative load,
+//! and `lea` targets in functions that jump through a register. The second
+//! walks each run of lines no fall-through reaches, from a `ret`, an indirect
+//! `jmp` or `...` zero fill to the next symbol, direct branch target or exit,
+//! and marks it up to its last nonsensical line, if it has one:
+//! - `(bad)`, `.byte`, `...` zero fill, or prefixes without an instruction
+//! - `es`/`ss` segment prefixes, meaningless in 64-bit code
+//! - I/O and privileged instructions (`in`, `out`, `hlt`, `cli`, ...)
+//! - zero bytes decoded as `add %al,(%rax)`, from the second on
+//! - the same store through a bare register three times in a row
+//!
+//! Marking starts at an anchor inside the run, else at its first line that
+//! isn't padding. Even without nonsense it covers the data a table base or
+//! load anchor is known to start: the table's entries, read from the raw
+//! bytes until one points outside the section, or as many bytes as the
+//! load's register holds. Table entries are branch targets too, so the case
+//! bodies after an in-text table end its run and stay counted.
+
+use crate::encoding;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+lazy_static! {
+    static ref SECTION_RE: Regex = Regex::new(r"^Disassembly of section (.+):$").unwrap();
+    static ref SYMBOL_RE: Regex = Regex::new(r"^[0-9a-fA-F]+ <.+>:$").unwrap();
+    // `jne 401154 <f+0x44>`, `call 0x401000` in Intel syntax
+    static ref DIRECT_RE: Regex = Regex::new(r"^(?:0x)?([0-9a-f]+)\b").unwrap();
+    // `jmp *0x401168(,%rdi,8)`, `jmp qword ptr [rdi*8+0x401168]`
+    static ref TABLE_RE: Regex = Regex::new(
+        r"^\*(?:0x)?([0-9a-f]+)\(,%?[a-z0-9]+,([248])\)|\[[a-z0-9]+\*([248])\+0x([0-9a-f]+)\]"
+    )
+    .unwrap();
+    // `movaps 0x2(%rip),%xmm0  # 4011a0 <consts+0x9>`
+    static ref RIP_RE: Regex = Regex::new(r"(?:\(%rip\)|\[rip[+-]).*#\s*([0-9a-f]+)\b").unwrap();
+    // `jmp *%rax`, `jmp rax`
+    static ref REGISTER_JUMP_RE: Regex = Regex::new(r"^\*?%?(?:[re][a-z0-9]{2}|r[0-9]{1,2}d?)$").unwrap();
+    // A store through a bare register, what short data mostly decodes to
+    static ref BARE_STORE_RE: Regex = Regex::new(r",\s*(?:\(%[a-z0-9]+\)|byte ptr \[[a-z0-9]+\])$|^(?:byte|[dq]?word) ptr \[[a-z0-9]+\],").unwrap();
+    static ref ZERO_RE: Regex =
+        Regex::new(r"^add\s+(?:%al,\(%[er]?ax\)|byte ptr \[[er]?ax\],\s*al)$").unwrap();
+}
+
+/// Prefixes objdump prints as separate words before the mnemonic.
+const PREFIXES: &[&str] = &[
+    "lock", "rep", "repz", "repe", "repnz", "repne", "bnd", "notrack", "data16", "data32",
+    "addr16", "addr32", "cs", "ds", "es", "ss", "fs", "gs", "xacquire", "xrelease",
+];
+
+/// Instructions no user-mode compiler output contains. `hlt` is left out:
+/// `_start` ends with one.
+const PRIVILEGED: &[&str] = &[
+    "in", "inb", "inw", "inl", "out", "outb", "outw", "outl", "insb", "insw", "insl", "insd",
+    "outsb", "outsw", "outsl", "outsd", "cli", "sti", "iret", "iretd", "iretq", "iretw", "lret",
+    "lretq", "lretl", "ljmp", "lcall", "into", "icebp", "int1",
+];
+
+/// One instruction line, as far as this module cares.
+struct Line<'a> {
+    index: usize,
+    address: u64,
+    /// Everything after the prefixes
+    insn: &'a str,
+    mnemonic: &'a str,
+    prefixes: Vec<&'a str>,
+}
+
+impl<'a> Line<'a> {
+    /// `text` (`  401007:\tjmp    *0x401010(,%rdi,8)`), lowercased.
+    fn parse(index: usize, text: &'a str) -> Option<Self> {
+        let (address, rest) = text.split_once(':')?;
+        let address = address.trim_start();
+        if !address.bytes().all(|b| b.is_ascii_hexdigit()) || !rest.starts_with(char::is_whitespace)
+        {
+            return None;
+        }
+        let address = u64::from_str_radix(address, 16).ok()?;
+        let mut insn = rest.trim();
+        let mut prefixes = Vec::new();
+        while let Some((word, rest)) = insn.split_once(char::is_whitespace) {
+            if !PREFIXES.contains(&word) && !word.starts_with("rex") {
+                break;
+            }
+            prefixes.push(word);
+            insn = rest.trim_start();
+        }
+        if PREFIXES.contains(&insn) || (insn.starts_with("rex") && !insn.contains(' ')) {
+            prefixes.push(insn);
+            insn = "";
+        }
+        let mnemonic = insn.split_whitespace().next().unwrap_or("");
+        Some(Line {
+            index,
+            address,
+            insn,
+            mnemonic,
+            prefixes,
+        })
+    }
+
+    fn operands(&self) -> &'a str {
+        self.insn[self.mnemonic.len()..].trim_start()
+    }
+
+    fn is_padding(&self) -> bool {
+        self.mnemonic.starts_with("nop")
+            || self.mnemonic == "int3"
+            || (self.mnemonic == "xchg" && ["%ax,%ax", "ax,ax"].contains(&self.operands()))
+    }
+
+    /// No fall-through: `jmp`, `ret` and `ud2`.
+    fn is_exit(&self) -> bool {
+        self.mnemonic.starts_with("jmp")
+            || self.mnemonic.starts_with("ret")
+            || self.mnemonic == "ud2"
+    }
+
+    /// An exit data may follow: `ret` or an indirect `jmp`. Code goes on
+    /// after the direct jumps of tail calls and loops, and after `ud2`.
+    fn may_precede_data(&self) -> bool {
+        self.mnemonic.starts_with("ret")
+            || (self.mnemonic.starts_with("jmp") && self.branch_target().is_none())
+    }
+
+    fn is_nonsense(&self) -> bool {
+        self.mnemonic.is_empty()
+            || self.mnemonic == "(bad)"
+            || self.mnemonic.starts_with('.')
+            || self.prefixes.iter().any(|p| *p == "es" || *p == "ss")
+            || PRIVILEGED.contains(&self.mnemonic)
+    }
+
+    /// Bytes of data a load touches at least, from its widest register.
+    fn access_size(&self) -> u64 {
+        let operands = self.operands();
+        if operands.contains("zmm") {
+            64
+        } else if operands.contains("ymm") {
+            32
+        } else if operands.contains("xmm") {
+            16
+        } else {
+            8
+        }
+    }
+
+    /// Where a direct branch or call goes.
+    fn branch_target(&self) -> Option<u64> {
+        let branch = self.mnemonic.starts_with('j')
+            || self.mnemonic.starts_with("call")
+            || self.mnemonic.starts_with("loop")
+            || self.mnemonic == "xbegin";
+        let captures = DIRECT_RE.captures(self.operands()).filter(|_| branch)?;
+        u64::from_str_radix(&captures[1], 16).ok()
+    }
+}
+
+/// One section's bytes as far as the listing shows them, for reading jump
+/// tables. `...` zero fill reads as zeros.
+#[derive(Default)]
+struct Image {
+    start: u64,
+    bytes: Vec<u8>,
+}
+
+impl Image {
+    fn push(&mut self, address: u64, bytes: &[u8]) {
+        if self.bytes.is_empty() {
+            self.start = address;
+        }
+        let offset = address
+            .checked_sub(self.start)
+            .and_then(|offset| usize::try_from(offset).ok());
+        if let Some(offset) = offset.filter(|&offset| offset >= self.bytes.len()) {
+            self.bytes.resize(offset, 0);
+            self.bytes.extend_from_slice(bytes);
+        }
+    }
+
+    fn contains(&self, address: u64) -> bool {
+        address
+            .checked_sub(self.start)
+            .is_some_and(|offset| offset < self.bytes.len() as u64)
+    }
+
+    /// The little-endian `size`-byte value at `address`.
+    fn read(&self, address: u64, size: usize) -> Option<u64> {
+        let offset = usize::try_from(address.checked_sub(self.start)?).ok()?;
+        let bytes = self.bytes.get(offset..offset.checked_add(size)?)?;
+        Some(
+            bytes
+                .iter()
+                .rev()
+                .fold(0, |value, &byte| value << 8 | u64::from(byte)),
+        )
+    }
+
+    /// The case addresses in the table at `base`, read until an entry points
+    /// outside the section or back into the table. `relative` entries are
+    /// 32-bit offsets from `base` (`lea`, `movslq`, `add`, `jmp *%rax`), the
+    /// rest `size`-byte addresses.
+    fn table(&self, base: u64, size: usize, relative: bool) -> Vec<u64> {
+        let mut entries = Vec::new();
+        loop {
+            let at = base + (entries.len() * size) as u64;
+            let Some(value) = self.read(at, size) else {
+                break;
+            };
+            let target = if relative {
+                base.wrapping_add_signed(i64::from(value as i32))
+            } else {
+                value
+            };
+            if !self.contains(target) || (base..at + size as u64).contains(&target) {
+                break;
+            }
+            entries.push(target);
+        }
+        entries
+    }
+}
+
+/// One line of a section, as the second pass walks them.
+enum Entry<'a> {
+    Symbol,
+    /// `...`, elided zero fill
+    Elided,
+    Insn(Line<'a>),
+}
+
+/// Indices into `lines` of the instruction lines that look like data.
+pub fn find(lines: &[encoding::Split]) -> HashSet<usize> {
+    let mut found = HashSet::new();
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut image = Image::default();
+    for (index, split) in lines.iter().enumerate() {
+        let Some(text) = &split.text else {
+            // The tail of the previous line's bytes
+            image.bytes.extend_from_slice(&split.bytes);
+            continue;
+        };
+        if text.ends_with(">:") && SYMBOL_RE.is_match(text) {
+            entries.push(Entry::Symbol);
+        } else if let Some(line) = Line::parse(index, text) {
+            if !split.bytes.is_empty() {
+                image.push(line.address, &split.bytes);
+            }
+            entries.push(Entry::Insn(line));
+        } else if text.trim() == "..." {
+            entries.push(Entry::Elided);
+        } else if SECTION_RE.is_match(text) {
+            scan_section(&entries, &image, &mut found);
+            entries.clear();
+            image = Image::default();
+        }
+    }
+    scan_section(&entries, &image, &mut found);
+    found
+}
+
+/// Both passes over one section's lines, raw bytes split off into `image`.
+fn scan_section(entries: &[Entry], image: &Image, found: &mut HashSet<usize>) {
+    let mut targets: BTreeSet<u64> = BTreeSet::new();
+    // Table bases and load targets, which mark their run as data outright,
+    // with how many bytes of data each is known to start
+    let mut anchors: BTreeMap<u64, u64> = BTreeMap::new();
+    let widen = |anchors: &mut BTreeMap<u64, u64>, at: u64, size: u64| {
+        let known = anchors.entry(at).or_default();
+        *known = (*known).max(size);
+    };
+    // Table bases with their entry size
+    let mut tables: Vec<(u64, usize)> = Vec::new();
+    // `lea` targets, kept when the function also jumps through a register
+    let mut weak_anchors: BTreeSet<u64> = BTreeSet::new();
+    let mut leas: Vec<u64> = Vec::new();
+    let mut register_jump = false;
+    let mut previous: Option<&Line> = None;
+    let flush_leas = |leas: &mut Vec<u64>, register_jump: bool, weak: &mut BTreeSet<u64>| {
+        if register_jump {
+            weak.extend(leas.iter());
+        }
+        leas.clear();
+    };
+    for entry in entries {
+        let line = match entry {
+            Entry::Symbol => {
+                flush_leas(&mut leas, register_jump, &mut weak_anchors);
+                register_jump = false;
+                continue;
+            }
+            Entry::Elided => continue,
+            Entry::Insn(line) => line,
+        };
+        // A zero displacement in an object file points at the next
+        // instruction, relocation unapplied
+        if let Some(previous) = previous.take().filter(|p| p.insn.contains("rip")) {
+            if let Some(target) = RIP_RE
+                .captures(previous.insn)
+                .and_then(|c| u64::from_str_radix(&c[1], 16).ok())
+                .filter(|&target| target != line.address)
+            {
+                if previous.mnemonic == "lea" {
+                    leas.push(target);
+                } else {
+                    widen(&mut anchors, target, previous.access_size());
+                }
+            }
+        }
+        if let Some(target) = line.branch_target() {
+            targets.insert(target);
+        }
+        if line.mnemonic.starts_with("jmp") {
+            let operands = line.operands();
+            if let Some(captures) = TABLE_RE.captures(operands) {
+                let base = captures.get(1).or(captures.get(4)).map(|m| m.as_str());
+                let size = captures.get(2).or(captures.get(3)).map(|m| m.as_str());
+                if let (Some(base), Some(size)) = (
+                    base.and_then(|b| u64::from_str_radix(b, 16).ok()),
+                    size.and_then(|s| s.parse().ok()),
+                ) {
+                    tables.push((base, size));
+                }
+            } else if REGISTER_JUMP_RE.is_match(operands) {
+                register_jump = true;
+            }
+        }
+        previous = Some(line);
+    }
+    flush_leas(&mut leas, register_jump, &mut weak_anchors);
+    for (base, size) in tables {
+        let entries = image.table(base, size, false);
+        // Without the raw bytes, the first entry at least is data
+        widen(&mut anchors, base, (entries.len().max(1) * size) as u64);
+        targets.extend(entries);
+    }
+    for &base in &weak_anchors {
+        targets.extend(image.table(base, 4, true));
+    }
+
+    // Only the lines inside a run are looked at
+    let mut run: Option<Run> = None;
+    let mut last_address: Option<u64> = None;
+    for entry in entries {
+        let line = match entry {
+            Entry::Symbol => {
+                if let Some(run) = run.take() {
+                    run.mark(found);
+                }
+                continue;
+            }
+            Entry::Elided => {
+                run.get_or_insert_with(Run::default).nonsense_through(None);
+                continue;
+            }
+            Entry::Insn(line) => line,
+        };
+        let from = last_address.map_or(line.address, |a| a + 1);
+        last_address = Some(line.address);
+        let Some(current) = &mut run else {
+            if line.may_precede_data() {
+                run = Some(Run::default());
+            }
+            continue;
+        };
+        // Anchors and targets since the previous line count for this one:
+        // garbage decoded from data can run over them
+        let span = (from <= line.address).then_some(from..=line.address);
+        if span
+            .clone()
+            .is_some_and(|span| targets.range(span).next().is_some())
+        {
+            if let Some(run) = run.take() {
+                run.mark(found);
+            }
+            if line.may_precede_data() {
+                run = Some(Run::default());
+            }
+            continue;
+        }
+        let extent = span
+            .clone()
+            .and_then(|span| anchors.range(span).map(|(at, size)| at + size).max());
+        let weak =
+            extent.is_some() || span.is_some_and(|span| weak_anchors.range(span).next().is_some());
+        current.push(line, weak, extent);
+        if line.is_exit() {
+            if let Some(run) = run.replace(Run::default()) {
+                run.mark(found);
+            }
+        }
+    }
+    if let Some(run) = run {
+        run.mark(found);
+    }
+}
+
+/// Lines after an exit, up to where code visibly resumes.
+#[derive(Default)]
+struct Run<'a> {
+    /// Index and address of each line
+    lines: Vec<(usize, u64)>,
+    /// Position in `lines` where data would start
+    start: Option<usize>,
+    /// Position in `lines` of the last nonsensical line
+    end: Option<usize>,
+    /// Where the data its table bases and load targets start ends, at least
+    extent: Option<u64>,
+    zero_adds: usize,
+    /// The last non-padding instruction and how many times in a row
+    repeat: (&'a str, usize),
+}
+
+impl<'a> Run<'a> {
+    fn push(&mut self, line: &Line<'a>, weak: bool, extent: Option<u64>) {
+        let position = self.lines.len();
+        self.lines.push((line.index, line.address));
+        // The first anchor before any nonsense says where the data begins
+        if weak && self.end.is_none() && self.extent.is_none() {
+            self.start = Some(position);
+        }
+        self.extent = self.extent.max(extent);
+        if line.is_padding() {
+            return;
+        }
+        self.start.get_or_insert(position);
+        if self.repeat.0 == line.insn && BARE_STORE_RE.is_match(line.operands()) {
+            self.repeat.1 += 1;
+        } else {
+            self.repeat = (line.insn, 1);
+        }
+        let zero_add = ZERO_RE.is_match(line.insn);
+        self.zero_adds += usize::from(zero_add);
+        if line.is_nonsense() || (zero_add && self.zero_adds >= 2) || self.repeat.1 >= 3 {
+            self.nonsense_through(Some(position));
+        }
+    }
+
+    /// Note nonsense at `position`, or (for `...`) after the last line.
+    fn nonsense_through(&mut self, position: Option<usize>) {
+        if self.lines.is_empty() {
+            return;
+        }
+        let position = position.unwrap_or(self.lines.len() - 1);
+        self.start.get_or_insert(position);
+        self.end = Some(position);
+    }
+
+    fn mark(self, found: &mut HashSet<usize>) {
+        let Some(start) = self.start else {
+            return;
+        };
+        // The last line starting inside the anchored data
+        let covered = self.extent.and_then(|extent| {
+            self.lines
+                .iter()
+                .rposition(|&(_, address)| address < extent)
+        });
+        let Some(end) = self.end.max(covered) else {
+            return;
+        };
+        let lines = self.lines.get(start..=end).into_iter().flatten();
+        found.extend(lines.map(|&(index, _)| index));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classify::tests::{mnemonics, run};
+
+    /// A non-PIE link with an absolute and a PIC switch table, a constant
+    /// pool, and a pool ahead of case bodies reached through a `.rodata`
+    /// table, all in `.text`
+    const SWITCH: &str = include_str!("../testdata/switch.objdump");
+    const SWITCH_INTEL: &str = include_str!("../testdata/switch_intel.objdump");
+
+    /// Addresses in `from..to` of the lines taken for data.
+    fn marked(text: &str, from: u64, to: u64) -> Vec<u64> {
+        let lines: Vec<String> = text.lines().map(String::from).collect();
+        let mut addresses: Vec<u64> = find(&encoding::split_lines(&lines))
+            .into_iter()
+            .filter_map(|index| {
+                let address = lines[index].split(':').next()?.trim();
+                u64::from_str_radix(address, 16).ok()
+            })
+            .filter(|address| (from..to).contains(address))
+            .collect();
+        addresses.sort_unstable();
+        addresses
+    }
+
+    #[test]
+    fn absolute_table_after_indirect_jump() {
+        // The last entry's final zero runs into the `0f` of the first case
+        // body's `addps`, so the line holding both is data too
+        assert_eq!(
+            marked(SWITCH, 0x401000, 0x401046),
+            [
+                0x401010, 0x401012, 0x401015, 0x401017, 0x401019, 0x40101c, 0x40101e, 0x401020,
+                0x401025, 0x401027, 0x40102a, 0x40102d, 0x40102f,
+            ]
+        );
+    }
+
+    #[test]
+    fn pic_table_after_register_jump() {
+        assert_eq!(
+            marked(SWITCH, 0x401046, 0x401087),
+            [0x40105c, 0x40105e, 0x401060, 0x401062, 0x401064, 0x401066, 0x401068]
+        );
+    }
+
+    #[test]
+    fn rip_relative_constant_pool() {
+        assert_eq!(
+            marked(SWITCH, 0x401087, 0x4010c0),
+            [
+                0x4010a0, 0x4010a2, 0x4010a5, 0x4010a7, 0x4010aa, 0x4010ab, 0x4010ae, 0x4010b2,
+                0x4010b4, 0x4010b6, 0x4010b8, 0x4010ba, 0x4010bc, 0x4010be,
+            ]
+        );
+    }
+
+    #[test]
+    fn anchored_run_without_nonsense_ends_with_its_data() {
+        // The 16 bytes loaded into %xmm1 decode without a nonsensical line;
+        // the case body after them, up to its `jmp`, is code
+        assert_eq!(
+            marked(SWITCH, 0x4010c1, 0x4010fc),
+            [
+                0x4010e0, 0x4010e2, 0x4010e4, 0x4010e6, 0x4010e8, 0x4010ea, 0x4010eb, 0x4010ed,
+                0x4010ee, 0x4010ef,
+            ]
+        );
+    }
+
+    #[test]
+    fn case_bodies_stay_counted() {
+        assert_eq!(
+            marked(SWITCH, 0, u64::MAX),
+            marked(SWITCH_INTEL, 0, u64::MAX)
+        );
+        for text in [SWITCH, SWITCH_INTEL] {
+            let classification = run(text);
+            // objdump itself decodes the first body after each in-text table
+            // out of step with its instructions, so that one's `addps`,
+            // `mulps`, `addpd` and `mulpd` never show up
+            assert_eq!(
+                mnemonics(&classification, "SSE"),
+                [
+                    ("addps", 2),
+                    ("divps", 1),
+                    ("maxps", 1),
+                    ("movaps", 2),
+                    ("mulps", 1),
+                    ("subps", 2),
+                ]
+            );
+            assert_eq!(
+                mnemonics(&classification, "SSE2"),
+                [("divpd", 1), ("maxpd", 1), ("subpd", 1)]
+            );
+        }
+    }
+}
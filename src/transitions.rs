@@ -0,0 +1,161 @@
+//! `--check-transitions`: per-function, in-order checks for code that mixes
+//! register states that must not mix without a reset in between.
+//!
+//! Instructions are followed in address order, not along control flow, so a
+//! `vzeroupper` on another branch still counts as clearing the state.
+
+use crate::classify;
+use crate::encoding::Encoding;
+use crate::isa;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TransitionKind {
+    /// Legacy-encoded SSE while the upper halves of the ymm/zmm registers are
+    /// dirty: a state transition stall on every affected core
+    SseAfterVex,
+    /// Returning with dirty upper halves, leaving the stall to the caller
+    RetAfterVex,
+    /// x87 code after MMX with no `emms`, which reads garbage off the stack
+    X87AfterMmx,
+}
+
+impl TransitionKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            TransitionKind::SseAfterVex => "sse-after-vex",
+            TransitionKind::RetAfterVex => "ret-after-vex",
+            TransitionKind::X87AfterMmx => "x87-after-mmx",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct TransitionWarning {
+    /// Demangled symbol name, as in `top_functions`
+    pub function: String,
+    /// Raw symbol name, when demangling changed it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mangled: Option<String>,
+    pub kind: TransitionKind,
+    /// Address of the first offending instruction, as objdump printed it
+    pub address: String,
+    pub mnemonic: String,
+}
+
+/// What one instruction means for the register state.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// VEX/EVEX on ymm or zmm registers. 128-bit VEX forms zero the upper
+    /// halves and leave them clean.
+    DirtyUpper,
+    /// `vzeroupper` or `vzeroall`
+    CleanUpper,
+    /// Legacy-encoded SSE on xmm registers
+    LegacySse,
+    Mmx,
+    Emms,
+    X87,
+    Return,
+}
+
+impl Step {
+    /// The step for an instruction, or `None` if it changes nothing we track.
+    /// `isa` is the key the instruction was classified under, if any.
+    pub fn of(
+        mnemonic: &str,
+        operands: &str,
+        encoding: Encoding,
+        widest: Option<u8>,
+        isa: Option<&str>,
+    ) -> Option<Step> {
+        match mnemonic {
+            "vzeroupper" | "vzeroall" => return Some(Step::CleanUpper),
+            "emms" => return Some(Step::Emms),
+            _ => {}
+        }
+        if is_return(mnemonic, operands) {
+            return Some(Step::Return);
+        }
+        if isa::is_x87(mnemonic) {
+            return Some(Step::X87);
+        }
+        // `pshufw` and `paddq` on `%mm` are SSE and SSE2, but still MMX state
+        if classify::on_mmx_registers(operands, widest) {
+            return Some(Step::Mmx);
+        }
+        match (isa?, encoding, widest) {
+            (_, Encoding::Vex | Encoding::Evex, Some(b'y' | b'z')) => Some(Step::DirtyUpper),
+            (_, Encoding::Legacy, Some(_)) => Some(Step::LegacySse),
+            _ => None,
+        }
+    }
+}
+
+/// `ret` in any of its spellings, including behind a `rep` or `bnd` prefix.
+fn is_return(mnemonic: &str, operands: &str) -> bool {
+    let is_ret = |m: &str| matches!(m, "ret" | "retq" | "retl" | "retw");
+    is_ret(mnemonic)
+        || (matches!(mnemonic, "rep" | "repz" | "bnd")
+            && operands.split_whitespace().next().is_some_and(is_ret))
+}
+
+/// Follows one function at a time, in the order `classify` sees the
+/// instructions, and reports the first offence of each kind per function.
+#[derive(Default)]
+pub struct Tracker {
+    function: String,
+    upper_dirty: bool,
+    mmx_live: bool,
+    reported: Vec<TransitionKind>,
+    warnings: Vec<TransitionWarning>,
+}
+
+impl Tracker {
+    pub fn step(&mut self, function: &str, address: &str, mnemonic: &str, step: Step) {
+        if function != self.function {
+            self.function = function.to_string();
+            self.upper_dirty = false;
+            self.mmx_live = false;
+            self.reported.clear();
+        }
+        let offence = match step {
+            Step::DirtyUpper => {
+                self.upper_dirty = true;
+                None
+            }
+            Step::CleanUpper => {
+                self.upper_dirty = false;
+                None
+            }
+            Step::Mmx => {
+                self.mmx_live = true;
+                None
+            }
+            Step::Emms => {
+                self.mmx_live = false;
+                None
+            }
+            Step::LegacySse => self.upper_dirty.then_some(TransitionKind::SseAfterVex),
+            Step::Return => self.upper_dirty.then_some(TransitionKind::RetAfterVex),
+            Step::X87 => self.mmx_live.then_some(TransitionKind::X87AfterMmx),
+        };
+        if let Some(kind) = offence.filter(|kind| !self.reported.contains(kind)) {
+            self.reported.push(kind);
+            self.warnings.push(TransitionWarning {
+                function: function.to_string(),
+                mangled: None,
+                kind,
+                address: address.to_string(),
+                mnemonic: mnemonic.to_string(),
+            });
+        }
+    }
+
+    /// Warnings in disassembly order.
+    pub fn finish(self) -> Vec<TransitionWarning> {
+        self.warnings
+    }
+}
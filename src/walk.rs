@@ -0,0 +1,255 @@
+//! `--recursive`: every ELF, Mach-O and PE file under the directories given.
+//!
+//! Candidates are recognised by their first bytes rather than by extension,
+//! since executables and versioned shared objects (`libfoo.so.1.2`) often
+//! have none. Symlinks are followed; a directory already being walked is a
+//! loop and is skipped, and a file or directory reached twice is taken once.
+//! Everything passed over is counted in `skipped` instead of reported.
+
+use crate::isa_table;
+use crate::report::Skipped;
+use goblin::Hint;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Which files under a directory to take.
+pub struct Filter<'a> {
+    /// Globs a file has to match, any one of them; all files when empty
+    pub include: &'a [String],
+    /// Globs that drop a file, or a whole directory
+    pub exclude: &'a [String],
+    /// How many levels below each directory to look; 1 is its own entries
+    pub max_depth: Option<usize>,
+}
+
+impl Filter<'_> {
+    fn matches(patterns: &[String], relative: &Path) -> bool {
+        let path = relative.to_string_lossy();
+        let name = relative
+            .file_name()
+            .map_or(path.clone(), |name| name.to_string_lossy());
+        patterns.iter().any(|pattern| {
+            // A pattern with a slash is for the path below the directory
+            // given, one without for the file name
+            let text = if pattern.contains('/') { &path } else { &name };
+            isa_table::glob_matches(pattern.as_bytes(), text.as_bytes())
+        })
+    }
+}
+
+struct Walker<'a> {
+    filter: &'a Filter<'a>,
+    files: Vec<PathBuf>,
+    seen: HashSet<PathBuf>,
+    skipped: Skipped,
+}
+
+/// The files to scan for `paths`: files as given, directories replaced by
+/// the binaries under them in name order.
+pub fn expand(paths: &[PathBuf], filter: &Filter) -> (Vec<PathBuf>, Skipped) {
+    let mut walker = Walker {
+        filter,
+        files: Vec::new(),
+        seen: HashSet::new(),
+        skipped: Skipped::default(),
+    };
+    for path in paths {
+        if path.is_dir() {
+            walker.walk(path, path, 1, &mut Vec::new());
+        } else {
+            // Named on the command line: scanned whatever it is, so a
+            // mistake shows up as an error entry
+            walker.files.push(path.clone());
+        }
+    }
+    (walker.files, walker.skipped)
+}
+
+impl Walker<'_> {
+    /// Take the binaries in `dir`, whose entries are `depth` levels below
+    /// `root`. `ancestors` are the canonical directories being walked.
+    fn walk(&mut self, root: &Path, dir: &Path, depth: usize, ancestors: &mut Vec<PathBuf>) {
+        let Ok(canonical) = dir.canonicalize() else {
+            self.skipped.unreadable += 1;
+            return;
+        };
+        if ancestors.contains(&canonical) {
+            self.skipped.symlink_loops += 1;
+            return;
+        }
+        if !self.seen.insert(canonical.clone()) {
+            return;
+        }
+        let Ok(entries) = fs::read_dir(dir) else {
+            self.skipped.unreadable += 1;
+            return;
+        };
+        let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+        paths.sort();
+        ancestors.push(canonical);
+        for path in paths {
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            // Follows symlinks; a dangling one fails like an unreadable file
+            let Ok(metadata) = fs::metadata(&path) else {
+                self.skipped.unreadable += 1;
+                continue;
+            };
+            if Filter::matches(self.filter.exclude, relative) {
+                self.skipped.excluded += 1;
+            } else if metadata.is_dir() {
+                if self.filter.max_depth.is_none_or(|max| depth < max) {
+                    self.walk(root, &path, depth + 1, ancestors);
+                }
+            } else if !self.filter.include.is_empty()
+                && !Filter::matches(self.filter.include, relative)
+            {
+                self.skipped.excluded += 1;
+            } else if !metadata.is_file() {
+                // FIFOs, sockets and devices, never opened
+                self.skipped.not_binary += 1;
+            } else {
+                self.file(path);
+            }
+        }
+        ancestors.pop();
+    }
+
+    fn file(&mut self, path: PathBuf) {
+        let Ok(canonical) = path.canonicalize() else {
+            self.skipped.unreadable += 1;
+            return;
+        };
+        if !self.seen.insert(canonical) {
+            return;
+        }
+        let mut magic = [0u8; 16];
+        let read = File::open(&path).and_then(|mut file| file.read_exact(&mut magic));
+        match read {
+            Ok(()) if is_binary(&magic) => self.files.push(path),
+            Err(err) if err.kind() != std::io::ErrorKind::UnexpectedEof => {
+                self.skipped.unreadable += 1;
+            }
+            _ => self.skipped.not_binary += 1,
+        }
+    }
+}
+
+/// Whether a file starting with `magic` is an ELF, Mach-O or PE file.
+fn is_binary(magic: &[u8; 16]) -> bool {
+    match goblin::peek_bytes(magic) {
+        Ok(Hint::Elf(_) | Hint::Mach(_) | Hint::PE) => true,
+        // Java class files share the fat Mach-O magic; their version sits
+        // where the architecture count would, and is 45 or more
+        Ok(Hint::MachFat(architectures)) => (1..20).contains(&architectures),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    /// A directory tree under the temp dir, removed when dropped.
+    struct Tree(PathBuf);
+
+    impl Tree {
+        /// `root/lib/libfoo.so.1` and `root/bin/tool` (ELF headers),
+        /// `root/README` (text), `root/lib/cycle` back to `root`, and
+        /// `root/gone` pointing nowhere.
+        fn new(name: &str) -> Self {
+            let root =
+                std::env::temp_dir().join(format!("simdscan-walk-{}-{name}", std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            let elf = *b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0";
+            fs::create_dir_all(root.join("lib")).unwrap();
+            fs::create_dir_all(root.join("bin")).unwrap();
+            fs::write(root.join("lib/libfoo.so.1"), elf).unwrap();
+            fs::write(root.join("bin/tool"), elf).unwrap();
+            fs::write(root.join("README"), "not a binary\n").unwrap();
+            symlink("..", root.join("lib/cycle")).unwrap();
+            symlink("missing", root.join("gone")).unwrap();
+            Tree(root)
+        }
+
+        fn expand(
+            &self,
+            include: &[&str],
+            exclude: &[&str],
+            max_depth: Option<usize>,
+        ) -> (Vec<String>, Skipped) {
+            let include: Vec<String> = include.iter().map(|glob| glob.to_string()).collect();
+            let exclude: Vec<String> = exclude.iter().map(|glob| glob.to_string()).collect();
+            let filter = Filter {
+                include: &include,
+                exclude: &exclude,
+                max_depth,
+            };
+            let (files, skipped) = expand(std::slice::from_ref(&self.0), &filter);
+            let files = files
+                .iter()
+                .map(|file| {
+                    file.strip_prefix(&self.0)
+                        .unwrap()
+                        .to_string_lossy()
+                        .to_string()
+                })
+                .collect();
+            (files, skipped)
+        }
+    }
+
+    impl Drop for Tree {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn counts(skipped: &Skipped) -> [usize; 4] {
+        [
+            skipped.not_binary,
+            skipped.unreadable,
+            skipped.excluded,
+            skipped.symlink_loops,
+        ]
+    }
+
+    #[test]
+    fn loops_and_dangling_links_are_skipped() {
+        let tree = Tree::new("links");
+        let (files, skipped) = tree.expand(&[], &[], None);
+        assert_eq!(files, ["bin/tool", "lib/libfoo.so.1"]);
+        assert_eq!(counts(&skipped), [1, 1, 0, 1]);
+    }
+
+    #[test]
+    fn max_depth_stops_the_walk() {
+        let tree = Tree::new("depth");
+        let (files, skipped) = tree.expand(&[], &[], Some(1));
+        assert!(files.is_empty());
+        // The loop sits a level further down
+        assert_eq!(counts(&skipped), [1, 1, 0, 0]);
+        let (files, _) = tree.expand(&[], &[], Some(2));
+        assert_eq!(files, ["bin/tool", "lib/libfoo.so.1"]);
+    }
+
+    #[test]
+    fn include_and_exclude_globs() {
+        let tree = Tree::new("globs");
+        let (files, skipped) = tree.expand(&["*.so*"], &[], None);
+        assert_eq!(files, ["lib/libfoo.so.1"]);
+        // `tool` and `README`
+        assert_eq!(counts(&skipped), [0, 1, 2, 1]);
+
+        // A whole directory counts once
+        let (files, skipped) = tree.expand(&[], &["lib"], None);
+        assert_eq!(files, ["bin/tool"]);
+        assert_eq!(counts(&skipped), [1, 1, 1, 0]);
+
+        // With a slash, the path below the directory given
+        let (files, _) = tree.expand(&["bin/*"], &[], None);
+        assert_eq!(files, ["bin/tool"]);
+    }
+}